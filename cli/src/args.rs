@@ -0,0 +1,230 @@
+use std::path::PathBuf;
+
+use eyre::{eyre, Result, WrapErr};
+
+pub const USAGE: &str = "\
+Usage: fearless-nes-cli <ROM> [OPTIONS]
+
+Runs a game without a display. FRAME is the number of frames run since the start,
+outputs without a FRAME are written at the end.
+
+Options:
+  --savestate <FILE>           Start from a savestate (.fnes)
+  --movie <FILE>               Play back a movie (.fnesmovie, .fm2 or .fnesinputs)
+  --frames <N>                 Run N frames (by default until the end of the movie)
+  --until <ADDR>=<VALUE>       Stop as soon as the byte at the CPU address ADDR is VALUE,
+                               fails if it doesn't happen within --frames
+  --screenshot [FRAME:]<FILE>  Save a screenshot (PNG)
+  --hash [FRAME]               Print the hash of the framebuffer
+  --dump-ram [FRAME:]<FILE>    Write the CPU RAM to a file
+  --wav <FILE>                 Record the audio of the whole run (WAV)
+  -h, --help                   Print this help
+
+Numbers can be hexadecimal with a '$' or '0x' prefix.
+";
+
+/// Something to write at a given frame (None is the end of the run)
+pub struct Output {
+    pub frame: Option<u64>,
+    pub kind: OutputKind,
+}
+
+pub enum OutputKind {
+    Screenshot(PathBuf),
+    Hash,
+    RamDump(PathBuf),
+}
+
+pub struct Args {
+    pub rom: PathBuf,
+    pub savestate: Option<PathBuf>,
+    pub movie: Option<PathBuf>,
+    pub frames: Option<u64>,
+    /// CPU address and the expected value
+    pub until: Option<(u16, u8)>,
+    pub outputs: Vec<Output>,
+    pub wav: Option<PathBuf>,
+}
+
+impl Args {
+    /// Returns None if the help should be printed
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Option<Self>> {
+        let mut args = args.peekable();
+
+        let mut rom = None;
+        let mut parsed = Args {
+            rom: PathBuf::new(),
+            savestate: None,
+            movie: None,
+            frames: None,
+            until: None,
+            outputs: Vec::new(),
+            wav: None,
+        };
+
+        while let Some(arg) = args.next() {
+            if arg == "--hash" {
+                // The frame is optional
+                let frame = args.next_if(|next| parse_number(next).is_ok());
+                parsed.outputs.push(Output {
+                    frame: frame.map(|f| parse_number(&f)).transpose()?,
+                    kind: OutputKind::Hash,
+                });
+
+                continue;
+            }
+
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| eyre!("missing value for '{}'", arg))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--savestate" => parsed.savestate = Some(PathBuf::from(value()?)),
+                "--movie" => parsed.movie = Some(PathBuf::from(value()?)),
+                "--frames" => parsed.frames = Some(parse_number(&value()?)?),
+                "--until" => {
+                    let value = value()?;
+                    let (addr, val) = value
+                        .split_once('=')
+                        .ok_or_else(|| eyre!("expected <ADDR>=<VALUE>, got '{}'", value))?;
+
+                    let addr =
+                        u16::try_from(parse_number(addr)?).wrap_err("address isn't 16-bit")?;
+                    let val = u8::try_from(parse_number(val)?).wrap_err("value isn't a byte")?;
+                    parsed.until = Some((addr, val));
+                }
+                "--screenshot" => {
+                    let (frame, path) = parse_frame_path(&value()?);
+                    parsed.outputs.push(Output {
+                        frame,
+                        kind: OutputKind::Screenshot(path),
+                    });
+                }
+                "--dump-ram" => {
+                    let (frame, path) = parse_frame_path(&value()?);
+                    parsed.outputs.push(Output {
+                        frame,
+                        kind: OutputKind::RamDump(path),
+                    });
+                }
+                "--wav" => parsed.wav = Some(PathBuf::from(value()?)),
+                _ if arg.starts_with('-') => return Err(eyre!("unknown option '{}'", arg)),
+                _ if rom.is_none() => rom = Some(PathBuf::from(arg)),
+                _ => return Err(eyre!("unexpected argument '{}'", arg)),
+            }
+        }
+
+        parsed.rom = rom.ok_or_else(|| eyre!("missing the ROM path"))?;
+        Ok(Some(parsed))
+    }
+}
+
+fn parse_number(s: &str) -> Result<u64> {
+    let hex = s.strip_prefix('$').or_else(|| s.strip_prefix("0x"));
+
+    match hex {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .wrap_err_with(|| format!("'{}' isn't a number", s))
+}
+
+/// "FRAME:FILE" or just "FILE"
+fn parse_frame_path(s: &str) -> (Option<u64>, PathBuf) {
+    if let Some((frame, path)) = s.split_once(':') {
+        if let Ok(frame) = parse_number(frame) {
+            return (Some(frame), PathBuf::from(path));
+        }
+    }
+
+    (None, PathBuf::from(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Args>> {
+        Args::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn hash_frame_is_optional() {
+        let args = parse("--hash game.nes --hash 60 --hash $10")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(args.rom, PathBuf::from("game.nes"));
+        let frames: Vec<_> = args.outputs.iter().map(|o| o.frame).collect();
+        assert_eq!(frames, [None, Some(60), Some(0x10)]);
+        assert!(args
+            .outputs
+            .iter()
+            .all(|o| matches!(o.kind, OutputKind::Hash)));
+    }
+
+    #[test]
+    fn frame_path_splitting() {
+        assert_eq!(
+            parse_frame_path("100:a.png"),
+            (Some(100), PathBuf::from("a.png"))
+        );
+        assert_eq!(
+            parse_frame_path("0x10:a.png"),
+            (Some(0x10), PathBuf::from("a.png"))
+        );
+        assert_eq!(parse_frame_path("a.png"), (None, PathBuf::from("a.png")));
+        // Not a frame, so it's a part of the path
+        assert_eq!(
+            parse_frame_path("C:a.png"),
+            (None, PathBuf::from("C:a.png"))
+        );
+
+        let args = parse("game.nes --screenshot 5:a.png --dump-ram ram.bin")
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            &args.outputs[0],
+            Output { frame: Some(5), kind: OutputKind::Screenshot(path) } if path == "a.png"
+        ));
+        assert!(matches!(
+            &args.outputs[1],
+            Output { frame: None, kind: OutputKind::RamDump(path) } if path == "ram.bin"
+        ));
+    }
+
+    #[test]
+    fn hex_numbers() {
+        assert_eq!(parse_number("123").unwrap(), 123);
+        assert_eq!(parse_number("$7F").unwrap(), 0x7F);
+        assert_eq!(parse_number("0xff").unwrap(), 0xFF);
+        assert!(parse_number("$").is_err());
+        assert!(parse_number("0xZZ").is_err());
+        assert!(parse_number("7F").is_err());
+    }
+
+    #[test]
+    fn until_ranges() {
+        let args = parse("game.nes --until $6000=0x80 --frames 600")
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.until, Some((0x6000, 0x80)));
+        assert_eq!(args.frames, Some(600));
+
+        assert!(parse("game.nes --until $FFFF=255").is_ok());
+        assert!(parse("game.nes --until $10000=0").is_err());
+        assert!(parse("game.nes --until $6000=256").is_err());
+        assert!(parse("game.nes --until $6000").is_err());
+        assert!(parse("game.nes --until").is_err());
+    }
+
+    #[test]
+    fn unknown_options_and_arguments() {
+        assert!(parse("game.nes --fast").is_err());
+        assert!(parse("game.nes other.nes").is_err());
+        assert!(parse("--hash").is_err());
+        assert!(parse("game.nes --help").unwrap().is_none());
+    }
+}
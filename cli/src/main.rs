@@ -0,0 +1,212 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    hash::Hasher,
+    io::{BufWriter, Read},
+    path::Path,
+    process::ExitCode,
+};
+
+use eyre::{eyre, Result, WrapErr};
+use siphasher::sip::SipHasher13;
+
+use fearless_nes::{
+    Fm2, Movie, MovieMode, MoviePlayer, Nes, ReplayInputs, NES_HEIGHT, NES_WIDTH, PALETTE,
+};
+
+mod args;
+
+use args::{Args, Output, OutputKind, USAGE};
+
+const SAMPLE_RATE: u32 = 44100;
+/// Name of the savestate inside the .fnes archives of the GUI
+const SAVESTATE_PATH: &str = "savestate.fnes";
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => {
+            eprintln!("the --until condition wasn't met");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("error: {:?}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns false if the --until condition wasn't met
+fn run(args: &Args) -> Result<bool> {
+    let rom = fs::read(&args.rom).wrap_err("couldn't read the ROM")?;
+
+    let (mut nes, mut player) = match (&args.movie, &args.savestate) {
+        (Some(_), Some(_)) => return Err(eyre!("movies already start from their own state")),
+        (Some(movie_path), None) => {
+            let movie = load_movie(movie_path, &rom)?;
+            let (player, nes) = MoviePlayer::start(movie, &rom, MovieMode::ReadOnly)?;
+            (nes, Some(player))
+        }
+        (None, Some(savestate_path)) => (load_savestate(savestate_path)?, None),
+        (None, None) => (Nes::new(&rom)?, None),
+    };
+
+    if args.frames.is_none() && player.is_none() {
+        return Err(eyre!("nothing to run, use --frames or --movie"));
+    }
+
+    let mut wav = match &args.wav {
+        Some(path) => {
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            Some(hound::WavWriter::create(path, spec).wrap_err("couldn't create the WAV file")?)
+        }
+        None => None,
+    };
+    nes.set_sample_rate(SAMPLE_RATE as f64);
+    let mut samples = Vec::new();
+
+    let start_frame = nes.frame_count();
+    let mut condition_met = args.until.is_none();
+
+    loop {
+        let frame = nes.frame_count() - start_frame;
+        write_outputs(&nes, &args.outputs, Some(frame))?;
+
+        if let Some((addr, val)) = args.until {
+            if nes.peek_cpu(addr) == val {
+                condition_met = true;
+                break;
+            }
+        }
+
+        let finished = match (args.frames, &player) {
+            (Some(frames), _) => frame >= frames,
+            (None, Some(player)) => player.is_finished(&nes),
+            (None, None) => unreachable!(),
+        };
+        if finished {
+            break;
+        }
+
+        match &mut player {
+            Some(player) => player.run_frame(&mut nes),
+            None => nes.run_frame(),
+        }
+
+        nes.apu_samples(&mut samples);
+        if let Some(wav) = &mut wav {
+            for s in samples.iter() {
+                wav.write_sample(*s)?;
+            }
+        }
+        samples.clear();
+    }
+
+    write_outputs(&nes, &args.outputs, None)?;
+
+    if let Some(wav) = wav {
+        wav.finalize()?;
+    }
+
+    Ok(condition_met)
+}
+
+/// The format is chosen by the extension
+fn load_movie(path: &Path, rom: &[u8]) -> Result<Movie> {
+    let data = fs::read(path).wrap_err("couldn't read the movie")?;
+
+    let movie = match path.extension().and_then(OsStr::to_str) {
+        Some("fm2") => {
+            let nes = Nes::new(rom)?;
+            Fm2::parse(&String::from_utf8_lossy(&data))?.into_movie(&nes)?
+        }
+        Some("fnesinputs") => {
+            let nes = Nes::new(rom)?;
+            let mut movie = Movie::from_power_on(&nes);
+            movie.inputs = ReplayInputs::load_state(&data)?;
+            movie
+        }
+        _ => Movie::load(&data)?,
+    };
+
+    Ok(movie)
+}
+
+/// Loads both raw savestates and the zipped saves of the GUI
+fn load_savestate(path: &Path) -> Result<Nes> {
+    let file = File::open(path).wrap_err("couldn't open the savestate")?;
+
+    let savestate = match zip::ZipArchive::new(&file) {
+        Ok(mut archive) => {
+            let mut savestate = Vec::new();
+            archive
+                .by_name(SAVESTATE_PATH)?
+                .read_to_end(&mut savestate)?;
+            savestate
+        }
+        Err(_) => fs::read(path)?,
+    };
+
+    Ok(Nes::load_state(&savestate)?)
+}
+
+fn write_outputs(nes: &Nes, outputs: &[Output], frame: Option<u64>) -> Result<()> {
+    for output in outputs.iter().filter(|o| o.frame == frame) {
+        let frame_name = match frame {
+            Some(frame) => frame.to_string(),
+            None => "end".to_string(),
+        };
+
+        match &output.kind {
+            OutputKind::Screenshot(path) => write_png(nes, path)?,
+            OutputKind::Hash => println!("frame {}: {}", frame_name, framebuffer_hash(nes)),
+            OutputKind::RamDump(path) => {
+                fs::write(path, nes.cpu_ram()).wrap_err("couldn't write the RAM dump")?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The same hash as the one used by the hash tests of the core
+fn framebuffer_hash(nes: &Nes) -> u64 {
+    let mut hasher = SipHasher13::new();
+    hasher.write(nes.frame_buffer());
+    hasher.finish()
+}
+
+fn write_png(nes: &Nes, path: &Path) -> Result<()> {
+    let mut rgb = Vec::with_capacity(NES_WIDTH * NES_HEIGHT * 3);
+    for &color in nes.frame_buffer().iter() {
+        let index = color as usize * 3;
+        rgb.extend_from_slice(&PALETTE[index..index + 3]);
+    }
+
+    let file = File::create(path).wrap_err("couldn't create the screenshot")?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), NES_WIDTH as u32, NES_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgb)?;
+
+    Ok(())
+}
@@ -10,8 +10,9 @@ use egui_glium::egui_winit::egui::{self, FontDefinitions, FontFamily};
 use eyre::Result;
 use gilrs::{Axis, EventType, Gilrs};
 
-use fearless_nes::{Button as NesButton, Nes};
+use fearless_nes::{Button as NesButton, ExpansionDevice, InputDevice, Nes};
 
+mod battery;
 mod config;
 mod debug;
 mod nesrender;
@@ -19,11 +20,12 @@ mod replays;
 mod saves;
 mod settings;
 
+use battery::BatterySave;
 pub use config::Config;
 use debug::Debug;
 use native_dialog::FileDialog;
 use nesrender::NesRender;
-pub use replays::{Recording, Replays};
+pub use replays::Replays;
 pub use saves::Saves;
 use settings::Settings;
 use winit::event::{ElementState, KeyboardInput};
@@ -44,10 +46,13 @@ pub struct App {
 
     nes: RuntimeNes,
     nes_channel: Option<Sender<NesMsg>>,
+    /// Movies need the ROM of the current game
+    rom_path: Option<PathBuf>,
 
     paused: bool,
 
     render: NesRender,
+    battery: BatterySave,
     saves: Saves,
     debug: Debug,
     replays: Replays,
@@ -61,10 +66,12 @@ impl App {
 
             nes: None,
             nes_channel: None,
+            rom_path: None,
 
             paused: false,
 
             render: NesRender::new(),
+            battery: BatterySave::new(),
             saves: Saves::new()?,
             debug: Debug::new(),
             replays: Replays::new(),
@@ -74,19 +81,42 @@ impl App {
 
     // TODO(high): refactor this... need to handle this in the core instead
     pub fn create_nes_with_file(&mut self, rom_path: PathBuf) -> Result<()> {
-        let rom = fs::read(rom_path).report_dialog_msg("Error while reading the ROM file")?;
-        let new_nes = Nes::new(&rom)
+        let rom = fs::read(&rom_path).report_dialog_msg("Error while reading the ROM file")?;
+        let mut new_nes = Nes::new(&rom)
             .report_dialog_with(|e| format!("Error while loading the ROM: {:?}", e))?;
 
+        if let Some(timing) = self.config.timing_override.timing_mode() {
+            new_nes.set_timing_mode(timing);
+        }
+
+        // The previous game has to be saved before the battery save is switched to the new one
+        self.flush_battery_save();
+
+        let sav_path = self.sav_path(&rom_path);
+        self.battery
+            .attach(&mut new_nes, sav_path)
+            .report_dialog_with(|e| format!("Couldn't load the battery save. Error: {}", e))
+            .ok();
+
+        self.replays.stop();
         self.replace_nes(new_nes);
+        self.rom_path = Some(rom_path);
 
         Ok(())
     }
 
-    pub fn replace_nes(&mut self, new_nes: Nes) {
+    pub fn replace_nes(&mut self, mut new_nes: Nes) {
         if let Some(nes) = &self.nes {
             let mut nes = nes.lock().unwrap();
+            new_nes.keep_channel_settings(&nes);
             *nes = new_nes;
+
+            // The movie has to know about the new state before the Nes thread runs the next frame
+            if let Some(player) = self.replays.player.lock().unwrap().as_mut() {
+                player.state_loaded(&nes);
+            }
+
+            self.send_nes_msg(NesMsg::ClearRewind);
         } else {
             let nes = Arc::new(Mutex::new(new_nes));
             let (send, recv) = crossbeam::channel::bounded::<NesMsg>(1024);
@@ -94,17 +124,63 @@ impl App {
             self.nes = Some(Arc::clone(&nes));
             self.nes_channel = Some(send);
 
+            let movie = Arc::clone(&self.replays.player);
+            let debugger = Arc::clone(&self.debug.debugger.nes_debugger);
             std::thread::spawn(move || {
-                nesthread::run_nes_thread(Arc::clone(&nes), recv);
+                nesthread::run_nes_thread(Arc::clone(&nes), movie, debugger, recv);
             });
         }
     }
 
-    #[rustfmt::skip]
+    fn send_nes_msg(&self, msg: NesMsg) {
+        if let Some(channel) = &self.nes_channel {
+            channel
+                .send_timeout(msg, CHANNEL_TIMEOUT)
+                .report_dialog()
+                .ok();
+        }
+    }
+
+    /// Pausing cancels a step of the debugger, resuming continues after a break
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+
+        {
+            let mut debugger = self.debug.debugger.nes_debugger.lock().unwrap();
+            match paused {
+                true => debugger.cancel_step(),
+                false => debugger.resume(),
+            }
+        }
+
+        let msg = match paused {
+            true => NesMsg::Pause,
+            false => NesMsg::Unpause,
+        };
+        self.send_nes_msg(msg);
+    }
+
+    fn sav_path(&self, rom_path: &Path) -> PathBuf {
+        BatterySave::sav_path(
+            rom_path,
+            self.config.battery_saves_next_to_rom,
+            &self.saves.folder_path,
+        )
+    }
+
+    pub fn flush_battery_save(&mut self) {
+        self.battery
+            .flush(&self.nes)
+            .report_dialog_with(|e| format!("Couldn't write the battery save. Error: {}", e))
+            .ok();
+    }
+
     pub fn handle_keyboard_input(&mut self, input: KeyboardInput) {
         let state = input.state == ElementState::Pressed;
 
-        if let SelectedButton::Keyboard(_) = self.settings.keybinds.selected_nesbtn {
+        if let SelectedButton::Keyboard(_) | SelectedButton::PowerPad(_) =
+            self.settings.keybinds.selected_nesbtn
+        {
             if let Some(keycode) = input.virtual_keycode {
                 if input.state == ElementState::Pressed {
                     self.settings.keybinds.input_btn = Some(InputButton::Keyboard(keycode));
@@ -114,64 +190,81 @@ impl App {
         }
 
         if let Some(keycode) = input.virtual_keycode {
-            if keycode == self.config.keybinds.up.kbd { self.set_button(NesButton::Up, state) }
-            if keycode == self.config.keybinds.right.kbd { self.set_button(NesButton::Right, state) }
-            if keycode == self.config.keybinds.down.kbd { self.set_button(NesButton::Down, state) }
-            if keycode == self.config.keybinds.left.kbd { self.set_button(NesButton::Left, state) }
-            if keycode == self.config.keybinds.start.kbd { self.set_button(NesButton::Start, state) }
-            if keycode == self.config.keybinds.select.kbd { self.set_button(NesButton::Select, state) }
-            if keycode == self.config.keybinds.b.kbd { self.set_button(NesButton::B, state) }
-            if keycode == self.config.keybinds.a.kbd { self.set_button(NesButton::A, state) }
+            if keycode == self.config.rewind_key {
+                self.send_nes_msg(NesMsg::Rewind(state));
+                return;
+            }
+
+            // The Power Pad keys take precedence over the controllers when the mat is plugged
+            let pad_button = self
+                .config
+                .power_pad_keys
+                .iter()
+                .position(|k| *k == keycode);
+            if let (Some(button), Some(nes)) = (pad_button, &self.nes) {
+                let mut nes = nes.lock().unwrap();
+
+                if nes.port_device(1) == InputDevice::PowerPad
+                    || nes.expansion_device() == ExpansionDevice::FamilyTrainer
+                {
+                    nes.set_power_pad_button(button, state);
+                    return;
+                }
+            }
+
+            for player in 0..self.config.player_keybinds.len() {
+                for button in NesButton::ALL {
+                    if keycode == self.config.player_keybinds[player][button].kbd {
+                        self.set_button(player, button, state);
+                    }
+                }
+            }
         }
     }
 
-    #[rustfmt::skip]
     pub fn handle_gamepad_input(&mut self, gilrs: &mut Gilrs) {
-        while let Some(gilrs::Event { event, .. }) = gilrs.next_event() {
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            if let EventType::ButtonPressed(b, ..) = event {
+                if let SelectedButton::Controller(_) = self.settings.keybinds.selected_nesbtn {
+                    self.settings.keybinds.input_btn = Some(InputButton::Gamepad(b));
+                    continue;
+                }
+            }
+
+            // Gamepads are assigned to the players in the order they were connected
+            let player = usize::from(id);
+            if player >= self.config.player_keybinds.len() {
+                continue;
+            }
+
             match event {
-                EventType::ButtonPressed(b, ..) => {
-                    if let SelectedButton::Controller(_) = self.settings.keybinds.selected_nesbtn {
-                        self.settings.keybinds.input_btn = Some(InputButton::Gamepad(b));
-                        continue;
-                    }
+                EventType::ButtonPressed(b, ..) | EventType::ButtonReleased(b, ..) => {
+                    let state = matches!(event, EventType::ButtonPressed(..));
 
-                    if b == self.config.keybinds.a.ctrl { self.set_button(NesButton::A, true) }
-                    if b == self.config.keybinds.b.ctrl { self.set_button(NesButton::B, true) }
-                    if b == self.config.keybinds.start.ctrl { self.set_button(NesButton::Start, true) }
-                    if b == self.config.keybinds.select.ctrl { self.set_button(NesButton::Select, true) }
-                    if b == self.config.keybinds.up.ctrl { self.set_button(NesButton::Up, true) }
-                    if b == self.config.keybinds.right.ctrl { self.set_button(NesButton::Right, true) }
-                    if b == self.config.keybinds.down.ctrl { self.set_button(NesButton::Down, true) }
-                    if b == self.config.keybinds.left.ctrl { self.set_button(NesButton::Left, true) }
-                }
-                EventType::ButtonReleased(b, ..) => {
-                    if b == self.config.keybinds.a.ctrl { self.set_button(NesButton::A, false) }
-                    if b == self.config.keybinds.b.ctrl { self.set_button(NesButton::B, false) }
-                    if b == self.config.keybinds.start.ctrl { self.set_button(NesButton::Start, false) }
-                    if b == self.config.keybinds.select.ctrl { self.set_button(NesButton::Select, false) }
-                    if b == self.config.keybinds.up.ctrl { self.set_button(NesButton::Up, false) }
-                    if b == self.config.keybinds.right.ctrl { self.set_button(NesButton::Right, false) }
-                    if b == self.config.keybinds.down.ctrl { self.set_button(NesButton::Down, false) }
-                    if b == self.config.keybinds.left.ctrl { self.set_button(NesButton::Left, false) }
+                    for button in NesButton::ALL {
+                        if b == self.config.player_keybinds[player][button].ctrl {
+                            self.set_button(player, button, state);
+                        }
+                    }
                 }
                 EventType::AxisChanged(Axis::LeftStickX, val, ..) => {
                     if val > 0.5 {
-                        self.set_button(NesButton::Right, true);
+                        self.set_button(player, NesButton::Right, true);
                     } else if val < -0.5 {
-                        self.set_button(NesButton::Left, true);
+                        self.set_button(player, NesButton::Left, true);
                     } else {
-                        self.set_button(NesButton::Right, false);
-                        self.set_button(NesButton::Left, false);
+                        self.set_button(player, NesButton::Right, false);
+                        self.set_button(player, NesButton::Left, false);
                     }
                 }
                 EventType::AxisChanged(Axis::LeftStickY, val, ..) => {
                     if val > 0.5 {
-                        self.set_button(NesButton::Up, true);
+                        self.set_button(player, NesButton::Up, true);
                     } else if val < -0.5 {
-                        self.set_button(NesButton::Down, true);
+                        self.set_button(player, NesButton::Down, true);
                     } else {
-                        self.set_button(NesButton::Up, false);
-                        self.set_button(NesButton::Down, false);
+                        self.set_button(player, NesButton::Up, false);
+                        self.set_button(player, NesButton::Down, false);
                     }
                 }
                 _ => (),
@@ -179,14 +272,14 @@ impl App {
         }
     }
 
-    fn set_button(&mut self, button: NesButton, state: bool) {
+    fn set_button(&mut self, player: usize, button: NesButton, state: bool) {
         if let Some(nes) = &mut self.nes {
             let mut nes = nes.lock().unwrap();
 
-            nes.set_button_state(button, state);
-
-            if let Recording::On { replay_inputs, .. } = &mut self.replays.recording {
-                replay_inputs.add_input_change(nes.frame_count(), button, state);
+            // The movie decides whether the input is recorded or ignored
+            match self.replays.player.lock().unwrap().as_mut() {
+                Some(movie) => movie.set_button_state(&mut nes, player, button, state),
+                None => nes.set_player_button_state(player, button, state),
             }
         };
     }
@@ -197,12 +290,20 @@ impl App {
         Debug::gui_window(self, egui_ctx);
         Settings::gui_window(self, egui_ctx);
 
+        self.battery
+            .flush_periodically(&self.nes)
+            .report_dialog_with(|e| format!("Couldn't write the battery save. Error: {}", e))
+            .ok();
+
         if let Some(nes) = &self.nes {
-            let nes = nes.lock().unwrap();
+            let mut nes = nes.lock().unwrap();
 
             let nes_framebuffer = nes.frame_buffer();
             self.render
                 .draw_nes(nes_framebuffer, egui_ctx, &self.config.overscan);
+
+            nes.set_zapper_state(self.render.mouse_pixel, self.render.mouse_down);
+            nes.set_vaus_state(self.render.paddle_position, self.render.mouse_down);
         }
     }
 
@@ -264,6 +365,8 @@ impl App {
                 });
 
                 if let Some(nes) = &app.nes {
+                    let mut toggle_pause = false;
+
                     egui::menu::menu_button(ui, "Controls", |ui| {
                         let button_text = match app.paused {
                             true => "Resume",
@@ -271,31 +374,27 @@ impl App {
                         };
 
                         if ui.button(button_text).clicked() {
-                            app.paused = !app.paused;
-                            if let Some(channel) = &app.nes_channel {
-                                let msg = match app.paused {
-                                    true => NesMsg::Pause,
-                                    false => NesMsg::Unpause,
-                                };
-
-                                channel
-                                    .send_timeout(msg, CHANNEL_TIMEOUT)
-                                    .report_dialog()
-                                    .ok();
-                            }
+                            toggle_pause = true;
                         }
 
-                        if ui.button("Reset").clicked() {
+                        // Resets aren't a part of the movies
+                        let reset =
+                            ui.add_enabled(!app.replays.is_active(), egui::Button::new("Reset"));
+                        if reset.clicked() {
                             let mut nes = nes.lock().unwrap();
                             nes.reset();
                         }
                     });
 
+                    if toggle_pause {
+                        app.set_paused(!app.paused);
+                    }
+
                     egui::menu::menu_button(ui, "Debug", |ui| {
                         Debug::gui_embed(app, ui);
                     });
 
-                    egui::menu::menu_button(ui, "Record inputs", |ui| {
+                    egui::menu::menu_button(ui, "Movie", |ui| {
                         Replays::gui_embed(app, ui);
                     });
 
@@ -0,0 +1,91 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use eyre::Result;
+use fearless_nes::Nes;
+
+use super::RuntimeNes;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Keeps the battery-backed RAM of the current game in sync with its .sav file
+pub struct BatterySave {
+    path: Option<PathBuf>,
+    /// Contents of the .sav file the last time it was written, used to skip redundant writes
+    last_written: Vec<u8>,
+    last_flush: Instant,
+}
+
+impl BatterySave {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            last_written: Vec::new(),
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub fn sav_path(rom_path: &Path, next_to_rom: bool, data_dir: &Path) -> PathBuf {
+        let sav_name = rom_path.with_extension("sav");
+
+        match (next_to_rom, sav_name.file_name()) {
+            (false, Some(file_name)) => data_dir.join(file_name),
+            _ => sav_name,
+        }
+    }
+
+    /// Loads the .sav file into a freshly created Nes and remembers the path for later flushes
+    pub fn attach(&mut self, nes: &mut Nes, sav_path: PathBuf) -> Result<()> {
+        self.path = None;
+        self.last_written.clear();
+
+        if nes.battery_ram().is_none() {
+            return Ok(());
+        }
+
+        if sav_path.exists() {
+            let save = fs::read(&sav_path)?;
+            nes.load_battery_ram(&save)?;
+            self.last_written = save;
+        }
+
+        self.path = Some(sav_path);
+        Ok(())
+    }
+
+    /// The .sav file isn't written anymore, until a game is attached again
+    pub fn detach(&mut self) {
+        self.path = None;
+        self.last_written.clear();
+    }
+
+    /// Writes the battery RAM to the .sav file if it has changed since the last write
+    pub fn flush(&mut self, nes: &RuntimeNes) -> Result<()> {
+        self.last_flush = Instant::now();
+
+        if let (Some(path), Some(nes)) = (&self.path, nes) {
+            let nes = nes.lock().unwrap();
+
+            if let Some(ram) = nes.battery_ram() {
+                if ram != self.last_written.as_slice() {
+                    fs::write(path, ram)?;
+                    self.last_written = ram.to_vec();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Games don't tell us when they are done saving, so flush every few seconds
+    pub fn flush_periodically(&mut self, nes: &RuntimeNes) -> Result<()> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush(nes)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
 use directories::ProjectDirs;
 use eyre::{eyre, Result};
-use fearless_nes::{NES_HEIGHT, NES_WIDTH};
+use fearless_nes::{TimingMode, NES_HEIGHT, NES_WIDTH};
 use serde::{Deserialize, Serialize};
+use winit::event::VirtualKeyCode;
 
 use std::{
     fs::{create_dir_all, File, OpenOptions},
@@ -18,6 +19,7 @@ mod keybinds;
 pub use keybinds::Keybinds;
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub window_width: u32,
     pub window_height: u32,
@@ -26,10 +28,21 @@ pub struct Config {
 
     pub dark_mode: bool,
 
+    /// Whether battery saves (.sav) are stored in the ROM folder instead of the data directory
+    pub battery_saves_next_to_rom: bool,
+
+    pub timing_override: TimingOverride,
+
+    /// Rewinds the game while held
+    pub rewind_key: VirtualKeyCode,
+
     /* TOML docs: "Note that the TOML format has a restriction that if a table itself contains tables,
     all keys with non-table values must be emitted first." */
     pub overscan: Overscan,
-    pub keybinds: Keybinds,
+    /// Keybinds of the players 1 - 4
+    pub player_keybinds: [Keybinds; 4],
+    /// Keys for the Power Pad buttons 1 - 12
+    pub power_pad_keys: [VirtualKeyCode; 12],
 }
 
 impl Default for Config {
@@ -42,8 +55,15 @@ impl Default for Config {
 
             dark_mode: true,
 
+            battery_saves_next_to_rom: false,
+
+            timing_override: TimingOverride::Auto,
+
+            rewind_key: VirtualKeyCode::Back,
+
             overscan: Overscan::new(),
-            keybinds: Keybinds::new(),
+            player_keybinds: [0, 1, 2, 3].map(Keybinds::new),
+            power_pad_keys: keybinds::POWER_PAD_KEYS,
         }
     }
 }
@@ -107,3 +127,39 @@ impl Config {
         Ok(())
     }
 }
+
+/// Lets the user force a timing mode instead of relying on the region from the ROM header
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TimingOverride {
+    Auto,
+    Ntsc,
+    Pal,
+    Dendy,
+}
+
+impl TimingOverride {
+    pub const ALL: [TimingOverride; 4] = [
+        TimingOverride::Auto,
+        TimingOverride::Ntsc,
+        TimingOverride::Pal,
+        TimingOverride::Dendy,
+    ];
+
+    pub fn timing_mode(self) -> Option<TimingMode> {
+        match self {
+            TimingOverride::Auto => None,
+            TimingOverride::Ntsc => Some(TimingMode::Ntsc),
+            TimingOverride::Pal => Some(TimingMode::Pal),
+            TimingOverride::Dendy => Some(TimingMode::Dendy),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimingOverride::Auto => "Auto (from the ROM)",
+            TimingOverride::Ntsc => "NTSC",
+            TimingOverride::Pal => "PAL",
+            TimingOverride::Dendy => "Dendy",
+        }
+    }
+}
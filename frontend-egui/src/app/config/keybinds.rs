@@ -6,6 +6,22 @@ use std::ops::{Index, IndexMut};
 // Need to use winit as a dependency because egui_winit doesnt compile winit with serde features
 use winit::event::VirtualKeyCode;
 
+/// The keys are laid out like the buttons on the mat
+pub const POWER_PAD_KEYS: [VirtualKeyCode; 12] = [
+    VirtualKeyCode::Q,
+    VirtualKeyCode::W,
+    VirtualKeyCode::E,
+    VirtualKeyCode::R,
+    VirtualKeyCode::A,
+    VirtualKeyCode::S,
+    VirtualKeyCode::D,
+    VirtualKeyCode::F,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::X,
+    VirtualKeyCode::C,
+    VirtualKeyCode::V,
+];
+
 #[derive(Serialize, Deserialize)]
 pub struct Keybinds {
     pub a: Keys,
@@ -19,16 +35,61 @@ pub struct Keybinds {
 }
 
 impl Keybinds {
-    pub fn new() -> Self {
+    /// The default keyboard bindings of each player don't overlap.
+    /// Gamepads are assigned to the players in the order they were connected.
+    pub fn new(player: usize) -> Self {
+        let [a, b, start, select, up, right, down, left] = match player {
+            0 => [
+                VirtualKeyCode::F,
+                VirtualKeyCode::D,
+                VirtualKeyCode::Return,
+                VirtualKeyCode::Space,
+                VirtualKeyCode::Up,
+                VirtualKeyCode::Right,
+                VirtualKeyCode::Down,
+                VirtualKeyCode::Left,
+            ],
+            1 => [
+                VirtualKeyCode::P,
+                VirtualKeyCode::O,
+                VirtualKeyCode::Key0,
+                VirtualKeyCode::Key9,
+                VirtualKeyCode::I,
+                VirtualKeyCode::L,
+                VirtualKeyCode::K,
+                VirtualKeyCode::J,
+            ],
+            2 => [
+                VirtualKeyCode::Numpad3,
+                VirtualKeyCode::Numpad1,
+                VirtualKeyCode::NumpadEnter,
+                VirtualKeyCode::NumpadAdd,
+                VirtualKeyCode::Numpad8,
+                VirtualKeyCode::Numpad6,
+                VirtualKeyCode::Numpad5,
+                VirtualKeyCode::Numpad4,
+            ],
+            _ => [
+                VirtualKeyCode::PageUp,
+                VirtualKeyCode::Insert,
+                VirtualKeyCode::Key2,
+                VirtualKeyCode::Key1,
+                VirtualKeyCode::Home,
+                VirtualKeyCode::PageDown,
+                VirtualKeyCode::End,
+                VirtualKeyCode::Delete,
+            ],
+        };
+
         Self {
-            a: Keys::new(GButton::East, VirtualKeyCode::F),
-            b: Keys::new(GButton::West, VirtualKeyCode::D),
-            start: Keys::new(GButton::Start, VirtualKeyCode::Return),
-            select: Keys::new(GButton::Select, VirtualKeyCode::Space),
-            up: Keys::new(GButton::DPadUp, VirtualKeyCode::Up),
-            right: Keys::new(GButton::DPadRight, VirtualKeyCode::Right),
-            down: Keys::new(GButton::DPadDown, VirtualKeyCode::Down),
-            left: Keys::new(GButton::DPadLeft, VirtualKeyCode::Left),
+            a: Keys::new(GButton::East, a),
+            b: Keys::new(GButton::West, b),
+            start: Keys::new(GButton::Start, start),
+            select: Keys::new(GButton::Select, select),
+            up: Keys::new(GButton::DPadUp, up),
+            right: Keys::new(GButton::DPadRight, right),
+            down: Keys::new(GButton::DPadDown, down),
+            left: Keys::new(GButton::DPadLeft, left),
         }
     }
 
@@ -1,9 +1,17 @@
+mod audio;
 mod cartridge_info;
+mod debugger;
+mod disassembly;
 mod events;
+mod memory;
 mod ppu;
 
+use audio::Audio;
 use cartridge_info::CartridgeInfo;
+use debugger::Debugger;
+use disassembly::Disassembly;
 use egui_glium::egui_winit::egui;
+use memory::Memory;
 use ppu::Ppu;
 
 use crate::App;
@@ -13,7 +21,11 @@ pub use self::events::Events;
 pub struct Debug {
     pub window_active: bool,
     pub cartridge_info: CartridgeInfo,
+    pub debugger: Debugger,
+    pub disassembly: Disassembly,
     pub ppu: Ppu,
+    pub memory: Memory,
+    pub audio: Audio,
     pub perf: Perf,
     pub events: Events,
 }
@@ -23,7 +35,11 @@ impl Debug {
         Self {
             window_active: false,
             cartridge_info: CartridgeInfo::new(),
+            debugger: Debugger::new(),
+            disassembly: Disassembly::new(),
             ppu: Ppu::new(),
+            memory: Memory::new(),
+            audio: Audio::new(),
             perf: Perf::new(),
             events: Events::new(),
         }
@@ -57,7 +73,11 @@ impl Debug {
             }
 
             CartridgeInfo::gui_window(app, egui_ctx);
+            Debugger::gui_window(app, egui_ctx);
+            Disassembly::gui_window(app, egui_ctx);
             Ppu::gui_window(app, egui_ctx);
+            Memory::gui_window(app, egui_ctx);
+            Audio::gui_window(app, egui_ctx);
             Perf::gui_window(app, egui_ctx);
             Events::gui_window(app, egui_ctx);
         }
@@ -76,10 +96,26 @@ impl Debug {
             app.debug.window_active = true;
         }
 
+        if ui.button("Debugger").clicked() {
+            app.debug.debugger.window_active = true;
+        }
+
+        if ui.button("Disassembly").clicked() {
+            app.debug.disassembly.window_active = true;
+        }
+
         if ui.button("PPU").clicked() {
             app.debug.ppu.window_active = true;
         }
 
+        if ui.button("Memory").clicked() {
+            app.debug.memory.window_active = true;
+        }
+
+        if ui.button("Audio").clicked() {
+            app.debug.audio.window_active = true;
+        }
+
         if ui.button("Performance").clicked() {
             app.debug.perf.window_active = true;
         }
@@ -0,0 +1,151 @@
+use egui_glium::egui_winit::egui::{self, Color32, Pos2, RichText, Sense, Stroke, Vec2};
+use fearless_nes::{ApuChannel, ApuChannelState, Nes};
+
+use super::App;
+
+/// Scope samples drawn, the rest is used to find a trigger point
+const SCOPE_SAMPLES: usize = 512;
+const SCOPE_SIZE: Vec2 = Vec2::new(512., 48.);
+
+const DUTY_NAMES: [&str; 4] = ["12.5%", "25%", "50%", "25% neg"];
+
+pub struct Audio {
+    pub window_active: bool,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        Self {
+            window_active: false,
+        }
+    }
+
+    pub fn gui_window(app: &mut App, egui_ctx: &egui::Context) {
+        let audio = &mut app.debug.audio;
+
+        if let Some(n) = &mut app.nes {
+            let mut n = n.lock().unwrap();
+
+            egui::Window::new("Audio")
+                .open(&mut audio.window_active)
+                .resizable(false)
+                .show(egui_ctx, |ui| {
+                    for (i, channel) in ApuChannel::ALL.into_iter().enumerate() {
+                        if i > 0 {
+                            ui.separator();
+                        }
+
+                        controls(ui, &mut n, channel);
+                        scope(ui, &n, channel);
+                        state_label(ui, &n.channel_state(channel));
+                    }
+                });
+        }
+    }
+}
+
+fn channel_color(channel: ApuChannel) -> Color32 {
+    match channel {
+        ApuChannel::Pulse1 => Color32::from_rgb(255, 90, 90),
+        ApuChannel::Pulse2 => Color32::from_rgb(255, 170, 60),
+        ApuChannel::Triangle => Color32::from_rgb(80, 200, 255),
+        ApuChannel::Noise => Color32::from_rgb(200, 200, 200),
+        ApuChannel::Dmc => Color32::from_rgb(120, 230, 120),
+    }
+}
+
+fn controls(ui: &mut egui::Ui, nes: &mut Nes, channel: ApuChannel) {
+    ui.horizontal(|ui| {
+        ui.label(
+            RichText::new(channel.to_string())
+                .strong()
+                .color(channel_color(channel)),
+        );
+
+        let mut enabled = nes.channel_enabled(channel);
+        if ui.checkbox(&mut enabled, "Enabled").changed() {
+            nes.set_channel_enabled(channel, enabled);
+        }
+
+        let mut solo = nes.channel_solo() == Some(channel);
+        if ui.checkbox(&mut solo, "Solo").changed() {
+            nes.set_channel_solo(solo.then_some(channel));
+        }
+
+        let mut volume = nes.channel_volume(channel);
+        if ui
+            .add(egui::Slider::new(&mut volume, 0.0..=1.0).text("Volume"))
+            .changed()
+        {
+            nes.set_channel_volume(channel, volume);
+        }
+    });
+}
+
+/// The channel output, starting from the first rising edge so periodic waves stand still
+fn scope(ui: &mut egui::Ui, nes: &Nes, channel: ApuChannel) {
+    let samples = nes.channel_scope(channel);
+    let max = match channel {
+        ApuChannel::Dmc => 127.,
+        _ => 15.,
+    };
+
+    let search_end = samples.len() - SCOPE_SAMPLES;
+    let trigger = (1..search_end)
+        .find(|&i| samples[i - 1] == 0 && samples[i] > 0)
+        .unwrap_or(search_end);
+
+    let (rect, _) = ui.allocate_exact_size(SCOPE_SIZE, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2., Color32::from_gray(20));
+
+    let step = rect.width() / SCOPE_SAMPLES as f32;
+    let points = samples[trigger..trigger + SCOPE_SAMPLES]
+        .iter()
+        .enumerate()
+        .map(|(i, &out)| {
+            let y = rect.bottom() - 2. - (out as f32 / max) * (rect.height() - 4.);
+            Pos2::new(rect.left() + i as f32 * step, y)
+        })
+        .collect();
+
+    painter.add(egui::Shape::line(
+        points,
+        Stroke::new(1.5, channel_color(channel)),
+    ));
+}
+
+fn state_label(ui: &mut egui::Ui, state: &ApuChannelState) {
+    let mut text = format!("Period {:>4}", state.period);
+
+    if let Some(frequency) = state.frequency {
+        let note = state.note().unwrap_or_else(|| "-".to_owned());
+        text += &format!("  {:<3} {:>8.1} Hz", note, frequency);
+    }
+
+    if let Some(duty) = state.duty {
+        text += &format!("  Duty {:<7}", DUTY_NAMES[duty as usize]);
+    }
+
+    if let Some(volume) = state.volume {
+        let kind = if state.constant_volume {
+            "const"
+        } else {
+            "env"
+        };
+        text += &format!("  Volume {:>2} ({})", volume, kind);
+    }
+
+    if let Some(length) = state.length_counter {
+        let halted = if state.length_counter_halted {
+            " halted"
+        } else {
+            ""
+        };
+        text += &format!("  Length {:>3}{}", length, halted);
+    }
+
+    text += &format!("  Output {:>3}", state.output);
+
+    ui.label(RichText::new(text).monospace());
+}
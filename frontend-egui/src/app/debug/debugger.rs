@@ -0,0 +1,360 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use egui_glium::egui_winit::egui::{self, RichText};
+use fearless_nes::{
+    Access, BreakReason, Breakpoint, CpuRegisters, Debugger as NesDebugger, TraceLogger,
+};
+
+use super::App;
+use crate::{
+    app::get_save_named_path,
+    dialog::{report_error, DialogReport},
+};
+
+pub struct Debugger {
+    pub window_active: bool,
+    /// Shared with the Nes thread, always locked after the Nes
+    pub nes_debugger: Arc<Mutex<NesDebugger>>,
+    form: BreakpointForm,
+    /// The file of the running trace log
+    trace_path: Option<PathBuf>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            window_active: false,
+            nes_debugger: Arc::new(Mutex::new(NesDebugger::new())),
+            form: BreakpointForm::new(),
+            trace_path: None,
+        }
+    }
+
+    fn start_trace(&mut self) {
+        let path = match get_save_named_path(None, "Trace log", "log") {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Ok(logger) = TraceLogger::to_file(&path)
+            .report_dialog_with(|e| format!("Couldn't create the trace log. Error: {}", e))
+        {
+            self.nes_debugger.lock().unwrap().start_trace(logger);
+            self.trace_path = Some(path);
+        }
+    }
+
+    fn stop_trace(&mut self) {
+        let _ = self
+            .nes_debugger
+            .lock()
+            .unwrap()
+            .stop_trace()
+            .report_dialog_with(|e| format!("Couldn't write the trace log. Error: {}", e));
+        self.trace_path = None;
+    }
+}
+
+/// The inputs for a new breakpoint
+struct BreakpointForm {
+    start: String,
+    end: String,
+    read: bool,
+    write: bool,
+    execute: bool,
+    condition: String,
+    error: Option<String>,
+}
+
+impl BreakpointForm {
+    fn new() -> Self {
+        Self {
+            start: String::new(),
+            end: String::new(),
+            read: false,
+            write: false,
+            execute: true,
+            condition: String::new(),
+            error: None,
+        }
+    }
+
+    fn parse(&self) -> Result<Breakpoint, String> {
+        let start = parse_addr(&self.start).ok_or("Invalid start address")?;
+        let end = match self.end.trim().is_empty() {
+            true => start,
+            false => parse_addr(&self.end).ok_or("Invalid end address")?,
+        };
+
+        if end < start {
+            return Err("The end address is lower than the start address".to_string());
+        }
+
+        if !(self.read || self.write || self.execute) {
+            return Err("Select at least one of R / W / X".to_string());
+        }
+
+        let condition = match self.condition.trim().is_empty() {
+            true => None,
+            false => Some(self.condition.parse().map_err(|e| format!("{}", e))?),
+        };
+
+        Ok(Breakpoint {
+            start,
+            end,
+            execute: self.execute,
+            read: self.read,
+            write: self.write,
+            condition,
+            enabled: true,
+        })
+    }
+}
+
+/// Addresses are hexadecimal, the '$' is optional
+fn parse_addr(s: &str) -> Option<u16> {
+    let s = s.trim();
+    u16::from_str_radix(s.strip_prefix('$').unwrap_or(s), 16).ok()
+}
+
+impl Debugger {
+    pub fn gui_window(app: &mut App, egui_ctx: &egui::Context) {
+        // The Nes thread pauses itself when it hits a breakpoint
+        let (hit, trace_error) = {
+            let mut debugger = app.debug.debugger.nes_debugger.lock().unwrap();
+            (debugger.last_break(), debugger.take_trace_error())
+        };
+        if hit.is_some() && !app.paused {
+            app.paused = true;
+        }
+
+        if let Some(e) = trace_error {
+            app.debug.debugger.trace_path = None;
+            report_error(&format!(
+                "Couldn't write the trace log, tracing stopped. Error: {}",
+                e
+            ));
+        }
+
+        let nes = match (&app.nes, app.debug.debugger.window_active) {
+            (Some(nes), true) => nes,
+            _ => return,
+        };
+
+        let paused = app.paused;
+        let form = &mut app.debug.debugger.form;
+        let shared = &app.debug.debugger.nes_debugger;
+        let trace_path = &app.debug.debugger.trace_path;
+        let mut set_paused = None;
+        let mut toggle_trace = false;
+
+        egui::Window::new("Debugger")
+            .open(&mut app.debug.debugger.window_active)
+            .resizable(false)
+            .default_width(0.)
+            .show(egui_ctx, |ui| {
+                let mut nes = nes.lock().unwrap();
+                let mut debugger = shared.lock().unwrap();
+
+                let regs = nes.cpu_registers();
+                display_registers(ui, &regs);
+
+                let instruction = nes.disassemble(regs.pc);
+                ui.label(
+                    RichText::new(format!("${:04X}: {}", instruction.addr, instruction))
+                        .monospace(),
+                );
+
+                let status = match debugger.last_break() {
+                    _ if !paused => "Running".to_string(),
+                    Some(reason) => describe_break(reason),
+                    None => "Paused".to_string(),
+                };
+                ui.label(status);
+
+                ui.horizontal(|ui| {
+                    let (text, pause) = match paused {
+                        true => ("Continue", false),
+                        false => ("Break", true),
+                    };
+                    if ui.button(text).clicked() {
+                        set_paused = Some(pause);
+                    }
+
+                    if ui
+                        .add_enabled(paused, egui::Button::new("Step into"))
+                        .clicked()
+                    {
+                        debugger.step_into(&mut nes);
+                    }
+
+                    // Stepping over a subroutine call or out of one keeps the emulation running
+                    if ui
+                        .add_enabled(paused, egui::Button::new("Step over"))
+                        .clicked()
+                    {
+                        debugger.step_over(&mut nes);
+                        if debugger.is_stepping() {
+                            set_paused = Some(false);
+                        }
+                    }
+
+                    if ui
+                        .add_enabled(paused, egui::Button::new("Step out"))
+                        .clicked()
+                    {
+                        debugger.step_out(&nes);
+                        set_paused = Some(false);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let text = match trace_path {
+                        Some(_) => "Stop trace log",
+                        None => "Start trace log",
+                    };
+                    if ui.button(text).clicked() {
+                        toggle_trace = true;
+                    }
+
+                    if let Some(path) = trace_path {
+                        ui.label(format!("Tracing to {}", path.display()));
+                    }
+                });
+
+                ui.separator();
+                ui.label(RichText::new("Breakpoints").heading().strong());
+
+                let mut remove = None;
+                egui::Grid::new("breakpoints_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, bp) in debugger.breakpoints.iter_mut().enumerate() {
+                            ui.checkbox(&mut bp.enabled, "");
+                            ui.label(RichText::new(bp.to_string()).monospace());
+                            if ui.button("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(i) = remove {
+                    debugger.breakpoints.remove(i);
+                }
+
+                ui.separator();
+                breakpoint_form(ui, form, &mut debugger);
+            });
+
+        if toggle_trace {
+            match app.debug.debugger.trace_path {
+                Some(_) => app.debug.debugger.stop_trace(),
+                None => app.debug.debugger.start_trace(),
+            }
+        }
+
+        if let Some(paused) = set_paused {
+            app.set_paused(paused);
+        }
+    }
+}
+
+fn display_registers(ui: &mut egui::Ui, regs: &CpuRegisters) {
+    egui::Grid::new("registers_grid")
+        .spacing([20., 5.])
+        .show(ui, |ui| {
+            for name in ["PC", "A", "X", "Y", "SP", "P"] {
+                ui.label(RichText::new(name).strong());
+            }
+            ui.end_row();
+
+            let values = [
+                format!("${:04X}", regs.pc),
+                format!("${:02X}", regs.a),
+                format!("${:02X}", regs.x),
+                format!("${:02X}", regs.y),
+                format!("${:02X}", regs.sp),
+                format!("${:02X}", regs.p),
+            ];
+            for value in values {
+                ui.label(RichText::new(value).monospace());
+            }
+            ui.end_row();
+        });
+
+    // Set flags are uppercase
+    let flags: String = "NV-BDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match regs.p & (0x80 >> i) != 0 {
+            true => c,
+            false => c.to_ascii_lowercase(),
+        })
+        .collect();
+    ui.label(RichText::new(format!("Flags: {}", flags)).monospace());
+}
+
+fn describe_break(reason: BreakReason) -> String {
+    match reason {
+        BreakReason::Breakpoint {
+            index,
+            addr,
+            access,
+        } => {
+            let access = match access {
+                Access::Execute => "execution of",
+                Access::Read => "read from",
+                Access::Write => "write to",
+            };
+
+            format!("Breakpoint {}: {} ${:04X}", index + 1, access, addr)
+        }
+        BreakReason::Step => "Step finished".to_string(),
+    }
+}
+
+fn breakpoint_form(ui: &mut egui::Ui, form: &mut BreakpointForm, debugger: &mut NesDebugger) {
+    egui::Grid::new("breakpoint_form_grid").show(ui, |ui| {
+        ui.label("Address");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut form.start).desired_width(50.));
+            ui.label("-");
+            ui.add(
+                egui::TextEdit::singleline(&mut form.end)
+                    .desired_width(50.)
+                    .hint_text("end"),
+            );
+        });
+        ui.end_row();
+
+        ui.label("Break on");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut form.read, "Read");
+            ui.checkbox(&mut form.write, "Write");
+            ui.checkbox(&mut form.execute, "Execute");
+        });
+        ui.end_row();
+
+        ui.label("Condition");
+        ui.add(egui::TextEdit::singleline(&mut form.condition).hint_text("A == $10"));
+        ui.end_row();
+    });
+
+    if ui.button("Add breakpoint").clicked() {
+        match form.parse() {
+            Ok(bp) => {
+                debugger.breakpoints.push(bp);
+                form.error = None;
+            }
+            Err(e) => form.error = Some(e),
+        }
+    }
+
+    if let Some(error) = &form.error {
+        ui.colored_label(egui::Color32::RED, error.as_str());
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use egui_glium::egui_winit::egui::{self, Color32, RichText};
+use fearless_nes::{Breakpoint, Debugger as NesDebugger, Instruction, Nes};
+
+use super::App;
+
+/// Number of instructions displayed before and after the viewed address
+const LINES_BEFORE: usize = 8;
+const LINES_AFTER: usize = 24;
+
+pub struct Disassembly {
+    pub window_active: bool,
+    follow_pc: bool,
+    view_addr: u16,
+    goto: String,
+    labels: Labels,
+}
+
+impl Disassembly {
+    pub fn new() -> Self {
+        Self {
+            window_active: false,
+            follow_pc: true,
+            view_addr: 0,
+            goto: String::new(),
+            labels: Labels::new(),
+        }
+    }
+}
+
+/// User-defined labels, shown in addition to the vectors and the hardware registers
+struct Labels {
+    names: BTreeMap<u16, String>,
+    form_addr: String,
+    form_name: String,
+}
+
+impl Labels {
+    fn new() -> Self {
+        Self {
+            names: BTreeMap::new(),
+            form_addr: String::new(),
+            form_name: String::new(),
+        }
+    }
+}
+
+/// Labels of the interrupt handlers, read from the vectors at $FFFA - $FFFF
+struct Vectors([(u16, &'static str); 3]);
+
+impl Vectors {
+    fn read(nes: &Nes) -> Self {
+        let vector = |addr: u16| u16::from_le_bytes([nes.peek_cpu(addr), nes.peek_cpu(addr + 1)]);
+
+        Self([
+            (vector(0xFFFA), "NMI"),
+            (vector(0xFFFC), "RESET"),
+            (vector(0xFFFE), "IRQ"),
+        ])
+    }
+
+    fn get(&self, addr: u16) -> Option<&'static str> {
+        self.0
+            .iter()
+            .find(|(vector, _)| *vector == addr)
+            .map(|(_, name)| *name)
+    }
+}
+
+fn hardware_label(addr: u16) -> Option<&'static str> {
+    let label = match addr {
+        0x2000 => "PPUCTRL",
+        0x2001 => "PPUMASK",
+        0x2002 => "PPUSTATUS",
+        0x2003 => "OAMADDR",
+        0x2004 => "OAMDATA",
+        0x2005 => "PPUSCROLL",
+        0x2006 => "PPUADDR",
+        0x2007 => "PPUDATA",
+        0x4000 => "SQ1_VOL",
+        0x4001 => "SQ1_SWEEP",
+        0x4002 => "SQ1_LO",
+        0x4003 => "SQ1_HI",
+        0x4004 => "SQ2_VOL",
+        0x4005 => "SQ2_SWEEP",
+        0x4006 => "SQ2_LO",
+        0x4007 => "SQ2_HI",
+        0x4008 => "TRI_LINEAR",
+        0x400A => "TRI_LO",
+        0x400B => "TRI_HI",
+        0x400C => "NOISE_VOL",
+        0x400E => "NOISE_LO",
+        0x400F => "NOISE_HI",
+        0x4010 => "DMC_FREQ",
+        0x4011 => "DMC_RAW",
+        0x4012 => "DMC_START",
+        0x4013 => "DMC_LEN",
+        0x4014 => "OAMDMA",
+        0x4015 => "SND_CHN",
+        0x4016 => "JOY1",
+        0x4017 => "JOY2",
+        _ => return None,
+    };
+
+    Some(label)
+}
+
+/// Addresses are hexadecimal, the '$' is optional
+fn parse_addr(s: &str) -> Option<u16> {
+    let s = s.trim();
+    u16::from_str_radix(s.strip_prefix('$').unwrap_or(s), 16).ok()
+}
+
+impl Disassembly {
+    pub fn gui_window(app: &mut App, egui_ctx: &egui::Context) {
+        let nes = match (&app.nes, app.debug.disassembly.window_active) {
+            (Some(nes), true) => nes,
+            _ => return,
+        };
+
+        let state = &mut app.debug.disassembly;
+        let shared = &app.debug.debugger.nes_debugger;
+
+        egui::Window::new("Disassembly")
+            .open(&mut state.window_active)
+            .resizable(false)
+            .default_width(0.)
+            .show(egui_ctx, |ui| {
+                let nes = nes.lock().unwrap();
+                let mut debugger = shared.lock().unwrap();
+                let pc = nes.cpu_registers().pc;
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut state.follow_pc, "Follow PC");
+
+                    ui.add(
+                        egui::TextEdit::singleline(&mut state.goto)
+                            .desired_width(50.)
+                            .hint_text("addr"),
+                    );
+                    if ui.button("Go to").clicked() {
+                        if let Some(addr) = parse_addr(&state.goto) {
+                            state.view_addr = addr;
+                            state.follow_pc = false;
+                        }
+                    }
+                });
+
+                if state.follow_pc {
+                    state.view_addr = pc;
+                }
+
+                let mut lines = nes.disassemble_before(state.view_addr, LINES_BEFORE);
+                lines.extend(nes.disassemble_range(state.view_addr, LINES_AFTER));
+
+                let vectors = Vectors::read(&nes);
+                let labels = &state.labels.names;
+                let label = |addr: u16| {
+                    labels
+                        .get(&addr)
+                        .map(String::as_str)
+                        .or_else(|| vectors.get(addr))
+                        .or_else(|| hardware_label(addr))
+                };
+
+                ui.separator();
+                let response = ui
+                    .vertical(|ui| {
+                        for instruction in &lines {
+                            disassembly_line(ui, instruction, pc, &label, &mut debugger);
+                        }
+                    })
+                    .response;
+
+                // Scrolling moves the view one instruction at a time
+                let scroll = ui.input().scroll_delta.y;
+                if response.hovered() && scroll != 0. {
+                    state.follow_pc = false;
+                    state.view_addr = match scroll > 0. {
+                        true => nes
+                            .disassemble_before(state.view_addr, 1)
+                            .first()
+                            .map_or(state.view_addr.wrapping_sub(1), |i| i.addr),
+                        false => nes.disassemble(state.view_addr).next_addr(),
+                    };
+                }
+
+                ui.label(
+                    RichText::new("Click an address to toggle an execution breakpoint").small(),
+                );
+
+                ui.separator();
+                labels_form(ui, &mut state.labels);
+            });
+    }
+}
+
+fn disassembly_line<'a>(
+    ui: &mut egui::Ui,
+    instruction: &Instruction,
+    pc: u16,
+    label: &impl Fn(u16) -> Option<&'a str>,
+    debugger: &mut NesDebugger,
+) {
+    let addr = instruction.addr;
+
+    if let Some(name) = label(addr) {
+        ui.label(
+            RichText::new(format!("{}:", name))
+                .monospace()
+                .color(Color32::LIGHT_BLUE),
+        );
+    }
+
+    let breakpoint = debugger
+        .breakpoints
+        .iter()
+        .position(|bp| bp.start == addr && bp.end == addr && bp.execute && !bp.read && !bp.write);
+
+    let info = instruction.info();
+    let bytes: Vec<String> = instruction
+        .bytes()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+    let cycles = format!("{}{}", info.cycles, if info.page_cycle { "+" } else { " " });
+
+    let marker = match (addr == pc, breakpoint.is_some()) {
+        (true, _) => "▶",
+        (false, true) => "●",
+        (false, false) => " ",
+    };
+
+    let text = format!(
+        "{:<9} {:<28} {}",
+        bytes.join(" "),
+        instruction.format_with_labels(label),
+        cycles
+    );
+
+    let mut color = match info.illegal {
+        true => Color32::GOLD,
+        false => ui.visuals().text_color(),
+    };
+    if addr == pc {
+        color = Color32::LIGHT_GREEN;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(marker).monospace().color(Color32::RED));
+
+        let addr_label = ui.add(
+            egui::Label::new(RichText::new(format!("${:04X}", addr)).monospace())
+                .sense(egui::Sense::click()),
+        );
+        let addr_label = match instruction.prg_offset {
+            Some(offset) => addr_label.on_hover_text(format!("PRG ROM offset ${:05X}", offset)),
+            None => addr_label,
+        };
+
+        if addr_label.clicked() {
+            match breakpoint {
+                Some(i) => {
+                    debugger.breakpoints.remove(i);
+                }
+                None => debugger.breakpoints.push(Breakpoint::execute(addr)),
+            }
+        }
+
+        ui.label(RichText::new(text).monospace().color(color));
+    });
+}
+
+fn labels_form(ui: &mut egui::Ui, labels: &mut Labels) {
+    egui::CollapsingHeader::new("Labels").show(ui, |ui| {
+        let mut remove = None;
+        egui::Grid::new("labels_grid").striped(true).show(ui, |ui| {
+            for (addr, name) in &labels.names {
+                ui.label(RichText::new(format!("${:04X}", addr)).monospace());
+                ui.label(RichText::new(name).monospace());
+                if ui.button("Remove").clicked() {
+                    remove = Some(*addr);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(addr) = remove {
+            labels.names.remove(&addr);
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut labels.form_addr)
+                    .desired_width(50.)
+                    .hint_text("addr"),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut labels.form_name)
+                    .desired_width(100.)
+                    .hint_text("name"),
+            );
+
+            let name = labels.form_name.trim();
+            if ui.button("Add label").clicked() && !name.is_empty() {
+                if let Some(addr) = parse_addr(&labels.form_addr) {
+                    labels.names.insert(addr, name.to_string());
+                    labels.form_name.clear();
+                }
+            }
+        });
+    });
+}
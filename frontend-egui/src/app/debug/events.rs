@@ -1,43 +1,192 @@
-use egui_glium::egui_winit::egui::{self, RichText};
+use egui_glium::egui_winit::egui::{
+    self, Color32, ColorImage, RichText, Sense, TextureHandle, Vec2,
+};
+use fearless_nes::{EventKind, Nes, NES_HEIGHT, NES_WIDTH, PALETTE};
 
 use super::App;
 
+/// PPU dots per scanline
+const DOTS: usize = 341;
+/// The timeline is drawn at 2x
+const SCALE: f32 = 2.;
+
 pub struct Events {
     pub window_active: bool,
+    timeline: Option<TextureHandle>,
 }
 
 impl Events {
     pub fn new() -> Self {
         Self {
             window_active: false,
+            timeline: None,
         }
     }
 
     pub fn gui_window(app: &mut App, egui_ctx: &egui::Context) {
+        let events = &mut app.debug.events;
+
         if let Some(n) = &mut app.nes {
-            let n = n.lock().unwrap();
-            let events = n.debug_events();
+            let mut n = n.lock().unwrap();
 
             egui::Window::new("Events")
-                .open(&mut app.debug.events.window_active)
-                .resizable(true)
+                .open(&mut events.window_active)
+                .resizable(false)
                 .show(egui_ctx, |ui| {
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        egui::Grid::new("events_grid").show(ui, |ui| {
-                            ui.label(RichText::new("Event").heading().strong());
-                            ui.label(RichText::new("Scanline").heading().strong());
-                            ui.label(RichText::new("Xpos").heading().strong());
-                            ui.end_row();
-
-                            for e in events {
-                                ui.label(e.kind.to_string());
-                                ui.label(e.scanline.to_string());
-                                ui.label(e.xpos.to_string());
-                                ui.end_row();
-                            }
-                        });
-                    });
+                    filters(ui, &mut n);
+                    ui.separator();
+                    timeline(ui, &n, &mut events.timeline);
+                    ui.separator();
+                    events_list(ui, &n);
                 });
         }
     }
 }
+
+fn event_color(kind: EventKind) -> Color32 {
+    match kind {
+        EventKind::Irq => Color32::from_rgb(255, 60, 60),
+        EventKind::Nmi => Color32::from_rgb(255, 150, 0),
+        EventKind::PpuRegisterRead => Color32::from_rgb(80, 160, 255),
+        EventKind::PpuRegisterWrite => Color32::from_rgb(60, 220, 220),
+        EventKind::ApuRegisterWrite => Color32::from_rgb(220, 220, 60),
+        EventKind::MapperRegisterWrite => Color32::from_rgb(200, 100, 255),
+        EventKind::OamDma => Color32::from_rgb(255, 120, 200),
+        EventKind::DmcDma => Color32::from_rgb(150, 110, 60),
+        EventKind::Sprite0Hit => Color32::from_rgb(60, 230, 60),
+        EventKind::SpriteOverflow => Color32::from_rgb(240, 240, 240),
+    }
+}
+
+fn event_details(kind: EventKind, addr: u16, val: u8) -> String {
+    match kind {
+        EventKind::PpuRegisterRead
+        | EventKind::PpuRegisterWrite
+        | EventKind::ApuRegisterWrite
+        | EventKind::MapperRegisterWrite
+        | EventKind::DmcDma => format!("${:04X} = ${:02X}", addr, val),
+        EventKind::OamDma => format!("page ${:02X}", val),
+        _ => String::new(),
+    }
+}
+
+fn filters(ui: &mut egui::Ui, nes: &mut Nes) {
+    egui::Grid::new("events_filters").show(ui, |ui| {
+        for (i, kind) in EventKind::ALL.into_iter().enumerate() {
+            let count = nes.debug_events().iter().filter(|e| e.kind == kind).count();
+            let mut enabled = nes.debug_event_enabled(kind);
+
+            ui.horizontal(|ui| {
+                let (rect, _) = ui.allocate_exact_size(Vec2::splat(10.), Sense::hover());
+                ui.painter().rect_filled(rect, 2., event_color(kind));
+
+                let text = format!("{} ({})", kind.to_string(), count);
+                if ui.checkbox(&mut enabled, text).changed() {
+                    nes.set_debug_event_enabled(kind, enabled);
+                }
+            });
+
+            if i % 4 == 3 {
+                ui.end_row();
+            }
+        }
+    });
+}
+
+/// The events of the last frame over a picture of all the dots of the frame
+fn timeline(ui: &mut egui::Ui, nes: &Nes, texture: &mut Option<TextureHandle>) {
+    let scanlines = nes.timing_mode().scanlines() as usize;
+
+    // The picture is output on dots 1 - 256 of the visible scanlines, it's dimmed
+    // so the events stand out
+    let mut image = ColorImage::new([DOTS, scanlines], Color32::from_gray(20));
+    let frame = nes.frame_buffer();
+    for y in 0..NES_HEIGHT {
+        for x in 0..NES_WIDTH {
+            let i = frame[y * NES_WIDTH + x] as usize * 3;
+            image[(x + 1, y)] =
+                Color32::from_rgb(PALETTE[i] / 3, PALETTE[i + 1] / 3, PALETTE[i + 2] / 3);
+        }
+    }
+
+    match texture {
+        Some(t) => t.set(image, egui::TextureFilter::Nearest),
+        None => {
+            *texture = Some(ui.ctx().load_texture(
+                "events-timeline",
+                image,
+                egui::TextureFilter::Nearest,
+            ))
+        }
+    }
+
+    let size = Vec2::new(DOTS as f32, scanlines as f32) * SCALE;
+    let response = ui.image(texture.as_ref().unwrap(), size);
+
+    let painter = ui.painter_at(response.rect);
+    let origin = response.rect.min;
+    let dot_pos = |xpos: u16, scanline: u16| {
+        origin + Vec2::new(xpos as f32 + 0.5, scanline as f32 + 0.5) * SCALE
+    };
+
+    let events = nes.debug_events();
+    for e in events {
+        painter.circle_filled(dot_pos(e.xpos, e.scanline), SCALE, event_color(e.kind));
+    }
+
+    if let Some(hover) = response.hover_pos() {
+        let nearest = events
+            .iter()
+            .map(|e| (e, dot_pos(e.xpos, e.scanline).distance(hover)))
+            .filter(|(_, distance)| *distance <= 3. * SCALE)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let text = match nearest {
+            Some((e, _)) => format!(
+                "{} {}\nScanline {}, dot {}",
+                e.kind.to_string(),
+                event_details(e.kind, e.addr, e.val),
+                e.scanline,
+                e.xpos
+            ),
+            None => {
+                let x = ((hover.x - origin.x) / SCALE) as usize;
+                let y = ((hover.y - origin.y) / SCALE) as usize;
+                format!("Scanline {}, dot {}", y, x)
+            }
+        };
+
+        response.on_hover_text(text);
+    }
+}
+
+fn events_list(ui: &mut egui::Ui, nes: &Nes) {
+    let events = nes.debug_events();
+
+    ui.label(
+        RichText::new(format!(
+            "{:<22} {:>8} {:>4}  {}",
+            "Event", "Scanline", "Dot", "Details"
+        ))
+        .monospace()
+        .strong(),
+    );
+
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    egui::ScrollArea::vertical()
+        .max_height(200.)
+        .auto_shrink([false, true])
+        .show_rows(ui, row_height, events.len(), |ui, rows| {
+            for e in &events[rows] {
+                let text = format!(
+                    "{:<22} {:>8} {:>4}  {}",
+                    e.kind.to_string(),
+                    e.scanline,
+                    e.xpos,
+                    event_details(e.kind, e.addr, e.val)
+                );
+
+                ui.label(RichText::new(text).monospace().color(event_color(e.kind)));
+            }
+        });
+}
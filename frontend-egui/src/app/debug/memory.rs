@@ -0,0 +1,254 @@
+use egui_glium::egui_winit::egui::{self, Color32, RichText, Sense};
+use fearless_nes::{MemoryRegion, Nes};
+
+use super::App;
+
+const BYTES_PER_ROW: usize = 16;
+
+pub struct Memory {
+    pub window_active: bool,
+    region: MemoryRegion,
+    /// The contents at the end of the last frame, and at the end of the frame before it.
+    /// Bytes that differ are highlighted.
+    current: Vec<u8>,
+    previous: Vec<u8>,
+    frame: u64,
+    selected: Option<usize>,
+    value: String,
+    search: String,
+    not_found: bool,
+    /// Row to scroll to after a search
+    scroll_to: Option<usize>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self {
+            window_active: false,
+            region: MemoryRegion::CpuBus,
+            current: Vec::new(),
+            previous: Vec::new(),
+            frame: 0,
+            selected: None,
+            value: String::new(),
+            search: String::new(),
+            not_found: false,
+            scroll_to: None,
+        }
+    }
+}
+
+impl Memory {
+    pub fn gui_window(app: &mut App, egui_ctx: &egui::Context) {
+        let nes = match (&app.nes, app.debug.memory.window_active) {
+            (Some(nes), true) => nes,
+            _ => return,
+        };
+
+        let state = &mut app.debug.memory;
+        let mut window_active = true;
+
+        egui::Window::new("Memory")
+            .open(&mut window_active)
+            .resizable(false)
+            .show(egui_ctx, |ui| {
+                let mut nes = nes.lock().unwrap();
+
+                let region = state.region;
+                egui::ComboBox::from_label("Region")
+                    .selected_text(region.to_string())
+                    .show_ui(ui, |ui| {
+                        for r in MemoryRegion::ALL {
+                            ui.selectable_value(&mut state.region, r, r.to_string());
+                        }
+                    });
+
+                if state.region != region {
+                    state.current.clear();
+                    state.selected = None;
+                    state.not_found = false;
+                }
+                state.update_snapshot(&nes);
+
+                if state.current.is_empty() {
+                    ui.label("The cartridge doesn't have this memory");
+                    return;
+                }
+
+                state.search_bar(ui);
+                state.edit_bar(ui, &mut nes);
+                ui.separator();
+                state.hex_view(ui, &nes);
+            });
+
+        state.window_active = window_active;
+    }
+
+    /// Takes a new snapshot of the region when a frame has been emulated
+    fn update_snapshot(&mut self, nes: &Nes) {
+        let len = nes.memory_len(self.region);
+        let new_frame = nes.frame_count() != self.frame;
+
+        if new_frame || self.current.len() != len {
+            let snapshot = (0..len)
+                .map(|addr| nes.peek_memory(self.region, addr))
+                .collect();
+
+            self.previous = match self.current.len() == len {
+                true => std::mem::replace(&mut self.current, snapshot),
+                false => {
+                    self.current = snapshot;
+                    self.current.clone()
+                }
+            };
+            self.frame = nes.frame_count();
+        }
+    }
+
+    fn search_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .desired_width(150.)
+                    .hint_text("A9 00 or \"text\""),
+            );
+            let enter = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+
+            if ui.button("Find next").clicked() || enter {
+                let start = self.selected.map_or(0, |addr| addr + 1);
+
+                match parse_search(&self.search)
+                    .and_then(|needle| find(&self.current, &needle, start))
+                {
+                    Some(addr) => {
+                        self.select(addr);
+                        self.scroll_to = Some(addr / BYTES_PER_ROW);
+                        self.not_found = false;
+                    }
+                    None => self.not_found = true,
+                }
+            }
+
+            if self.not_found {
+                ui.colored_label(Color32::LIGHT_RED, "Not found");
+            }
+        });
+    }
+
+    fn edit_bar(&mut self, ui: &mut egui::Ui, nes: &mut Nes) {
+        let addr = match self.selected {
+            Some(addr) => addr,
+            None => {
+                ui.label("Click a byte to edit it");
+                return;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("${:04X}:", addr)).monospace());
+
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.value)
+                    .desired_width(30.)
+                    .font(egui::TextStyle::Monospace),
+            );
+            let enter = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+
+            if ui.button("Write").clicked() || enter {
+                if let Ok(val) = u8::from_str_radix(self.value.trim(), 16) {
+                    nes.poke_memory(self.region, addr, val);
+                }
+            }
+        });
+    }
+
+    fn hex_view(&mut self, ui: &mut egui::Ui, nes: &Nes) {
+        let len = self.current.len();
+        let rows = len.div_ceil(BYTES_PER_ROW);
+        let addr_width = if len > 0x10000 { 5 } else { 4 };
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let mut scroll_area = egui::ScrollArea::vertical().max_height(400.);
+        if let Some(row) = self.scroll_to.take() {
+            let offset = row as f32 * (row_height + ui.spacing().item_spacing.y);
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+
+        scroll_area.show_rows(ui, row_height, rows, |ui, rows| {
+            for row in rows {
+                let start = row * BYTES_PER_ROW;
+                let end = (start + BYTES_PER_ROW).min(len);
+
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.;
+                    ui.label(
+                        RichText::new(format!("{:0width$X}", start, width = addr_width))
+                            .monospace()
+                            .color(Color32::LIGHT_BLUE),
+                    );
+
+                    let mut ascii = String::with_capacity(BYTES_PER_ROW);
+                    for addr in start..end {
+                        // Live values, the emulation might be running between the snapshots
+                        let val = nes.peek_memory(self.region, addr);
+                        ascii.push(match val {
+                            0x20..=0x7E => val as char,
+                            _ => '.',
+                        });
+
+                        let mut text = RichText::new(format!("{:02X}", val)).monospace();
+                        if self.previous[addr] != self.current[addr] {
+                            text = text.color(Color32::LIGHT_RED);
+                        }
+                        if self.selected == Some(addr) {
+                            text = text.background_color(Color32::DARK_BLUE);
+                        }
+
+                        if ui
+                            .add(egui::Label::new(text).sense(Sense::click()))
+                            .clicked()
+                        {
+                            self.select(addr);
+                        }
+                    }
+
+                    ui.label(RichText::new(ascii).monospace());
+                });
+            }
+        });
+    }
+
+    fn select(&mut self, addr: usize) {
+        self.selected = Some(addr);
+        self.value = format!("{:02X}", self.current[addr]);
+    }
+}
+
+/// Hexadecimal bytes ("A9 00" or "A900"), or ASCII text between quotes
+fn parse_search(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+
+    if let Some(text) = s.strip_prefix('"') {
+        let text = text.strip_suffix('"').unwrap_or(text);
+        return (!text.is_empty()).then(|| text.as_bytes().to_vec());
+    }
+
+    let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return None;
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// The first occurence of needle at or after start, wrapping around
+fn find(haystack: &[u8], needle: &[u8], start: usize) -> Option<usize> {
+    let matches = |addr: &usize| haystack[*addr..].starts_with(needle);
+
+    (start..haystack.len())
+        .chain(0..start.min(haystack.len()))
+        .find(matches)
+}
@@ -1,26 +1,54 @@
-use egui_glium::egui_winit::egui;
+use egui_glium::egui_winit::egui::{
+    self, Color32, ColorImage, Pos2, Rect, Response, RichText, Sense, Stroke, TextureHandle, Vec2,
+};
+use fearless_nes::{EventKind, Nes, PALETTE};
 use std::{collections::hash_map::DefaultHasher, hash::Hasher};
 
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Tab {
+    PatternTables,
+    Nametables,
+    Palettes,
+    Sprites,
+}
+
 pub struct Ppu {
     pub window_active: bool,
+    tab: Tab,
+    /// The palette used for the pattern tables, 0 - 3 are for the background, 4 - 7 for the sprites
+    palette: u8,
+    pattern_tables: [Option<TextureHandle>; 2],
+    nametables: Option<TextureHandle>,
+    /// The scanline whose sprite evaluation is highlighted in the sprites list
+    scanline: u16,
+    /// All the 64 sprites, 8 per row
+    sprites: Option<TextureHandle>,
 }
 
 impl Ppu {
     pub fn new() -> Self {
         Self {
             window_active: false,
+            tab: Tab::PatternTables,
+            palette: 0,
+            pattern_tables: [None, None],
+            nametables: None,
+            scanline: 0,
+            sprites: None,
         }
     }
 }
 
 impl Ppu {
     pub fn gui_window(app: &mut crate::app::App, egui_ctx: &egui::Context) {
-        let ppu_window_active = &mut app.debug.ppu.window_active;
+        let ppu = &mut app.debug.ppu;
         let nes = &app.nes;
 
-        if *ppu_window_active {
+        if ppu.window_active {
+            let mut window_active = true;
+
             egui::Window::new("PPU")
-                .open(ppu_window_active)
+                .open(&mut window_active)
                 .resizable(false)
                 .show(egui_ctx, |ui| {
                     if let Some(nes) = nes {
@@ -31,8 +59,302 @@ impl Ppu {
                         let hash = hasher.finish();
 
                         ui.text_edit_singleline(&mut format!("Display hash: {}", hash));
+
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut ppu.tab, Tab::PatternTables, "Pattern tables");
+                            ui.selectable_value(&mut ppu.tab, Tab::Nametables, "Nametables");
+                            ui.selectable_value(&mut ppu.tab, Tab::Palettes, "Palettes");
+                            ui.selectable_value(&mut ppu.tab, Tab::Sprites, "Sprites");
+                        });
+                        ui.separator();
+
+                        match ppu.tab {
+                            Tab::PatternTables => ppu.pattern_tables_view(ui, &nes),
+                            Tab::Nametables => ppu.nametables_view(ui, &nes),
+                            Tab::Palettes => palettes_view(ui, &nes),
+                            Tab::Sprites => ppu.sprites_view(ui, &nes),
+                        }
                     }
                 });
+
+            ppu.window_active = window_active;
+        }
+    }
+
+    fn pattern_tables_view(&mut self, ui: &mut egui::Ui, nes: &Nes) {
+        ui.horizontal(|ui| {
+            ui.label("Palette:");
+            for palette in 0..8 {
+                let text = match palette {
+                    0..=3 => format!("BG {}", palette),
+                    _ => format!("SP {}", palette - 4),
+                };
+                ui.selectable_value(&mut self.palette, palette, text);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            for table in 0..2 {
+                let pixels = nes.pattern_table(table, self.palette);
+                let texture = update_texture(
+                    &mut self.pattern_tables[table as usize],
+                    ui.ctx(),
+                    &format!("pattern-table-{}", table),
+                    [128, 128],
+                    &pixels,
+                );
+
+                let response = ui.image(texture, [256., 256.]);
+                if let Some((x, y)) = hovered_pixel(&response, [128, 128]) {
+                    let tile = (y / 8) * 16 + x / 8;
+                    let addr = table as usize * 0x1000 + tile * 16;
+
+                    response.on_hover_text(format!("Tile ${:02X}\nAddress ${:04X}", tile, addr));
+                }
+            }
+        });
+    }
+
+    fn nametables_view(&mut self, ui: &mut egui::Ui, nes: &Nes) {
+        let registers = nes.ppu_registers();
+        let (scroll_x, scroll_y) = registers.scroll();
+
+        ui.label(format!(
+            "Mirroring: {:?}, scroll: ({}, {})",
+            nes.mirroring(),
+            scroll_x,
+            scroll_y
+        ));
+
+        let pixels = nes.nametables();
+        let texture = update_texture(
+            &mut self.nametables,
+            ui.ctx(),
+            "nametables",
+            [512, 480],
+            &pixels,
+        );
+
+        let response = ui.image(texture, [512., 480.]);
+
+        // The visible screen, it wraps around the edges of the nametables
+        let painter = ui.painter_at(response.rect);
+        let origin = response.rect.min;
+        for (dx, dy) in [(0., 0.), (-512., 0.), (0., -480.), (-512., -480.)] {
+            let min = origin + Vec2::new(scroll_x as f32 + dx, scroll_y as f32 + dy);
+            let screen = Rect::from_min_size(min, Vec2::new(256., 240.));
+            painter.rect_stroke(screen, 0., Stroke::new(1., Color32::RED));
+        }
+
+        if let Some((x, y)) = hovered_pixel(&response, [512, 480]) {
+            let nametable = (y / 240) * 2 + x / 256;
+            let (col, row) = ((x % 256) / 8, (y % 240) / 8);
+
+            let base = 0x2000 + nametable as u16 * 0x400;
+            let addr = base + (row * 32 + col) as u16;
+            let attribute_addr = base + 0x3C0 + (row as u16 / 4) * 8 + col as u16 / 4;
+
+            response.on_hover_text(format!(
+                "Nametable {}, column {}, row {}\nAddress ${:04X}\nTile ${:02X}\nAttribute ${:04X}: ${:02X}\nPalette {}",
+                nametable,
+                col,
+                row,
+                addr,
+                nes.peek_ppu(addr),
+                attribute_addr,
+                nes.peek_ppu(attribute_addr),
+                nes.attribute_palette(base, col as u16, row as u16)
+            ));
+        }
+    }
+
+    fn sprites_view(&mut self, ui: &mut egui::Ui, nes: &Nes) {
+        let registers = nes.ppu_registers();
+        let height = registers.sprite_height as usize;
+
+        match height {
+            8 => ui.label(format!(
+                "Sprite size: 8x8, pattern table ${:04X}",
+                registers.sprite_pattern_table
+            )),
+            _ => ui.label("Sprite size: 8x16"),
+        };
+
+        // The flags are cleared on the pre-render scanline, the events are from the last frame
+        for (kind, name) in [
+            (EventKind::Sprite0Hit, "Sprite 0 hit"),
+            (EventKind::SpriteOverflow, "Sprite overflow"),
+        ] {
+            let event = nes.debug_events().iter().find(|e| e.kind == kind);
+
+            match event {
+                Some(e) => ui.colored_label(
+                    Color32::LIGHT_RED,
+                    format!("{}: scanline {}, dot {}", name, e.scanline, e.xpos),
+                ),
+                None => ui.label(format!("{}: none", name)),
+            };
         }
+
+        ui.horizontal(|ui| {
+            ui.label("Sprite evaluation of scanline");
+            ui.add(egui::DragValue::new(&mut self.scanline).clamp_range(0..=239));
+        });
+
+        let in_range = nes.scanline_sprites(self.scanline);
+        ui.label(
+            RichText::new("Green: in the secondary OAM, orange: dropped by the sprite overflow")
+                .small(),
+        );
+        ui.separator();
+
+        let mut image = ColorImage::new([64, 8 * height], Color32::TRANSPARENT);
+        let entries: Vec<_> = (0..64).map(|index| nes.oam_entry(index)).collect();
+        for entry in &entries {
+            let pixels = nes.sprite_pixels(entry);
+            let (sprite_x, sprite_y) = (
+                (entry.index as usize % 8) * 8,
+                (entry.index as usize / 8) * height,
+            );
+
+            for (i, &color) in pixels.iter().enumerate() {
+                if color != 0 {
+                    let index = nes.peek_palette(entry.palette() * 4 + color);
+                    image[(sprite_x + i % 8, sprite_y + i / 8)] = nes_color(index);
+                }
+            }
+        }
+        let texture_id = upload_texture(&mut self.sprites, ui.ctx(), "sprites", image).id();
+
+        egui::ScrollArea::vertical()
+            .max_height(480.)
+            .show(ui, |ui| {
+                egui::Grid::new("sprites_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["#", "", "X", "Y", "Tile", "Palette", "Priority", "Flip"] {
+                            ui.label(RichText::new(header).strong());
+                        }
+                        ui.end_row();
+
+                        for entry in &entries {
+                            let color = match in_range.iter().position(|&i| i == entry.index) {
+                                Some(0..=7) => Color32::LIGHT_GREEN,
+                                Some(_) => Color32::from_rgb(255, 165, 0),
+                                None => ui.visuals().text_color(),
+                            };
+                            ui.label(
+                                RichText::new(entry.index.to_string())
+                                    .monospace()
+                                    .color(color),
+                            );
+
+                            let uv = Rect::from_min_size(
+                                Pos2::new(
+                                    (entry.index % 8) as f32 / 8.,
+                                    (entry.index / 8) as f32 / 8.,
+                                ),
+                                Vec2::new(1. / 8., 1. / 8.),
+                            );
+                            ui.add(
+                                egui::Image::new(texture_id, [16., 2. * height as f32])
+                                    .uv(uv)
+                                    .bg_fill(Color32::from_gray(40)),
+                            );
+
+                            ui.label(entry.x.to_string());
+                            ui.label(entry.y.to_string());
+                            ui.label(format!("${:02X}", entry.tile));
+                            ui.label(entry.palette().to_string());
+                            ui.label(match entry.behind_background() {
+                                true => "Behind",
+                                false => "Front",
+                            });
+                            ui.label(match (entry.horizontal_flip(), entry.vertical_flip()) {
+                                (false, false) => "-",
+                                (true, false) => "H",
+                                (false, true) => "V",
+                                (true, true) => "HV",
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+fn palettes_view(ui: &mut egui::Ui, nes: &Nes) {
+    egui::Grid::new("palettes_grid")
+        .spacing([4., 4.])
+        .show(ui, |ui| {
+            for palette in 0..8u8 {
+                let text = match palette {
+                    0..=3 => format!("BG {}", palette),
+                    _ => format!("SP {}", palette - 4),
+                };
+                ui.label(text);
+
+                for color in 0..4u8 {
+                    let addr = palette * 4 + color;
+                    let index = nes.peek_palette(addr);
+
+                    let (rect, response) =
+                        ui.allocate_exact_size(Vec2::new(24., 24.), Sense::hover());
+                    ui.painter().rect_filled(rect, 2., nes_color(index));
+
+                    response.on_hover_text(format!(
+                        "${:04X}: ${:02X}",
+                        0x3F00 + addr as u16,
+                        index
+                    ));
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn nes_color(index: u8) -> Color32 {
+    let i = (index as usize & 0x3F) * 3;
+    Color32::from_rgb(PALETTE[i], PALETTE[i + 1], PALETTE[i + 2])
+}
+
+/// Converts the pixels (indexes into the NES palette) and uploads them to the texture
+fn update_texture<'a>(
+    texture: &'a mut Option<TextureHandle>,
+    ctx: &egui::Context,
+    name: &str,
+    size: [usize; 2],
+    pixels: &[u8],
+) -> &'a TextureHandle {
+    let mut image = ColorImage::new(size, Color32::BLACK);
+    for (dst, &index) in image.pixels.iter_mut().zip(pixels) {
+        *dst = nes_color(index);
+    }
+
+    upload_texture(texture, ctx, name, image)
+}
+
+fn upload_texture<'a>(
+    texture: &'a mut Option<TextureHandle>,
+    ctx: &egui::Context,
+    name: &str,
+    image: ColorImage,
+) -> &'a TextureHandle {
+    match texture {
+        Some(t) => t.set(image, egui::TextureFilter::Nearest),
+        None => *texture = Some(ctx.load_texture(name, image, egui::TextureFilter::Nearest)),
     }
+
+    texture.as_ref().unwrap()
+}
+
+/// The pixel of the picture under the mouse cursor
+fn hovered_pixel(response: &Response, size: [usize; 2]) -> Option<(usize, usize)> {
+    let pos: Pos2 = response.hover_pos()?;
+    let rect = response.rect;
+
+    let x = ((pos.x - rect.min.x) / rect.width() * size[0] as f32) as usize;
+    let y = ((pos.y - rect.min.y) / rect.height() * size[1] as f32) as usize;
+
+    Some((x.min(size[0] - 1), y.min(size[1] - 1)))
 }
@@ -6,6 +6,13 @@ use fearless_nes::{FRAMEBUFFER_SIZE, NES_HEIGHT, NES_WIDTH, PALETTE};
 pub struct NesRender {
     pub image: ColorImage,
     texture: Option<TextureHandle>,
+
+    /// The NES pixel under the mouse cursor, used for aiming the Zapper
+    pub mouse_pixel: Option<(usize, usize)>,
+    /// The primary mouse button pulls the Zapper trigger / presses the Arkanoid fire button
+    pub mouse_down: bool,
+    /// The horizontal mouse position over the picture turns the Arkanoid knob
+    pub paddle_position: u8,
 }
 
 impl NesRender {
@@ -13,6 +20,10 @@ impl NesRender {
         Self {
             image: ColorImage::new([NES_WIDTH, NES_HEIGHT], Color32::BLACK),
             texture: None,
+
+            mouse_pixel: None,
+            mouse_down: false,
+            paddle_position: 0x80,
         }
     }
 
@@ -55,9 +66,33 @@ impl NesRender {
             let rect = Self::calculate_nes_rect(available);
 
             img.paint_at(ui, rect);
+
+            let input = ui.input();
+            let hover_pos = input.pointer.hover_pos();
+
+            self.mouse_pixel = hover_pos
+                .filter(|pos| rect.contains(*pos))
+                .map(|pos| Self::nes_pixel(pos, rect, uv));
+            self.mouse_down = input.pointer.primary_down();
+
+            if let Some(pos) = hover_pos {
+                let ratio = ((pos.x - rect.min.x) / rect.width()).clamp(0., 1.);
+                self.paddle_position = (ratio * 255.) as u8;
+            }
         });
     }
 
+    /// Maps a point on the screen to a pixel of the (cropped) NES picture
+    fn nes_pixel(pos: Pos2, rect: egui::Rect, uv: [Pos2; 2]) -> (usize, usize) {
+        let u = uv[0].x + (pos.x - rect.min.x) / rect.width() * (uv[1].x - uv[0].x);
+        let v = uv[0].y + (pos.y - rect.min.y) / rect.height() * (uv[1].y - uv[0].y);
+
+        let x = (u * NES_WIDTH as f32) as usize;
+        let y = (v * NES_HEIGHT as f32) as usize;
+
+        (x.min(NES_WIDTH - 1), y.min(NES_HEIGHT - 1))
+    }
+
     fn calculate_nes_size(overscan: &Overscan) -> [f32; 2] {
         let width = NES_WIDTH as f32 - overscan.left as f32 - overscan.right as f32;
         let height = NES_HEIGHT as f32 - overscan.top as f32 - overscan.bottom as f32;
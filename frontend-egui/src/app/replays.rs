@@ -1,83 +1,230 @@
 use egui_glium::egui_winit::egui;
-use std::{fs, io::Write};
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
 
-use fearless_nes::ReplayInputs;
+use eyre::{eyre, Result};
+use fearless_nes::{Fm2, Movie, MovieMode, MoviePlayer};
 
-use crate::app::get_save_named_path;
-use crate::dialog::{report_error, DialogReport};
+use crate::app::{get_open_file_path, get_save_named_path, App};
+use crate::{dialog::DialogReport, nesthread::NesMsg};
 
-use super::RuntimeNes;
-
-pub enum Recording {
-    On { replay_inputs: ReplayInputs },
-    Off,
-}
+const MOVIE_EXTENSION: &str = "fnesmovie";
 
 pub struct Replays {
-    pub recording: Recording,
+    /// Shared with the Nes thread, which applies the inputs at the start of every frame.
+    /// Always locked after the Nes.
+    pub player: Arc<Mutex<Option<MoviePlayer>>>,
 }
 
 impl Replays {
     pub fn new() -> Self {
         Self {
-            recording: Recording::Off,
+            player: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.player.lock().unwrap().is_some()
+    }
+
+    pub fn stop(&mut self) {
+        *self.player.lock().unwrap() = None;
+    }
+
+    /// The game's .sav file is reloaded, the battery RAM of the movie isn't saved
+    fn stop_and_reattach_battery(app: &mut App) -> Result<()> {
+        app.replays.stop();
+
+        if let (Some(rom_path), Some(nes)) = (&app.rom_path, &app.nes) {
+            let sav_path = app.sav_path(rom_path);
+            let mut nes = nes.lock().unwrap();
+            app.battery.attach(&mut nes, sav_path)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_rom(app: &App) -> Result<Vec<u8>> {
+        let rom_path = app.rom_path.as_ref().ok_or(eyre!("No ROM is loaded"))?;
+        Ok(fs::read(rom_path)?)
+    }
+
+    fn start(app: &mut App, movie: Movie, mode: MovieMode) -> Result<()> {
+        let rom = Self::read_rom(app)?;
+        let (player, new_nes) = MoviePlayer::start(movie, &rom, mode)?;
+
+        // The movie shouldn't overwrite the game's battery save
+        app.flush_battery_save();
+        app.battery.detach();
+
+        // Both have to be replaced before the Nes thread runs the next frame
+        if let Some(nes) = &app.nes {
+            let mut nes = nes.lock().unwrap();
+            *nes = new_nes;
+            *app.replays.player.lock().unwrap() = Some(player);
         }
+
+        app.send_nes_msg(NesMsg::ClearRewind);
+        Ok(())
     }
 
-    pub fn start_recording(&mut self) {
-        self.recording = Recording::On {
-            replay_inputs: ReplayInputs::new(),
+    fn record(app: &mut App, from_power_on: bool) -> Result<()> {
+        let movie = match &app.nes {
+            Some(nes) => {
+                let nes = nes.lock().unwrap();
+                match from_power_on {
+                    true => Movie::from_power_on(&nes),
+                    false => Movie::from_savestate(&nes)?,
+                }
+            }
+            None => return Ok(()),
         };
+
+        Self::start(app, movie, MovieMode::ReadWrite)
     }
 
-    pub fn stop_recording(&mut self, nes: &RuntimeNes) {
-        // We can unwrap app.nes, because recording can only exist if we have
-        // a Nes instance
-        let nes = nes.as_ref().unwrap();
-        let nes = nes.lock().unwrap();
-
-        let save_path =
-            match get_save_named_path(None, "FearLess-NES recorded inputs", "fnesinputs") {
-                Some(p) => p,
-                None => return,
-            };
-
-        match &mut self.recording {
-            Recording::On { replay_inputs } => {
-                let inputs = match replay_inputs
-                    .save_with_end_frame(nes.frame_count())
-                    .report_dialog_msg("Couldn't save the recording")
-                {
-                    Ok(i) => i,
-                    Err(_) => return,
-                };
-
-                let mut save_file = fs::File::create(save_path).unwrap();
-                save_file
-                    .write_all(&inputs)
-                    .report_dialog_msg("Couldn't save the recording")
-                    .ok();
+    fn play(app: &mut App) -> Result<()> {
+        let path = match get_open_file_path(None) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
 
-                self.recording = Recording::Off;
+        let movie = Movie::load(&fs::read(path)?)?;
+        Self::start(app, movie, MovieMode::ReadOnly)
+    }
+
+    /// FCEUX movies
+    fn import_fm2(app: &mut App) -> Result<()> {
+        let path = match get_open_file_path(None) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        let fm2 = Fm2::parse(&fs::read_to_string(path)?)?;
+        let movie = match &app.nes {
+            Some(nes) => fm2.into_movie(&nes.lock().unwrap())?,
+            None => return Ok(()),
+        };
+
+        Self::start(app, movie, MovieMode::ReadOnly)
+    }
+
+    fn export_fm2(app: &App) -> Result<()> {
+        let mut fm2 = match &app.nes {
+            Some(nes) => {
+                let nes = nes.lock().unwrap();
+                match app.replays.player.lock().unwrap().as_ref() {
+                    Some(player) => Fm2::from_movie(player.movie(), &nes)?,
+                    None => return Ok(()),
+                }
             }
-            Recording::Off => report_error("No started recording to save"),
+            None => return Ok(()),
+        };
+
+        if let Some(name) = app.rom_path.as_ref().and_then(|p| p.file_stem()) {
+            fm2.rom_filename = name.to_string_lossy().into_owned();
         }
+
+        let path = match get_save_named_path(None, "FCEUX movie", "fm2") {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        fs::write(path, fm2.export())?;
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let movie = match self.player.lock().unwrap().as_ref() {
+            Some(player) => player.movie().save()?,
+            None => return Ok(()),
+        };
+
+        let path = match get_save_named_path(None, "Fearless-NES movie", MOVIE_EXTENSION) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        fs::write(path, movie)?;
+        Ok(())
     }
 }
 
 impl Replays {
-    pub fn gui_embed(app: &mut super::App, ui: &mut egui::Ui) {
-        match &mut app.replays.recording {
-            Recording::On { .. } => {
-                if ui.button("Stop recording").clicked() {
-                    app.replays.stop_recording(&app.nes);
-                }
+    pub fn gui_embed(app: &mut App, ui: &mut egui::Ui) {
+        if !app.replays.is_active() {
+            if ui.button("Record from power on").clicked() {
+                Self::record(app, true)
+                    .report_dialog_with(|e| format!("Couldn't start the recording. Error: {}", e))
+                    .ok();
             }
-            Recording::Off => {
-                if ui.button("Start recording").clicked() {
-                    app.replays.start_recording();
+
+            if ui.button("Record from here").clicked() {
+                Self::record(app, false)
+                    .report_dialog_with(|e| format!("Couldn't start the recording. Error: {}", e))
+                    .ok();
+            }
+
+            if ui.button("Play movie").clicked() {
+                Self::play(app)
+                    .report_dialog_with(|e| format!("Couldn't play the movie. Error: {}", e))
+                    .ok();
+            }
+
+            if ui.button("Import FM2").clicked() {
+                Self::import_fm2(app)
+                    .report_dialog_with(|e| format!("Couldn't import the movie. Error: {}", e))
+                    .ok();
+            }
+
+            return;
+        }
+
+        if let Some(nes) = &app.nes {
+            let nes = nes.lock().unwrap();
+            if let Some(player) = app.replays.player.lock().unwrap().as_mut() {
+                let movie = player.movie();
+                ui.label(format!(
+                    "Frame {} / {}",
+                    nes.frame_count(),
+                    movie.end_frame()
+                ));
+                ui.label(format!("Rerecords: {}", movie.rerecord_count));
+
+                let mut read_only = player.mode() == MovieMode::ReadOnly;
+                if ui
+                    .checkbox(&mut read_only, "Read-only")
+                    .on_hover_text("Turning off read-only cuts off the rest of the movie")
+                    .changed()
+                {
+                    let mode = match read_only {
+                        true => MovieMode::ReadOnly,
+                        false => MovieMode::ReadWrite,
+                    };
+                    player.set_mode(&nes, mode);
                 }
             }
         }
+
+        if ui.button("Save movie").clicked() {
+            app.replays
+                .save()
+                .report_dialog_with(|e| format!("Couldn't save the movie. Error: {}", e))
+                .ok();
+        }
+
+        if ui.button("Export FM2").clicked() {
+            Self::export_fm2(app)
+                .report_dialog_with(|e| format!("Couldn't export the movie. Error: {}", e))
+                .ok();
+        }
+
+        if ui.button("Stop movie").clicked() {
+            Self::stop_and_reattach_battery(app)
+                .report_dialog_with(|e| format!("Couldn't load the battery save. Error: {}", e))
+                .ok();
+        }
     }
 }
@@ -187,6 +187,19 @@ impl Saves {
                 });
 
             if let Some(loaded_nes) = loaded_nes {
+                app.flush_battery_save();
+
+                // The saves of all of the games are listed. The battery RAM of another game
+                // mustn't be written to the .sav file of the current one.
+                let same_game = match &app.nes {
+                    Some(nes) => nes.lock().unwrap().rom_sha1() == loaded_nes.rom_sha1(),
+                    None => false,
+                };
+                if !same_game {
+                    app.battery.detach();
+                    app.rom_path = None;
+                }
+
                 app.replace_nes(loaded_nes);
             }
         }
@@ -2,7 +2,7 @@ use egui_glium::egui_winit::egui::{self, Button, RichText, Ui};
 use gilrs::Button as GButton;
 use winit::event::VirtualKeyCode;
 
-use crate::app::config::Keybinds;
+use crate::app::config::{Keybinds, TimingOverride};
 
 use fearless_nes::Button as NesButton;
 
@@ -37,6 +37,20 @@ impl Settings {
             app.config.dark_mode = !app.config.dark_mode;
         }
 
+        ui.checkbox(
+            &mut app.config.battery_saves_next_to_rom,
+            "Battery saves next to the ROM",
+        );
+
+        // Takes effect when the next game is loaded
+        egui::ComboBox::from_label("Timing")
+            .selected_text(app.config.timing_override.label())
+            .show_ui(ui, |ui| {
+                for timing in TimingOverride::ALL {
+                    ui.selectable_value(&mut app.config.timing_override, timing, timing.label());
+                }
+            });
+
         if ui.button("Overscan").clicked() {
             app.settings.overscan.window_shown = true;
         }
@@ -102,6 +116,8 @@ impl OverscanUi {
 
 pub struct KeybindsUi {
     pub window_shown: bool,
+    /// The player whose keybinds are shown
+    pub player: usize,
     pub selected_nesbtn: SelectedButton,
     pub input_btn: Option<InputButton>,
 }
@@ -110,6 +126,7 @@ impl KeybindsUi {
     pub fn new() -> Self {
         Self {
             window_shown: false,
+            player: 0,
             selected_nesbtn: SelectedButton::None,
             input_btn: None,
         }
@@ -163,8 +180,42 @@ impl KeybindsUi {
         ui.end_row();
     }
 
+    fn display_power_pad(keys: &[VirtualKeyCode; 12], ui: &mut Ui, selected: &mut SelectedButton) {
+        let txt_color = ui.style().visuals.text_color();
+        let highlight_color = ui.style().visuals.warn_fg_color;
+
+        egui::Grid::new("Power Pad Grid")
+            .spacing([5., 5.])
+            .show(ui, |ui| {
+                for (index, key) in keys.iter().enumerate() {
+                    let color = match *selected {
+                        SelectedButton::PowerPad(i) if i == index => highlight_color,
+                        _ => txt_color,
+                    };
+
+                    let text = RichText::new(format!("{}: {:?}", index + 1, key)).color(color);
+                    if ui.add(Button::new(text)).clicked() {
+                        if *selected == SelectedButton::PowerPad(index) {
+                            *selected = SelectedButton::None;
+                        } else {
+                            *selected = SelectedButton::PowerPad(index);
+                        }
+                    }
+
+                    // The buttons are laid out like on the mat
+                    if index % 4 == 3 {
+                        ui.end_row();
+                    }
+                }
+            });
+    }
+
+    /// Gamepad buttons only have to be unique per player, keys have to be unique across all players
     fn change_keybind(
-        binds: &mut Keybinds,
+        player_binds: &mut [Keybinds; 4],
+        player: usize,
+        power_pad_keys: &mut [VirtualKeyCode; 12],
+        rewind_key: &mut VirtualKeyCode,
         selected: &mut SelectedButton,
         input_button: &mut Option<InputButton>,
     ) {
@@ -172,8 +223,8 @@ impl KeybindsUi {
             SelectedButton::None => (),
             SelectedButton::Controller(btn) => {
                 if let Some(InputButton::Gamepad(b)) = input_button {
-                    if !binds.ctrl_btn_used(*b) {
-                        binds[btn].ctrl = *b;
+                    if !player_binds[player].ctrl_btn_used(*b) {
+                        player_binds[player][btn].ctrl = *b;
                         *selected = SelectedButton::None;
                         *input_button = None;
                     }
@@ -181,8 +232,25 @@ impl KeybindsUi {
             }
             SelectedButton::Keyboard(btn) => {
                 if let Some(InputButton::Keyboard(key)) = input_button {
-                    if !binds.key_used(*key) {
-                        binds[btn].kbd = *key;
+                    if !player_binds.iter().any(|binds| binds.key_used(*key)) {
+                        player_binds[player][btn].kbd = *key;
+                        *selected = SelectedButton::None;
+                        *input_button = None;
+                    }
+                }
+            }
+            // The Power Pad keys take precedence over the controller keys, so they can overlap
+            SelectedButton::PowerPad(index) => {
+                if let Some(InputButton::Keyboard(key)) = input_button {
+                    power_pad_keys[index] = *key;
+                    *selected = SelectedButton::None;
+                    *input_button = None;
+                }
+            }
+            SelectedButton::Rewind => {
+                if let Some(InputButton::Keyboard(key)) = input_button {
+                    if !player_binds.iter().any(|binds| binds.key_used(*key)) {
+                        *rewind_key = *key;
                         *selected = SelectedButton::None;
                         *input_button = None;
                     }
@@ -195,12 +263,26 @@ impl KeybindsUi {
         let window_shown = &mut app.settings.keybinds.window_shown;
         let selected = &mut app.settings.keybinds.selected_nesbtn;
         let input_button = &mut app.settings.keybinds.input_btn;
-        let binds = &mut app.config.keybinds;
+        let player = &mut app.settings.keybinds.player;
+        let player_binds = &mut app.config.player_keybinds;
+        let power_pad_keys = &mut app.config.power_pad_keys;
+        let rewind_key = &mut app.config.rewind_key;
 
         egui::Window::new("Key bindings")
             .open(window_shown)
             .resizable(false)
             .show(egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for p in 0..player_binds.len() {
+                        let label = format!("Player {}", p + 1);
+                        if ui.selectable_value(player, p, label).clicked() {
+                            *selected = SelectedButton::None;
+                        }
+                    }
+                });
+
+                let binds = &mut player_binds[*player];
+
                 egui::Grid::new("Key Bindingss Grid")
                     .striped(true)
                     .spacing([20., 5.])
@@ -218,10 +300,39 @@ impl KeybindsUi {
                         KeybindsUi::display_keybind(binds, NesButton::Right, ui, selected);
                         KeybindsUi::display_keybind(binds, NesButton::Down, ui, selected);
                         KeybindsUi::display_keybind(binds, NesButton::Left, ui, selected);
-                    })
+                    });
+
+                ui.collapsing("Power Pad", |ui| {
+                    KeybindsUi::display_power_pad(power_pad_keys, ui, selected);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Rewind");
+
+                    let color = match *selected {
+                        SelectedButton::Rewind => ui.style().visuals.warn_fg_color,
+                        _ => ui.style().visuals.text_color(),
+                    };
+
+                    let text = RichText::new(format!("{:?}", rewind_key)).color(color);
+                    if ui.add(Button::new(text)).clicked() {
+                        if *selected == SelectedButton::Rewind {
+                            *selected = SelectedButton::None;
+                        } else {
+                            *selected = SelectedButton::Rewind;
+                        }
+                    }
+                });
             });
 
-        KeybindsUi::change_keybind(binds, selected, input_button);
+        KeybindsUi::change_keybind(
+            player_binds,
+            *player,
+            power_pad_keys,
+            rewind_key,
+            selected,
+            input_button,
+        );
     }
 }
 
@@ -229,6 +340,9 @@ impl KeybindsUi {
 pub enum SelectedButton {
     Controller(NesButton),
     Keyboard(NesButton),
+    /// Index of the Power Pad button
+    PowerPad(usize),
+    Rewind,
     None,
 }
 
@@ -92,6 +92,8 @@ fn main() {
 }
 
 fn on_exit(display: &glium::Display, app: &mut App) {
+    app.flush_battery_save();
+
     let size = display.gl_window().window().inner_size();
 
     app.config.window_height = size.height;
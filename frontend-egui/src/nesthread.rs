@@ -8,31 +8,49 @@ use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     SampleRate, StreamConfig, StreamError,
 };
-use crossbeam::channel::Receiver;
-use fearless_nes::Nes;
+use crossbeam::channel::{Receiver, Sender};
+use fearless_nes::{Debugger, MoviePlayer, Nes, Rewind, TimingMode};
+
+/// A snapshot is taken every 2 frames, so rewinding runs at twice the normal speed
+const REWIND_INTERVAL: u64 = 2;
+/// At least 30 seconds of history
+const REWIND_CAPACITY: usize = 900;
 
 pub enum NesMsg {
     Pause,
     Unpause,
+    /// Rewind while the hotkey is held
+    Rewind(bool),
+    /// A different game or a savestate was loaded
+    ClearRewind,
     Exit,
 }
 
 struct State {
     paused: bool,
+    rewinding: bool,
 }
 
 impl State {
     fn new() -> Self {
-        Self { paused: false }
+        Self {
+            paused: false,
+            rewinding: false,
+        }
     }
 }
 
-// The NTSC PPU runs at 60.0988 Hz
-const FRAME_DURATION: Duration = Duration::from_nanos(16639267);
-
-pub fn run_nes_thread(nes: Arc<Mutex<Nes>>, channel: Receiver<NesMsg>) -> JoinHandle<()> {
+/// The movie is always locked after the Nes, and the debugger after the movie.
+/// The thread pauses itself when the debugger breaks.
+pub fn run_nes_thread(
+    nes: Arc<Mutex<Nes>>,
+    movie: Arc<Mutex<Option<MoviePlayer>>>,
+    debugger: Arc<Mutex<Debugger>>,
+    channel: Receiver<NesMsg>,
+) -> JoinHandle<()> {
     std::thread::spawn(move || {
         let mut state = State::new();
+        let mut rewind = Rewind::new(REWIND_INTERVAL, REWIND_CAPACITY);
 
         let (audio_send, audio_recv) = crossbeam::channel::bounded::<i16>(2048);
         let (stream, sample_rate) = setup_audio(audio_recv);
@@ -43,9 +61,11 @@ pub fn run_nes_thread(nes: Arc<Mutex<Nes>>, channel: Receiver<NesMsg>) -> JoinHa
         }
 
         let mut samples = Vec::with_capacity(16);
+        // The NTSC PPU runs at 60.0988 Hz, PAL and Dendy at 50.007 Hz
+        let mut frame_duration = Duration::from_secs_f64(1. / TimingMode::Ntsc.frame_rate());
 
         loop {
-            let deadline = Instant::now() + FRAME_DURATION;
+            let deadline = Instant::now() + frame_duration;
 
             for msg in channel.try_iter() {
                 match msg {
@@ -58,20 +78,52 @@ pub fn run_nes_thread(nes: Arc<Mutex<Nes>>, channel: Receiver<NesMsg>) -> JoinHa
                         state.paused = false;
                         stream.play().ok();
                     }
+                    NesMsg::Rewind(rewinding) => {
+                        // Rewinding while recording a movie is a rerecord
+                        if state.rewinding && !rewinding {
+                            let n = nes.lock().unwrap();
+                            if let Some(player) = movie.lock().unwrap().as_mut() {
+                                player.state_loaded(&n);
+                            }
+                        }
+
+                        state.rewinding = rewinding;
+                    }
+                    NesMsg::ClearRewind => rewind.clear(),
                 }
             }
 
             // TODO(rewrite): performance monitoring
             if !state.paused {
                 let mut n = nes.lock().unwrap();
+                frame_duration = Duration::from_secs_f64(1. / n.timing_mode().frame_rate());
+
+                // The audio is muted while rewinding
+                if state.rewinding {
+                    rewind.rewind(&mut n, REWIND_INTERVAL).ok();
+                } else {
+                    if let Some(player) = movie.lock().unwrap().as_mut() {
+                        player.apply_inputs(&mut n);
+                    }
 
-                while !n.run_scanline() {
-                    n.apu_samples(&mut samples);
-
-                    for s in samples.iter() {
-                        audio_send.try_send(*s).ok();
+                    let mut debugger = debugger.lock().unwrap();
+                    if debugger.is_active() {
+                        let hit = debugger.run_frame(&mut n).is_some();
+                        send_samples(&mut n, &mut samples, &audio_send);
+
+                        if hit {
+                            state.paused = true;
+                            stream.pause().ok();
+                        } else {
+                            rewind.capture(&n).ok();
+                        }
+                    } else {
+                        while !n.run_scanline() {
+                            send_samples(&mut n, &mut samples, &audio_send);
+                        }
+
+                        rewind.capture(&n).ok();
                     }
-                    samples.clear();
                 }
             }
 
@@ -86,6 +138,15 @@ pub fn run_nes_thread(nes: Arc<Mutex<Nes>>, channel: Receiver<NesMsg>) -> JoinHa
     })
 }
 
+fn send_samples(nes: &mut Nes, samples: &mut Vec<i16>, audio_send: &Sender<i16>) {
+    nes.apu_samples(samples);
+
+    for s in samples.iter() {
+        audio_send.try_send(*s).ok();
+    }
+    samples.clear();
+}
+
 fn setup_audio(audio_recv: Receiver<i16>) -> (cpal::Stream, SampleRate) {
     let host = cpal::default_host();
     let device = host.default_output_device().unwrap();
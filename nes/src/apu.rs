@@ -1,10 +1,17 @@
 use bincode::{Decode, Encode};
 
 mod blip_buf;
+mod mixer;
 
 use self::blip_buf::BlipBuf;
+use self::mixer::ChannelMixer;
+#[cfg(feature = "debug_tools")]
+use self::mixer::Scopes;
+pub use self::mixer::{ApuChannel, ApuChannelState};
+#[cfg(feature = "debug_tools")]
+use crate::EventKind;
 
-use super::Nes;
+use super::{timing::TimingMode, Nes};
 
 #[derive(Decode, Encode)]
 pub struct Apu {
@@ -18,15 +25,22 @@ pub struct Apu {
     dmc: Dmc,
     frame_counter: FrameCounter,
 
+    mixer: ChannelMixer,
+    #[cfg(feature = "debug_tools")]
+    scopes: Scopes,
+
+    /// Dendy uses the NTSC frame counter and period tables, only PAL differs
+    timing: TimingMode,
+    sample_rate: f64,
+
     // The buffer size needs to be higher if not emptied every scanline
     pub blip_buf: BlipBuf<4096>,
 }
 
 impl Apu {
-    pub const CLOCK_RATE: f64 = 1789772.7272;
     const DEFAULT_SAMPLE_RATE: f64 = 48000.;
 
-    pub(crate) fn new() -> Apu {
+    pub(crate) fn new(timing: TimingMode) -> Apu {
         Apu {
             cycles: 0,
 
@@ -37,10 +51,29 @@ impl Apu {
             dmc: Dmc::new(),
             frame_counter: FrameCounter::new(),
 
-            blip_buf: BlipBuf::new(Self::CLOCK_RATE, Self::DEFAULT_SAMPLE_RATE),
+            mixer: ChannelMixer::new(),
+            #[cfg(feature = "debug_tools")]
+            scopes: Scopes::new(),
+
+            timing,
+            sample_rate: Self::DEFAULT_SAMPLE_RATE,
+
+            blip_buf: BlipBuf::new(timing.cpu_clock_rate(), Self::DEFAULT_SAMPLE_RATE),
         }
     }
 
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.blip_buf
+            .set_rates(self.timing.cpu_clock_rate(), sample_rate);
+    }
+
+    pub(crate) fn set_timing(&mut self, timing: TimingMode) {
+        self.timing = timing;
+        self.cycles = 0;
+        self.set_sample_rate(self.sample_rate);
+    }
+
     fn quarter_frame_clock(&mut self) {
         self.pulse_1.envelope.clock();
         self.pulse_2.envelope.clock();
@@ -55,22 +88,26 @@ impl Apu {
         self.noise.length_counter.clock();
     }
 
+    /// Famicom cartridges can mix their own audio (expansion_audio) with the APU output
     #[inline]
-    fn mix_channels(&mut self) -> i32 {
-        let pulse_1 = self.pulse_1.output() as f64;
-        let pulse_2 = self.pulse_2.output() as f64;
-        let pulse_out = pulse_1 + pulse_2;
+    fn mix_channels(&mut self, expansion_audio: i32) -> i32 {
+        let outputs = self.channel_outputs();
+        #[cfg(feature = "debug_tools")]
+        self.scopes.record(&outputs);
+
+        // Muted channels have a gain of 0
+        let gains = &self.mixer.gains;
+        let [pulse_1, pulse_2, triangle, noise, dmc] =
+            [0, 1, 2, 3, 4].map(|i| outputs[i] as f64 * gains[i]);
 
-        let triangle = self.triangle.output() as f64;
-        let noise = self.noise.output() as f64;
-        let dmc = self.dmc.output() as f64;
+        let pulse_out = pulse_1 + pulse_2;
         let tnd_out = 3. * triangle + 2. * noise + dmc;
 
         // INVESTIGATE: formulas from Mesen, don't know how what the primary source is
         let square_volume = (477600. / (8128.0 / pulse_out + 100.0)) as i32;
         let tnd_volume = (818350. / (24329.0 / tnd_out + 100.0)) as i32;
 
-        square_volume + tnd_volume
+        square_volume + tnd_volume + expansion_audio
     }
 }
 
@@ -96,49 +133,60 @@ impl Nes {
                 a => a - 1,
             };
             self.apu.dmc.sample_buffer = self.cpu_read(addr as usize);
+
+            #[cfg(feature = "debug_tools")]
+            self.debug_events.add_access(
+                EventKind::DmcDma,
+                addr,
+                self.apu.dmc.sample_buffer,
+                self.ppu.scanline,
+                self.ppu.xpos,
+            );
         }
 
         self.apu.cycles = self.apu.cycles.wrapping_add(1);
 
+        let sequence = FrameCounter::sequence(self.apu.timing);
+
         if self.apu.frame_counter.mode {
             match self.apu.cycles {
-                7457 => {
+                c if c == sequence.quarter_1 => {
                     self.apu.quarter_frame_clock();
                 }
-                14913 => {
+                c if c == sequence.half_1 => {
                     self.apu.quarter_frame_clock();
                     self.apu.half_frame_clock();
                 }
-                22371 => {
+                c if c == sequence.quarter_2 => {
                     self.apu.quarter_frame_clock();
                 }
-                37281 => {
+                c if c == sequence.half_2_5step => {
                     self.apu.quarter_frame_clock();
                     self.apu.half_frame_clock();
                 }
-                37282 => {
+                c if c == sequence.half_2_5step + 1 => {
                     self.apu.cycles = 0;
                 }
                 _ => (),
             }
         } else {
             match self.apu.cycles {
-                7457 => {
+                c if c == sequence.quarter_1 => {
                     self.apu.quarter_frame_clock();
                 }
-                14913 => {
+                c if c == sequence.half_1 => {
                     self.apu.quarter_frame_clock();
                     self.apu.half_frame_clock();
                 }
-                22371 => {
+                c if c == sequence.quarter_2 => {
                     self.apu.quarter_frame_clock();
                 }
-                29828 => {
+                c if c == sequence.half_2_4step - 1 => {
                     if !self.apu.frame_counter.irq_inhibit {
                         self.apu.frame_counter.interrupt_flag = true;
                     }
                 }
-                29829 => {
+                c if c == sequence.half_2_4step => {
                     self.apu.quarter_frame_clock();
                     self.apu.half_frame_clock();
 
@@ -146,7 +194,7 @@ impl Nes {
                         self.apu.frame_counter.interrupt_flag = true;
                     }
                 }
-                29830 => {
+                c if c == sequence.half_2_4step + 1 => {
                     if !self.apu.frame_counter.irq_inhibit {
                         self.apu.frame_counter.interrupt_flag = true;
                     }
@@ -160,7 +208,7 @@ impl Nes {
         self.cpu.irq_apu_signal =
             self.apu.frame_counter.interrupt_flag || self.apu.dmc.interrupt_flag;
 
-        let output = self.apu.mix_channels();
+        let output = self.apu.mix_channels(self.mapper.audio_output());
         self.apu.blip_buf.add_sample(output);
     }
 
@@ -180,9 +228,9 @@ impl Nes {
             0x400A => self.apu.triangle.set_timer_low(val),
             0x400B => self.apu.triangle.set_lh(val),
             0x400C => self.apu.noise.set_lcn(val),
-            0x400E => self.apu.noise.set_lp(val),
+            0x400E => self.apu.noise.set_lp(val, self.apu.timing),
             0x400F => self.apu.noise.set_length_counter(val),
-            0x4010 => self.apu.dmc.set_ilf(val),
+            0x4010 => self.apu.dmc.set_ilf(val, self.apu.timing),
             0x4011 => self.apu.dmc.set_direct(val),
             0x4012 => self.apu.dmc.set_sample_address(val),
             0x4013 => self.apu.dmc.set_sample_length(val),
@@ -219,6 +267,14 @@ impl Nes {
     */
     #[inline]
     pub(crate) fn apu_read_status(&mut self) -> u8 {
+        let result = self.apu_peek_status();
+        self.apu.frame_counter.interrupt_flag = false;
+
+        result
+    }
+
+    #[inline]
+    pub(crate) fn apu_peek_status(&self) -> u8 {
         let mut result = 0;
 
         if self.apu.pulse_1.length_counter.counter > 0 {
@@ -249,8 +305,6 @@ impl Nes {
             result |= 0x80;
         }
 
-        self.apu.frame_counter.interrupt_flag = false;
-
         result
     }
 
@@ -348,11 +402,42 @@ impl FrameCounter {
         }
         // INVESTIGATE: reset timer (?) after 3 or 4 CPU clocks
     }
+
+    /// The steps are in CPU cycles, the APU cycles in the wiki table are multiplied by 2
+    fn sequence(timing: TimingMode) -> FrameSequence {
+        match timing {
+            TimingMode::Ntsc | TimingMode::Dendy => FrameSequence {
+                quarter_1: 7457,
+                half_1: 14913,
+                quarter_2: 22371,
+                half_2_4step: 29829,
+                half_2_5step: 37281,
+            },
+            TimingMode::Pal => FrameSequence {
+                quarter_1: 8313,
+                half_1: 16627,
+                quarter_2: 24939,
+                half_2_4step: 33253,
+                half_2_5step: 41565,
+            },
+        }
+    }
+}
+
+/// CPU cycles on which the frame counter steps take place
+struct FrameSequence {
+    quarter_1: u16,
+    half_1: u16,
+    quarter_2: u16,
+    /// The IRQ flag is set one cycle before and one cycle after this step too
+    half_2_4step: u16,
+    half_2_5step: u16,
 }
 
 /// <https://wiki.nesdev.org/w/index.php?title=APU_Pulse>
+/// Also used by the MMC5 expansion audio
 #[derive(Decode, Encode)]
-struct Pulse<const ADDER: u16> {
+pub(crate) struct Pulse<const ADDER: u16> {
     /// At which step of the 8-step sequence it currently is
     seq_cycle: u8,
     /// Which of the 4 8-step duty cycle sequences is currently selected
@@ -364,7 +449,7 @@ struct Pulse<const ADDER: u16> {
 }
 
 impl<const ADDER: u16> Pulse<ADDER> {
-    fn new() -> Pulse<ADDER> {
+    pub(crate) fn new() -> Pulse<ADDER> {
         Pulse {
             seq_cycle: 0,
             waveform: 0,
@@ -380,7 +465,7 @@ impl<const ADDER: u16> Pulse<ADDER> {
     and volume/envelope divider period
     */
     #[inline]
-    fn set_dlcv(&mut self, val: u8) {
+    pub(crate) fn set_dlcv(&mut self, val: u8) {
         self.waveform = (val & 0xC0) >> 3;
         self.length_counter.halt = (val & 0x20) != 0;
         self.envelope._loop = (val & 0x20) != 0;
@@ -397,7 +482,7 @@ impl<const ADDER: u16> Pulse<ADDER> {
     $4002 LLLL.LLLL    Pulse 1 timer Low 8 bits
     */
     #[inline]
-    fn set_timer_low(&mut self, val: u8) {
+    pub(crate) fn set_timer_low(&mut self, val: u8) {
         self.sweep.timer_reload = (self.sweep.timer_reload & !0xFF) | u16::from(val);
     }
 
@@ -408,7 +493,7 @@ impl<const ADDER: u16> Pulse<ADDER> {
     The envelope is also restarted. The period divider is not reset.
     */
     #[inline]
-    fn set_lh(&mut self, val: u8) {
+    pub(crate) fn set_lh(&mut self, val: u8) {
         self.envelope.start = true;
         self.seq_cycle = 0;
         self.length_counter.set_counter((val & 0xF8) >> 3);
@@ -416,7 +501,7 @@ impl<const ADDER: u16> Pulse<ADDER> {
     }
 
     #[inline]
-    fn timer_tick(&mut self) {
+    pub(crate) fn timer_tick(&mut self) {
         if self.sweep.timer > 0 {
             self.sweep.timer -= 1;
         } else {
@@ -436,7 +521,26 @@ impl<const ADDER: u16> Pulse<ADDER> {
     }
 
     #[inline]
-    fn half_frame_clock(&mut self) {
+    pub(crate) fn quarter_frame_clock(&mut self) {
+        self.envelope.clock();
+    }
+
+    /// Used by the $4015 status register (and $5015 on the MMC5)
+    #[inline]
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.length_counter.counter = 0;
+        }
+        self.length_counter.enabled = enabled;
+    }
+
+    #[inline]
+    pub(crate) fn length_counter_active(&self) -> bool {
+        self.length_counter.counter > 0
+    }
+
+    #[inline]
+    pub(crate) fn half_frame_clock(&mut self) {
         self.length_counter.clock();
         self.sweep.clock();
     }
@@ -456,19 +560,27 @@ impl<const ADDER: u16> Pulse<ADDER> {
         true,  true,  true,  true,  true,  true,  false, false,
     ];
 
+    /*
+    The mixer receives the current envelope volume except when The sequencer output is zero,
+    or overflow from the sweep unit's adder is silencing the channel, or the length counter is
+    zero, or the timer has a value less than eight.
+    */
     #[inline]
-    fn output(&self) -> u8 {
+    pub(crate) fn output(&self) -> u8 {
+        if self.sweep.timer >= 8 && self.sweep.period < 0x800 {
+            self.sequencer_output()
+        } else {
+            0
+        }
+    }
+
+    /// The output without the muting of the sweep unit. The MMC5 pulse channels don't have
+    /// a sweep unit, so they keep playing (ultrasonic) tones with a period lower than 8.
+    #[inline]
+    pub(crate) fn sequencer_output(&self) -> u8 {
         let active = Self::DUTY_SEQUENCE[(self.waveform | self.seq_cycle) as usize];
-        /*
-        The mixer receives the current envelope volume except when The sequencer output is zero,
-        or overflow from the sweep unit's adder is silencing the channel, or the length counter is
-        zero, or the timer has a value less than eight.
-        */
-        if active
-            && self.length_counter.counter > 0
-            && self.sweep.timer >= 8
-            && self.sweep.period < 0x800
-        {
+
+        if active && self.length_counter.counter > 0 {
             self.envelope.get_volume()
         } else {
             0
@@ -665,14 +777,23 @@ impl Noise {
         4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
     ];
 
+    const TIMER_PERIODS_PAL: [u16; 0x10] = [
+        4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+    ];
+
     /*
     $400E L--- PPPP   Mode flag, noise period
     */
     #[inline]
-    fn set_lp(&mut self, val: u8) {
+    fn set_lp(&mut self, val: u8, timing: TimingMode) {
         self.shift_feedback_mode = (val & 0x80) != 0;
+
+        let periods = match timing {
+            TimingMode::Pal => &Self::TIMER_PERIODS_PAL,
+            TimingMode::Ntsc | TimingMode::Dendy => &Self::TIMER_PERIODS,
+        };
         // INVESTIGATE: according to MESEN this should be minus one ?
-        self.timer_period_reload = Self::TIMER_PERIODS[(val & 0xF) as usize] - 1;
+        self.timer_period_reload = periods[(val & 0xF) as usize] - 1;
     }
 
     /*
@@ -788,20 +909,28 @@ impl Dmc {
         428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
     ];
 
+    const FREQ_TABLE_PAL: [u16; 16] = [
+        398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+    ];
+
     /*
     $4010 IL-- FFFF    IRQ enable, loop sample, frequency index
 
     IRQ enabled flag. If clear, the interrupt flag is cleared.
     */
     #[inline]
-    fn set_ilf(&mut self, val: u8) {
+    fn set_ilf(&mut self, val: u8, timing: TimingMode) {
         self.irq_enable = (val & 0x80) != 0;
         if !self.irq_enable {
             self.interrupt_flag = false;
         }
 
         self.loop_enable = (val & 0x40) != 0;
-        self.timer_reload = Self::FREQ_TABLE[(val & 0xF) as usize];
+        let freq_table = match timing {
+            TimingMode::Pal => &Self::FREQ_TABLE_PAL,
+            TimingMode::Ntsc | TimingMode::Dendy => &Self::FREQ_TABLE,
+        };
+        self.timer_reload = freq_table[(val & 0xF) as usize];
     }
 
     /*
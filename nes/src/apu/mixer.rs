@@ -0,0 +1,292 @@
+use std::fmt;
+
+use bincode::{Decode, Encode};
+
+use super::Apu;
+use crate::Nes;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Decode, Encode)]
+pub enum ApuChannel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+impl ApuChannel {
+    pub const ALL: [ApuChannel; 5] = [
+        ApuChannel::Pulse1,
+        ApuChannel::Pulse2,
+        ApuChannel::Triangle,
+        ApuChannel::Noise,
+        ApuChannel::Dmc,
+    ];
+}
+
+impl fmt::Display for ApuChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ApuChannel::Pulse1 => "Pulse 1",
+            ApuChannel::Pulse2 => "Pulse 2",
+            ApuChannel::Triangle => "Triangle",
+            ApuChannel::Noise => "Noise",
+            ApuChannel::Dmc => "DMC",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Per-channel volume and muting, applied before the channels are mixed.
+/// These are user settings, so they aren't a part of the savestates.
+#[derive(Clone)]
+pub(crate) struct ChannelMixer {
+    volumes: [f64; 5],
+    enabled: [bool; 5],
+    /// Only this channel can be heard
+    solo: Option<ApuChannel>,
+    /// The resulting multiplier of each channel
+    pub(crate) gains: [f64; 5],
+}
+
+impl ChannelMixer {
+    pub(crate) fn new() -> Self {
+        Self {
+            volumes: [1.; 5],
+            enabled: [true; 5],
+            solo: None,
+            gains: [1.; 5],
+        }
+    }
+
+    fn update_gains(&mut self) {
+        for channel in ApuChannel::ALL {
+            let i = channel as usize;
+            let audible = match self.solo {
+                Some(solo) => solo == channel,
+                None => self.enabled[i],
+            };
+
+            self.gains[i] = if audible { self.volumes[i] } else { 0. };
+        }
+    }
+}
+
+/// Number of samples kept for each oscilloscope
+#[cfg(feature = "debug_tools")]
+pub(crate) const SCOPE_LEN: usize = 1024;
+
+/// The last outputs of the channels, taken every SCOPE_PERIOD CPU cycles.
+/// They aren't a part of the savestates either.
+#[cfg(feature = "debug_tools")]
+#[derive(Clone)]
+pub(crate) struct Scopes {
+    samples: [[u8; SCOPE_LEN]; 5],
+    pos: usize,
+    counter: u8,
+}
+
+#[cfg(feature = "debug_tools")]
+impl Scopes {
+    const SCOPE_PERIOD: u8 = 16;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            samples: [[0; SCOPE_LEN]; 5],
+            pos: 0,
+            counter: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record(&mut self, outputs: &[u8; 5]) {
+        self.counter += 1;
+        if self.counter < Self::SCOPE_PERIOD {
+            return;
+        }
+        self.counter = 0;
+
+        for (samples, output) in self.samples.iter_mut().zip(outputs) {
+            samples[self.pos] = *output;
+        }
+        self.pos = (self.pos + 1) % SCOPE_LEN;
+    }
+}
+
+impl_not_saved!(ChannelMixer);
+#[cfg(feature = "debug_tools")]
+impl_not_saved!(Scopes);
+
+/// The state of an APU channel, for debugging
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ApuChannelState {
+    /// The timer period. For the noise and the DMC it's in CPU cycles.
+    pub period: u16,
+    /// The frequency of the tone in Hz, None for the noise and the DMC
+    pub frequency: Option<f64>,
+    /// 0 - 3 (12.5%, 25%, 50%, 25% negated), None if it isn't a pulse channel
+    pub duty: Option<u8>,
+    /// The envelope or constant volume, None for the triangle and the DMC
+    pub volume: Option<u8>,
+    pub constant_volume: bool,
+    /// None for the DMC
+    pub length_counter: Option<u8>,
+    pub length_counter_halted: bool,
+    /// The value sent to the mixer, 0 - 15 (0 - 127 for the DMC)
+    pub output: u8,
+}
+
+impl ApuChannelState {
+    const NOTE_NAMES: [&'static str; 12] = [
+        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+    ];
+
+    /// The closest note in the equal temperament (A4 = 440 Hz), like "A4" or "C#5"
+    pub fn note(&self) -> Option<String> {
+        let frequency = self.frequency?;
+        // The range of a piano, the rest can't be heard properly anyway
+        if !(27.5..=4186.01).contains(&frequency) {
+            return None;
+        }
+
+        // MIDI note numbers, 69 is A4
+        let midi = (69. + 12. * (frequency / 440.).log2()).round() as usize;
+        Some(format!("{}{}", Self::NOTE_NAMES[midi % 12], midi / 12 - 1))
+    }
+}
+
+impl Apu {
+    #[inline]
+    pub(crate) fn channel_outputs(&self) -> [u8; 5] {
+        [
+            self.pulse_1.output(),
+            self.pulse_2.output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc.output(),
+        ]
+    }
+}
+
+impl Nes {
+    /// The channel settings and the scopes aren't a part of the savestates,
+    /// this keeps the ones of the Nes that is replaced by a loaded state
+    pub fn keep_channel_settings(&mut self, previous: &Nes) {
+        self.apu.mixer = previous.apu.mixer.clone();
+        #[cfg(feature = "debug_tools")]
+        {
+            self.apu.scopes = previous.apu.scopes.clone();
+        }
+    }
+
+    /// A disabled channel is muted
+    pub fn set_channel_enabled(&mut self, channel: ApuChannel, enabled: bool) {
+        self.apu.mixer.enabled[channel as usize] = enabled;
+        self.apu.mixer.update_gains();
+    }
+
+    pub fn channel_enabled(&self, channel: ApuChannel) -> bool {
+        self.apu.mixer.enabled[channel as usize]
+    }
+
+    /// Only the solo channel can be heard, whether the others are enabled or not
+    pub fn set_channel_solo(&mut self, channel: Option<ApuChannel>) {
+        self.apu.mixer.solo = channel;
+        self.apu.mixer.update_gains();
+    }
+
+    pub fn channel_solo(&self) -> Option<ApuChannel> {
+        self.apu.mixer.solo
+    }
+
+    /// 0.0 - 1.0
+    pub fn set_channel_volume(&mut self, channel: ApuChannel, volume: f64) {
+        self.apu.mixer.volumes[channel as usize] = volume.clamp(0., 1.);
+        self.apu.mixer.update_gains();
+    }
+
+    pub fn channel_volume(&self, channel: ApuChannel) -> f64 {
+        self.apu.mixer.volumes[channel as usize]
+    }
+
+    pub fn channel_state(&self, channel: ApuChannel) -> ApuChannelState {
+        let apu = &self.apu;
+        let cpu_clock = self.timing.cpu_clock_rate();
+
+        match channel {
+            ApuChannel::Pulse1 | ApuChannel::Pulse2 => {
+                let (envelope, sweep, length_counter, waveform, output) = match channel {
+                    ApuChannel::Pulse1 => (
+                        &apu.pulse_1.envelope,
+                        apu.pulse_1.sweep.timer_reload,
+                        &apu.pulse_1.length_counter,
+                        apu.pulse_1.waveform,
+                        apu.pulse_1.output(),
+                    ),
+                    _ => (
+                        &apu.pulse_2.envelope,
+                        apu.pulse_2.sweep.timer_reload,
+                        &apu.pulse_2.length_counter,
+                        apu.pulse_2.waveform,
+                        apu.pulse_2.output(),
+                    ),
+                };
+
+                ApuChannelState {
+                    period: sweep,
+                    frequency: Some(cpu_clock / (16. * (sweep as f64 + 1.))),
+                    duty: Some(waveform >> 3),
+                    volume: Some(envelope.get_volume()),
+                    constant_volume: envelope.constant_volume,
+                    length_counter: Some(length_counter.counter),
+                    length_counter_halted: length_counter.halt,
+                    output,
+                }
+            }
+            ApuChannel::Triangle => ApuChannelState {
+                period: apu.triangle.timer_reload,
+                frequency: Some(cpu_clock / (32. * (apu.triangle.timer_reload as f64 + 1.))),
+                duty: None,
+                volume: None,
+                constant_volume: false,
+                length_counter: Some(apu.triangle.length_counter.counter),
+                length_counter_halted: apu.triangle.length_counter.halt,
+                output: apu.triangle.output(),
+            },
+            ApuChannel::Noise => ApuChannelState {
+                period: apu.noise.timer_period_reload + 1,
+                frequency: None,
+                duty: None,
+                volume: Some(apu.noise.envelope.get_volume()),
+                constant_volume: apu.noise.envelope.constant_volume,
+                length_counter: Some(apu.noise.length_counter.counter),
+                length_counter_halted: apu.noise.length_counter.halt,
+                output: apu.noise.output(),
+            },
+            ApuChannel::Dmc => ApuChannelState {
+                period: apu.dmc.timer_reload,
+                frequency: None,
+                duty: None,
+                volume: None,
+                constant_volume: false,
+                length_counter: None,
+                length_counter_halted: false,
+                output: apu.dmc.output(),
+            },
+        }
+    }
+
+    /// The last SCOPE_LEN outputs of a channel (taken every 16 CPU cycles), oldest first
+    #[cfg(feature = "debug_tools")]
+    pub fn channel_scope(&self, channel: ApuChannel) -> Vec<u8> {
+        let scopes = &self.apu.scopes;
+        let samples = &scopes.samples[channel as usize];
+
+        samples[scopes.pos..]
+            .iter()
+            .chain(&samples[..scopes.pos])
+            .copied()
+            .collect()
+    }
+}
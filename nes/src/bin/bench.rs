@@ -36,7 +36,7 @@ fn game_bench(rom_path: &str, inputs_path: &str) {
             }
         }
 
-        nes.set_button_state(ic.button, ic.state);
+        nes.set_player_button_state(ic.player, ic.button, ic.state);
     }
 
     while nes.frame_count() < inputs.end_frame {
@@ -3,17 +3,29 @@ use std::{convert::TryFrom, fmt::Display, str::FromStr};
 use crate::{ppu::Mirroring, NesError};
 
 use bincode::{Decode, Encode};
+use md5::Md5;
+use sha1::{Digest, Sha1};
 
 mod gamedb;
 
 const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+/// Offset of $7000 in PRG RAM
+const TRAINER_START: usize = 0x1000;
 
 #[derive(Decode, Encode)]
 pub struct Cartridge {
     pub header: Header,
     prg_rom: Vec<u8>,
     prg_wram: Option<Vec<u8>>,
+    /// Some mappers (Namco 163) have internal RAM which is battery-backed together with
+    /// the PRG RAM. It's stored at the end of prg_wram, so it ends up in the same .sav file.
+    mapper_ram_size: usize,
     chr: Vec<u8>,
+    /// SHA-1 of the PRG ROM and CHR ROM, the same as the ROM SHA-1 of the game database
+    pub(crate) rom_sha1: [u8; 20],
+    /// MD5 of the PRG ROM and CHR ROM, used by FCEUX movies
+    pub(crate) rom_md5: [u8; 16],
 }
 
 impl Cartridge {
@@ -26,6 +38,14 @@ impl Cartridge {
 
         let header = Header::from_ines(header)?;
 
+        let (trainer, rom) = match header.trainer {
+            true => {
+                let trainer = rom.get(0..TRAINER_SIZE).ok_or(NesError::RomCorrupted)?;
+                (Some(trainer), &rom[TRAINER_SIZE..])
+            }
+            false => (None, rom),
+        };
+
         let prg_end = header.prg_rom_size as usize;
         let prg_portion = rom.get(0..prg_end).ok_or(NesError::RomCorrupted)?;
 
@@ -36,15 +56,42 @@ impl Cartridge {
             None
         };
 
-        let header = Header::from_prg_chr(prg_portion, chr_portion)?.unwrap_or(header);
+        let chr_data = chr_portion.unwrap_or_default();
+        let rom_sha1 = Sha1::new()
+            .chain_update(prg_portion)
+            .chain_update(chr_data)
+            .finalize()
+            .into();
+        let rom_md5 = Md5::new()
+            .chain_update(prg_portion)
+            .chain_update(chr_data)
+            .finalize()
+            .into();
+
+        let mut header = Header::from_prg_chr(prg_portion, chr_portion)?.unwrap_or(header);
+        // The game database describes the original cartridge, the trainer is a property of the dump
+        header.trainer = trainer.is_some();
 
         let prg_rom = Vec::from(rom.get(0..prg_end).ok_or(NesError::RomCorrupted)?);
-        let prg_wram = match (header.prg_ram_size, header.prg_nvram_size) {
+        let mut prg_wram = match (header.prg_ram_size, header.prg_nvram_size) {
             (Some(size), None) | (None, Some(size)) => Some(vec![0; size as usize]),
             (None, None) => None,
             (Some(_), Some(_)) => return Err(NesError::ChrRomAndRamUnsupported),
         };
 
+        /*
+        The trainer is loaded into $7000-$71FF before the game starts.
+        Cartridges with a trainer need PRG RAM even if the header doesn't say so.
+        */
+        if let Some(trainer) = trainer {
+            let prg_ram = prg_wram.get_or_insert_with(Vec::new);
+            if prg_ram.len() < TRAINER_START + TRAINER_SIZE {
+                prg_ram.resize(BankSize::Kb8 as usize, 0);
+            }
+
+            prg_ram[TRAINER_START..TRAINER_START + TRAINER_SIZE].copy_from_slice(trainer);
+        }
+
         // TODO: handle games with both CHR RAM and ROM (also fix has_chr_ram()...)
         let chr = match (header.chr_ram_size, header.chr_rom_size) {
             // Unwrap should be safe because header.chr_rom_size is Some(_)...
@@ -58,16 +105,15 @@ impl Cartridge {
             return Err(NesError::ConsoleUnsupported(header.console_typ));
         };
 
-        if header.region != Region::Ntsc && header.region != Region::Multi {
-            return Err(NesError::RegionUnsupported(header.region));
-        };
-
         Ok(Cartridge {
             header,
 
             prg_rom,
             prg_wram,
+            mapper_ram_size: 0,
             chr,
+            rom_sha1,
+            rom_md5,
         })
     }
 
@@ -85,6 +131,15 @@ impl Cartridge {
         self.prg_rom[addr]
     }
 
+    pub(crate) fn prg_rom_len(&self) -> usize {
+        self.prg_rom.len()
+    }
+
+    /// Patches the PRG ROM, for debugging
+    pub(crate) fn poke_prg_rom(&mut self, addr: usize, val: u8) {
+        self.prg_rom[addr] = val;
+    }
+
     #[inline]
     pub(crate) fn read_prg_ram(&self, addr: usize) -> Option<u8> {
         self.prg_wram.as_ref().map(|prg_ram| prg_ram[addr])
@@ -97,6 +152,61 @@ impl Cartridge {
         }
     }
 
+    /// The battery-backed PRG RAM (followed by the internal RAM of the mapper),
+    /// None if the cartridge doesn't have a battery.
+    /// The contents are laid out the same way as the raw .sav files of other emulators.
+    pub(crate) fn battery_ram(&self) -> Option<&[u8]> {
+        match self.header.battery {
+            true => self.prg_wram.as_deref(),
+            false => None,
+        }
+    }
+
+    pub(crate) fn load_battery_ram(&mut self, save: &[u8]) -> Result<(), NesError> {
+        match (self.header.battery, &mut self.prg_wram) {
+            (true, Some(prg_ram)) if prg_ram.len() == save.len() => {
+                prg_ram.copy_from_slice(save);
+                Ok(())
+            }
+            _ => Err(NesError::InvalidBatterySave),
+        }
+    }
+
+    /// Size of the whole PRG RAM (volatile or battery-backed) in bytes
+    #[inline]
+    pub(crate) fn prg_ram_len(&self) -> usize {
+        self.prg_wram.as_ref().map_or(0, |prg_ram| prg_ram.len()) - self.mapper_ram_size
+    }
+
+    /// Reserves the internal RAM of the mapper after the PRG RAM
+    pub(crate) fn add_mapper_ram(&mut self, size: usize) {
+        let prg_ram = self.prg_wram.get_or_insert_with(Vec::new);
+        prg_ram.resize(prg_ram.len() + size, 0);
+        self.mapper_ram_size += size;
+    }
+
+    #[inline]
+    pub(crate) fn read_mapper_ram(&self, addr: usize) -> u8 {
+        let prg_ram_len = self.prg_ram_len();
+        self.prg_wram
+            .as_ref()
+            .map_or(0, |ram| ram[prg_ram_len + addr])
+    }
+
+    #[inline]
+    pub(crate) fn write_mapper_ram(&mut self, addr: usize, val: u8) {
+        let prg_ram_len = self.prg_ram_len();
+        if let Some(ref mut ram) = self.prg_wram {
+            ram[prg_ram_len + addr] = val
+        }
+    }
+
+    /// Size of the CHR ROM or CHR RAM in bytes
+    #[inline]
+    pub(crate) fn chr_len(&self) -> usize {
+        self.chr.len()
+    }
+
     #[inline]
     pub(crate) fn read_chr(&self, addr: usize) -> u8 {
         self.chr[addr]
@@ -110,6 +220,11 @@ impl Cartridge {
         }
     }
 
+    /// Writes to the CHR RAM, or patches the CHR ROM, for debugging
+    pub(crate) fn poke_chr(&mut self, addr: usize, val: u8) {
+        self.chr[addr] = val;
+    }
+
     #[inline]
     pub fn prg_rom_count(&self, unit: BankSize) -> u32 {
         Self::ceil_div(self.header.prg_rom_size, unit)
@@ -184,7 +299,8 @@ pub struct Header {
 
     // TODO(low): https://wiki.nesdev.org/w/index.php?title=NES_2.0#Default_Expansion_Device
     pub expansion: u32,
-    // TODO(low): 10 mostly homebrew or compatibility hacks games have a trainer
+    /// Mostly homebrew or compatibility hacks have a 512-byte trainer, which is loaded at $7000
+    pub trainer: bool,
 }
 
 const NES_CONSTANT: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
@@ -205,16 +321,14 @@ impl Header {
         };
 
         let battery = ines[6] & 2 != 0;
-        if ines[6] & (1 << 2) != 0 {
-            return Err(NesError::TrainerUnsupported);
-        }
-
-        let mapper = u32::from((ines[6] >> 4) | (ines[7] & 0xF0));
+        let trainer = ines[6] & (1 << 2) != 0;
 
         if ines[7] & 0xC == 0x8 {
-            return Err(NesError::Ines2Unsupported);
+            return Self::from_ines2(ines, mirroring, battery, trainer);
         }
 
+        let mapper = u32::from((ines[6] >> 4) | (ines[7] & 0xF0));
+
         let (chr_rom_size, chr_ram_size) = match ines[5] {
             0 => (None, Some(BankSize::Kb8 as u32)),
             cnt => (Some(cnt as u32 * BankSize::Kb8 as u32), None),
@@ -245,14 +359,163 @@ impl Header {
             mapper,
             submapper: 0,
             battery,
+            trainer,
             console_typ,
             region: Region::try_from(ines[9])?,
             expansion: 1,
         })
     }
+
+    /** <https://wiki.nesdev.org/w/index.php?title=NES_2.0#Header> **/
+    fn from_ines2(
+        ines: &[u8],
+        mirroring: Mirroring,
+        battery: bool,
+        trainer: bool,
+    ) -> Result<Self, NesError> {
+        /*
+        Byte 8 - Mapper MSB/Submapper
+        7654 3210
+        ---------
+        SSSS NNNN
+        |||| ++++- Mapper number D8..D11
+        ++++------ Submapper number
+        */
+        let mapper =
+            u32::from(ines[6] >> 4) | u32::from(ines[7] & 0xF0) | (u32::from(ines[8] & 0xF) << 8);
+        let submapper = u32::from(ines[8] >> 4);
+
+        /*
+        Byte 9 - PRG-ROM/CHR-ROM size MSB
+        7654 3210
+        ---------
+        CCCC PPPP
+        |||| ++++- PRG-ROM size MSB
+        ++++------ CHR-ROM size MSB
+        */
+        let prg_rom_size = Self::ines2_rom_size(ines[4], ines[9] & 0xF, BankSize::Kb16)?;
+        let chr_rom_size = Self::ines2_rom_size(ines[5], ines[9] >> 4, BankSize::Kb8)?;
+
+        /*
+        Byte 10 - PRG-RAM/EEPROM size
+        Byte 11 - CHR-RAM size
+        7654 3210
+        ---------
+        pppp PPPP
+        |||| ++++- (CHR-)RAM (volatile) shift count
+        ++++------ (CHR-)NVRAM/EEPROM (non-volatile) shift count
+        If the shift count is zero, there is no RAM.
+        If the shift count is non-zero, the actual size is "64 << shift count" bytes.
+        */
+        let prg_ram_size = Self::ines2_ram_size(ines[10] & 0xF);
+        let prg_nvram_size = Self::ines2_ram_size(ines[10] >> 4);
+
+        // We don't distinguish between CHR RAM and CHR NVRAM, so add them together
+        let chr_ram_size = match (
+            Self::ines2_ram_size(ines[11] & 0xF),
+            Self::ines2_ram_size(ines[11] >> 4),
+        ) {
+            (Some(ram), Some(nvram)) => Some(ram + nvram),
+            (ram, nvram) => ram.or(nvram),
+        };
+
+        // Some headers don't specify the CHR RAM size, assume the usual 8KB like with iNES 1
+        let (chr_rom_size, chr_ram_size) = match (chr_rom_size, chr_ram_size) {
+            (0, None) => (None, Some(BankSize::Kb8 as u32)),
+            (0, chr_ram_size) => (None, chr_ram_size),
+            (chr_rom_size, chr_ram_size) => (Some(chr_rom_size), chr_ram_size),
+        };
+
+        let console_typ = match ines[7] & 3 {
+            0 => ConsoleType::Standard,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice,
+            3 => ConsoleType::Extended,
+            _ => unreachable!(),
+        };
+
+        /*
+        Byte 12 - CPU/PPU Timing
+        7654 3210
+        ---------
+        .... ..VV
+               ++- CPU/PPU timing mode
+                    0: RP2C02 ("NTSC NES")
+                    1: RP2C07 ("Licensed PAL NES")
+                    2: Multiple-region
+                    3: UMC 6527P ("Dendy")
+        */
+        let region = match ines[12] & 3 {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::Multi,
+            3 => Region::Dendy,
+            _ => unreachable!(),
+        };
+
+        /*
+        Byte 15 - Default Expansion Device
+        7654 3210
+        ---------
+        ..DD DDDD
+          ++-++++- Default Expansion Device
+        */
+        let expansion = u32::from(ines[15] & 0x3F);
+
+        Ok(Header {
+            mirroring,
+            source: HeaderSource::Ines2,
+            name: String::from(""),
+            prg_rom_size,
+            chr_rom_size,
+            chr_ram_size,
+            prg_ram_size,
+            prg_nvram_size,
+            mapper,
+            submapper,
+            battery,
+            trainer,
+            console_typ,
+            region,
+            expansion,
+        })
+    }
+
+    /*
+    If the MSB nibble is $F, an exponent-multiplier notation is used:
+    ++++----------- Header byte 9 D0..D3 / D4..D7
+    ||||
+    |||| ++++-++++- Header byte 4 / 5
+    D~BA98 7654 3210
+    --------------
+    1111 EEEE EEMM
+         |||| ||++- Multiplier, actual value is MM*2+1 (1,3,5,7)
+         ++++-++--- Exponent (2^E), 0-63
+
+    The actual ROM size is (2^E)*(MM*2+1) bytes.
+    */
+    fn ines2_rom_size(lsb: u8, msb: u8, unit: BankSize) -> Result<u32, NesError> {
+        if msb == 0xF {
+            let exponent = u32::from(lsb >> 2);
+            let multiplier = u32::from(lsb & 3) * 2 + 1;
+
+            2u32.checked_pow(exponent)
+                .and_then(|size| size.checked_mul(multiplier))
+                .ok_or(NesError::InvalidInesFormat)
+        } else {
+            Ok(((u32::from(msb) << 8) | u32::from(lsb)) * unit as u32)
+        }
+    }
+
+    fn ines2_ram_size(shift: u8) -> Option<u32> {
+        match shift {
+            0 => None,
+            shift => Some(64 << shift),
+        }
+    }
 }
 
-#[derive(Decode, Encode)]
+#[derive(Debug, PartialEq, Decode, Encode)]
 pub enum HeaderSource {
     Ines1,
     Ines2,
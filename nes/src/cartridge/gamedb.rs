@@ -137,6 +137,8 @@ impl Header {
             submapper,
             mirroring,
             battery,
+            // Filled in by Cartridge::from_rom
+            trainer: false,
 
             console_typ,
             region,
@@ -162,5 +164,3 @@ impl Header {
             .map_err(|_| NesError::GameDbFormat)
     }
 }
-
-// TODO: write tests for iNES 2.0 parsing
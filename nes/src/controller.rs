@@ -1,54 +1,53 @@
 use bincode::{Decode, Encode};
 
+/**
+The standard controller is a 8-bit parallel-to-serial shift register.
+The button state is latched from the player's buttons when the strobe goes low.
+
+<https://wiki.nesdev.org/w/index.php?title=Standard_controller>
+**/
 #[derive(Decode, Encode)]
 pub struct Controller {
-    strobe: bool,
     shifter: u8,
-    state: u8,
 }
 
 impl Controller {
     pub fn new() -> Controller {
-        Controller {
-            strobe: false,
-            shifter: 0,
-            state: 0,
-        }
+        Controller { shifter: 0 }
     }
 
     #[inline]
-    pub(crate) fn write_reg(&mut self, val: u8) {
-        if self.strobe && (val & 1) == 0 {
-            self.shifter = self.state;
-        }
-
-        self.strobe = (val & 1) != 0;
+    pub(crate) fn latch(&mut self, buttons: u8) {
+        self.shifter = buttons;
     }
 
+    /// While the strobe is high, the controller keeps reloading the shifter and returns the A button
     #[inline]
-    pub(crate) fn read_reg(&mut self) -> u8 {
-        if self.strobe {
-            return self.state & 1;
+    pub(crate) fn read(&mut self, strobe: bool, buttons: u8) -> u8 {
+        if strobe {
+            return buttons & 1;
         }
 
         let key = self.shifter & 1;
+        // Official controllers return 1 after the 8 buttons were read
         self.shifter = 0x80 | (self.shifter >> 1);
 
         key
     }
+}
 
-    #[inline]
-    pub fn set_button(&mut self, keycode: Button, state: bool) {
-        self.state = match keycode {
-            Button::A => (self.state & !1) | (state as u8),
-            Button::B => (self.state & !(1 << 1)) | (state as u8) << 1,
-            Button::Select => (self.state & !(1 << 2)) | (state as u8) << 2,
-            Button::Start => (self.state & !(1 << 3)) | (state as u8) << 3,
-            Button::Up => (self.state & !(1 << 4)) | (state as u8) << 4,
-            Button::Down => (self.state & !(1 << 5)) | (state as u8) << 5,
-            Button::Left => (self.state & !(1 << 6)) | (state as u8) << 6,
-            Button::Right => (self.state & !(1 << 7)) | (state as u8) << 7,
-        }
+/// Sets the button's bit in the report order of the standard controller
+#[inline]
+pub(crate) fn set_button(buttons: u8, keycode: Button, state: bool) -> u8 {
+    match keycode {
+        Button::A => (buttons & !1) | (state as u8),
+        Button::B => (buttons & !(1 << 1)) | (state as u8) << 1,
+        Button::Select => (buttons & !(1 << 2)) | (state as u8) << 2,
+        Button::Start => (buttons & !(1 << 3)) | (state as u8) << 3,
+        Button::Up => (buttons & !(1 << 4)) | (state as u8) << 4,
+        Button::Down => (buttons & !(1 << 5)) | (state as u8) << 5,
+        Button::Left => (buttons & !(1 << 6)) | (state as u8) << 6,
+        Button::Right => (buttons & !(1 << 7)) | (state as u8) << 7,
     }
 }
 
@@ -65,6 +64,18 @@ pub enum Button {
 }
 
 impl Button {
+    /// All of the buttons in the report order of the standard controller
+    pub const ALL: [Button; 8] = [
+        Button::A,
+        Button::B,
+        Button::Select,
+        Button::Start,
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+    ];
+
     pub fn name(self) -> &'static str {
         match self {
             Button::A => "A",
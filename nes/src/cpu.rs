@@ -1,7 +1,7 @@
 use bincode::{Decode, Encode};
 
 #[cfg(feature = "debug_tools")]
-use crate::EventKind;
+use crate::{debugger::Access, EventKind};
 
 use super::Nes;
 
@@ -77,7 +77,33 @@ pub struct Cpu {
     copy_buffer: u8,
     dma_cycles: u16,
 
-    ram: [u8; RAM_SIZE],
+    pub(crate) ram: [u8; RAM_SIZE],
+}
+
+/// A snapshot of the CPU registers for debugging tools
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CpuRegisters {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    /// Address of the next instruction
+    pub pc: u16,
+    pub sp: u8,
+    /**
+    7  bit  0
+    ---- ----
+    NV1B DIZC
+    |||| ||||
+    |||| |||+- Carry
+    |||| ||+-- Zero
+    |||| |+--- Interrupt Disable
+    |||| +---- Decimal
+    |||+------ Always 0 outside of the pushed copy
+    ||+------- Always 1
+    |+-------- Overflow
+    +--------- Negative
+    **/
+    pub p: u8,
 }
 
 impl Cpu {
@@ -136,16 +162,34 @@ impl Nes {
 
     #[inline]
     pub(crate) fn cpu_read(&mut self, index: usize) -> u8 {
+        #[cfg(feature = "debug_tools")]
+        self.access_log.record(index, Access::Read);
+
         self.cpu.open_bus = match index {
-            0x4020..=0xFFFF => self.mapper.cpu_read(index).unwrap_or(self.cpu.open_bus),
+            0x4020..=0xFFFF => self
+                .mapper
+                .cpu_read(index, &mut self.cpu.irq_mapper_signal)
+                .unwrap_or(self.cpu.open_bus),
             0..=0x1FFF => self.cpu.ram[index & 0x7FF],
             0x2000..=0x3FFF => self.ppu_read_reg(index),
-            0x4000..=0x4014 | 0x4017..=0x401F => self.cpu.open_bus,
-            0x4016 => (self.cpu.open_bus & 0xE0) | self.controller.read_reg(),
+            0x4000..=0x4014 | 0x4018..=0x401F => self.cpu.open_bus,
+            0x4016 => (self.cpu.open_bus & 0xE0) | self.inputs.read_reg(0, &self.ppu),
+            0x4017 => (self.cpu.open_bus & 0xE0) | self.inputs.read_reg(1, &self.ppu),
             0x4015 => self.apu_read_status(),
             _ => unreachable!("memory access into unmapped address: 0x{:X}", index),
         };
 
+        #[cfg(feature = "debug_tools")]
+        if let 0x2000..=0x3FFF = index {
+            self.debug_events.add_access(
+                EventKind::PpuRegisterRead,
+                index as u16,
+                self.cpu.open_bus,
+                self.ppu.scanline,
+                self.ppu.xpos,
+            );
+        }
+
         self.cpu.db = self.cpu.open_bus;
         if let DmaHijack::Request = self.cpu.hijack_read {
             self.cpu.hijack_read = DmaHijack::Hijacked;
@@ -156,16 +200,34 @@ impl Nes {
 
     #[inline]
     pub(crate) fn cpu_write(&mut self, index: usize, val: u8) {
+        #[cfg(feature = "debug_tools")]
+        {
+            self.access_log.record(index, Access::Write);
+
+            if let Some(kind) = EventKind::from_write(index) {
+                self.debug_events.add_access(
+                    kind,
+                    index as u16,
+                    val,
+                    self.ppu.scanline,
+                    self.ppu.xpos,
+                );
+            }
+        }
+
         match index {
             0..=0x1FFF => self.cpu.ram[index & 0x7FF] = val,
-            0x2000..=0x3FFF => self.ppu_write_reg(index, val),
+            0x2000..=0x3FFF => {
+                self.mapper.notify_ppu_reg_write(index, val);
+                self.ppu_write_reg(index, val);
+            }
             0x4000..=0x4013 => self.apu_write_reg(index, val),
             0x4014 => {
                 self.cpu.hijack_read = DmaHijack::Request;
                 self.cpu.dma_addr = (val as u16) << 8;
             }
             0x4015 => self.apu_write_reg(index, val),
-            0x4016 => self.controller.write_reg(val),
+            0x4016 => self.inputs.write_reg(val),
             0x4017 => self.apu_write_reg(index, val),
             0x4018..=0x401F => (),
             0x4020..=0xFFFF => self.mapper.cpu_write(
@@ -178,6 +240,63 @@ impl Nes {
         }
     }
 
+    pub fn cpu_registers(&self) -> CpuRegisters {
+        // The opcode of the next instruction has already been fetched, unless an interrupt
+        // is taken instead
+        let pc = match self.cpu.take_interrupt {
+            true => self.cpu.pc,
+            false => self.cpu.pc.wrapping_sub(1),
+        };
+
+        CpuRegisters {
+            a: self.cpu.a,
+            x: self.cpu.x,
+            y: self.cpu.y,
+            pc,
+            sp: self.cpu.sp,
+            p: self.cpu_status(false),
+        }
+    }
+
+    /// False while an OAM DMA is running
+    #[cfg(feature = "debug_tools")]
+    pub(crate) fn cpu_at_instruction_boundary(&self) -> bool {
+        self.cpu.dma_cycles == 0 && !matches!(self.cpu.hijack_read, DmaHijack::Hijacked)
+    }
+
+    /// The next "instruction" is the interrupt sequence
+    #[cfg(feature = "debug_tools")]
+    pub(crate) fn cpu_interrupt_pending(&self) -> bool {
+        self.cpu.take_interrupt
+    }
+
+    /// Reads a byte from the CPU address space without any side effects
+    /// (clearing the vblank flag, reading the controllers, acknowledging IRQs...).
+    /// The controller ports and the write-only registers read as open bus.
+    pub fn peek_cpu(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+
+        match addr {
+            0..=0x1FFF => self.cpu.ram[addr & 0x7FF],
+            0x2000..=0x3FFF => self.ppu_peek_reg(addr),
+            0x4015 => self.apu_peek_status(),
+            0x4000..=0x401F => self.cpu.open_bus,
+            _ => self.mapper.peek_cpu(addr).unwrap_or(self.cpu.open_bus),
+        }
+    }
+
+    /// Writes a byte to the internal RAM or to the cartridge's PRG RAM ($6000 - $7FFF),
+    /// bypassing the write protection of the mapper. Writes to the registers and ROM are ignored.
+    pub fn poke_cpu(&mut self, addr: u16, val: u8) {
+        let addr = addr as usize;
+
+        match addr {
+            0..=0x1FFF => self.cpu.ram[addr & 0x7FF] = val,
+            0x6000..=0x7FFF => self.mapper.poke_prg_ram(addr, val),
+            _ => (),
+        }
+    }
+
     //https://forums.nesdev.org/viewtopic.php?f=3&t=14120
     #[inline]
     fn dma(&mut self) {
@@ -2066,6 +2185,10 @@ impl Nes {
             self.cpu.cached_irq = false;
             self.cpu.take_interrupt = true;
             self.cpu.interrupt_type = InterruptType::Irq;
+
+            #[cfg(feature = "debug_tools")]
+            self.debug_events
+                .add(EventKind::Irq, self.ppu.scanline, self.ppu.xpos);
         }
 
         if self.cpu.cached_nmi {
@@ -2073,14 +2196,10 @@ impl Nes {
             self.cpu.nmi_signal = false;
             self.cpu.take_interrupt = true;
             self.cpu.interrupt_type = InterruptType::Nmi;
-        }
 
-        #[cfg(feature = "debug_tools")]
-        {
-            if let InterruptType::Irq = self.cpu.interrupt_type {
-                self.debug_events
-                    .add(EventKind::Irq, self.ppu.scanline, self.ppu.xpos);
-            }
+            #[cfg(feature = "debug_tools")]
+            self.debug_events
+                .add(EventKind::Nmi, self.ppu.scanline, self.ppu.xpos);
         }
     }
 
@@ -2131,6 +2250,12 @@ impl Nes {
 
     #[inline]
     fn push_status(&mut self, brk_php: bool) {
+        let status = self.cpu_status(brk_php);
+        self.cpu_write(self.cpu.ab as usize, status);
+    }
+
+    #[inline]
+    fn cpu_status(&self, brk_php: bool) -> u8 {
         let mut status: u8 = 1 << 5;
         status |= (if self.cpu.n { 1 } else { 0 }) << 7;
         status |= (if self.cpu.v { 1 } else { 0 }) << 6;
@@ -2139,7 +2264,7 @@ impl Nes {
         status |= (if self.cpu.i { 1 } else { 0 }) << 2;
         status |= (if self.cpu.z { 1 } else { 0 }) << 1;
         status |= if self.cpu.c { 1 } else { 0 };
-        self.cpu_write(self.cpu.ab as usize, status);
+        status
     }
 
     #[inline]
@@ -4,6 +4,8 @@ use bincode::{Decode, Encode};
 pub struct DebugEvents {
     events: [Vec<DebugEvent>; 2],
     current_frame: u8,
+    /// A bit per EventKind
+    enabled: u16,
 }
 
 impl DebugEvents {
@@ -11,20 +13,41 @@ impl DebugEvents {
         Self {
             events: [Vec::with_capacity(128), Vec::with_capacity(128)],
             current_frame: 0,
+            enabled: u16::MAX,
         }
     }
 
     pub fn add(&mut self, kind: EventKind, scanline: u16, xpos: u16) {
+        self.add_access(kind, 0, 0, scanline, xpos);
+    }
+
+    pub fn add_access(&mut self, kind: EventKind, addr: u16, val: u8, scanline: u16, xpos: u16) {
+        if !self.is_enabled(kind) {
+            return;
+        }
+
         let e = DebugEvent {
             kind,
+            addr,
+            val,
             scanline,
             xpos,
         };
 
-        // TODO: have some sort of filter
         self.events[self.current_frame as usize].push(e);
     }
 
+    pub fn set_enabled(&mut self, kind: EventKind, enabled: bool) {
+        match enabled {
+            true => self.enabled |= kind.mask(),
+            false => self.enabled &= !kind.mask(),
+        }
+    }
+
+    pub fn is_enabled(&self, kind: EventKind) -> bool {
+        self.enabled & kind.mask() != 0
+    }
+
     pub fn on_frame_ended(&mut self) {
         if self.current_frame == 0 {
             self.current_frame = 1;
@@ -44,19 +67,74 @@ impl DebugEvents {
 #[derive(Encode, Decode)]
 pub struct DebugEvent {
     pub kind: EventKind,
+    /// The register (or the DMA source address), 0 for the other events
+    pub addr: u16,
+    /// The value written or read, 0 for the other events
+    pub val: u8,
     pub scanline: u16,
     pub xpos: u16,
 }
 
-#[derive(Encode, Decode, Debug)]
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EventKind {
     Irq,
+    Nmi,
+    PpuRegisterRead,
+    PpuRegisterWrite,
+    ApuRegisterWrite,
+    MapperRegisterWrite,
+    OamDma,
+    DmcDma,
+    Sprite0Hit,
+    SpriteOverflow,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 10] = [
+        EventKind::Irq,
+        EventKind::Nmi,
+        EventKind::PpuRegisterRead,
+        EventKind::PpuRegisterWrite,
+        EventKind::ApuRegisterWrite,
+        EventKind::MapperRegisterWrite,
+        EventKind::OamDma,
+        EventKind::DmcDma,
+        EventKind::Sprite0Hit,
+        EventKind::SpriteOverflow,
+    ];
+
+    /// The event of a CPU write, if the address is a register.
+    /// $6000 - $7FFF is left out, it's PRG RAM on most cartridges.
+    pub(crate) fn from_write(addr: usize) -> Option<EventKind> {
+        let kind = match addr {
+            0x2000..=0x3FFF => EventKind::PpuRegisterWrite,
+            0x4000..=0x4013 | 0x4015 | 0x4017 => EventKind::ApuRegisterWrite,
+            0x4014 => EventKind::OamDma,
+            0x4020..=0x5FFF | 0x8000..=0xFFFF => EventKind::MapperRegisterWrite,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    fn mask(self) -> u16 {
+        1 << self as u16
+    }
 }
 
 impl ToString for EventKind {
     fn to_string(&self) -> String {
         match self {
             EventKind::Irq => "IRQ".to_owned(),
+            EventKind::Nmi => "NMI".to_owned(),
+            EventKind::PpuRegisterRead => "PPU register read".to_owned(),
+            EventKind::PpuRegisterWrite => "PPU register write".to_owned(),
+            EventKind::ApuRegisterWrite => "APU register write".to_owned(),
+            EventKind::MapperRegisterWrite => "Mapper register write".to_owned(),
+            EventKind::OamDma => "OAM DMA".to_owned(),
+            EventKind::DmcDma => "DMC DMA".to_owned(),
+            EventKind::Sprite0Hit => "Sprite 0 hit".to_owned(),
+            EventKind::SpriteOverflow => "Sprite overflow".to_owned(),
         }
     }
 }
@@ -0,0 +1,551 @@
+use std::{fmt, io, str::FromStr};
+
+use bincode::{Decode, Encode};
+
+use crate::{CpuRegisters, Nes, NesError, TraceLogger};
+
+const JSR: u8 = 0x20;
+const RTS: u8 = 0x60;
+const RTI: u8 = 0x40;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Encode, Decode)]
+pub enum Access {
+    Execute,
+    Read,
+    Write,
+}
+
+/// The CPU bus accesses of the current instruction, only recorded while the debugger is running.
+/// It isn't a part of the savestates.
+pub(crate) struct AccessLog {
+    enabled: bool,
+    accesses: Vec<(u16, Access)>,
+}
+
+impl_not_saved!(AccessLog);
+
+impl AccessLog {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            accesses: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn record(&mut self, addr: usize, access: Access) {
+        if self.enabled {
+            self.accesses.push((addr as u16, access));
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    Pc,
+    /// The whole status register
+    P,
+    N,
+    V,
+    D,
+    I,
+    Z,
+    C,
+}
+
+impl Register {
+    fn value(self, regs: &CpuRegisters) -> u16 {
+        let flag = |bit: u8| ((regs.p >> bit) & 1) as u16;
+
+        match self {
+            Register::A => regs.a as u16,
+            Register::X => regs.x as u16,
+            Register::Y => regs.y as u16,
+            Register::Sp => regs.sp as u16,
+            Register::Pc => regs.pc,
+            Register::P => regs.p as u16,
+            Register::N => flag(7),
+            Register::V => flag(6),
+            Register::D => flag(3),
+            Register::I => flag(2),
+            Register::Z => flag(1),
+            Register::C => flag(0),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Register::A => "A",
+            Register::X => "X",
+            Register::Y => "Y",
+            Register::Sp => "SP",
+            Register::Pc => "PC",
+            Register::P => "P",
+            Register::N => "N",
+            Register::V => "V",
+            Register::D => "D",
+            Register::I => "I",
+            Register::Z => "Z",
+            Register::C => "C",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+/// A comparison of a register with a value, for example "A == $10" or "C != 0".
+/// The flags (N, V, D, I, Z, C) are 0 or 1.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn is_met(&self, regs: &CpuRegisters) -> bool {
+        let reg = self.register.value(regs);
+
+        match self.comparison {
+            Comparison::Eq => reg == self.value,
+            Comparison::Ne => reg != self.value,
+            Comparison::Lt => reg < self.value,
+            Comparison::Le => reg <= self.value,
+            Comparison::Gt => reg > self.value,
+            Comparison::Ge => reg >= self.value,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = NesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is_op = |c: char| "=!<>".contains(c);
+
+        let op_start = s.find(is_op).ok_or(NesError::InvalidCondition)?;
+        let op_len = s[op_start..]
+            .find(|c| !is_op(c))
+            .ok_or(NesError::InvalidCondition)?;
+
+        let register = match s[..op_start].trim().to_ascii_uppercase().as_str() {
+            "A" => Register::A,
+            "X" => Register::X,
+            "Y" => Register::Y,
+            "SP" | "S" => Register::Sp,
+            "PC" => Register::Pc,
+            "P" => Register::P,
+            "N" => Register::N,
+            "V" => Register::V,
+            "D" => Register::D,
+            "I" => Register::I,
+            "Z" => Register::Z,
+            "C" => Register::C,
+            _ => return Err(NesError::InvalidCondition),
+        };
+
+        let comparison = match &s[op_start..op_start + op_len] {
+            "==" | "=" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            _ => return Err(NesError::InvalidCondition),
+        };
+
+        let value = parse_value(&s[op_start + op_len..]).ok_or(NesError::InvalidCondition)?;
+
+        Ok(Self {
+            register,
+            comparison,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ${:X}",
+            self.register.name(),
+            self.comparison.symbol(),
+            self.value
+        )
+    }
+}
+
+/// Hexadecimal with a '$' or '0x' prefix, decimal otherwise
+fn parse_value(s: &str) -> Option<u16> {
+    let s = s.trim();
+
+    match s.strip_prefix('$').or_else(|| s.strip_prefix("0x")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Execution breakpoints are checked before the instruction is executed.
+/// Read and write watchpoints are checked after the instruction that accessed the address,
+/// all of the CPU bus accesses count (dummy reads, opcode fetches, OAM DMA...).
+#[derive(Clone, Debug)]
+pub struct Breakpoint {
+    pub start: u16,
+    /// Inclusive
+    pub end: u16,
+    pub execute: bool,
+    pub read: bool,
+    pub write: bool,
+    /// Only break if the condition is met
+    pub condition: Option<Condition>,
+    pub enabled: bool,
+}
+
+impl Breakpoint {
+    /// A PC breakpoint
+    pub fn execute(addr: u16) -> Self {
+        Self {
+            start: addr,
+            end: addr,
+            execute: true,
+            read: false,
+            write: false,
+            condition: None,
+            enabled: true,
+        }
+    }
+
+    pub fn watch(start: u16, end: u16, read: bool, write: bool) -> Self {
+        Self {
+            start,
+            end,
+            execute: false,
+            read,
+            write,
+            condition: None,
+            enabled: true,
+        }
+    }
+
+    fn triggers(&self, addr: u16, access: Access, regs: &CpuRegisters) -> bool {
+        let kind = match access {
+            Access::Execute => self.execute,
+            Access::Read => self.read,
+            Access::Write => self.write,
+        };
+
+        self.enabled
+            && kind
+            && (self.start..=self.end).contains(&addr)
+            && self.condition.is_none_or(|c| c.is_met(regs))
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.start == self.end {
+            true => write!(f, "${:04X}", self.start)?,
+            false => write!(f, "${:04X}-${:04X}", self.start, self.end)?,
+        }
+
+        let flags = [(self.read, 'R'), (self.write, 'W'), (self.execute, 'X')];
+        let flags: String = flags
+            .iter()
+            .map(|&(set, c)| if set { c } else { '-' })
+            .collect();
+        write!(f, " {}", flags)?;
+
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BreakReason {
+    Breakpoint {
+        /// Index into `Debugger::breakpoints`
+        index: usize,
+        addr: u16,
+        access: Access,
+    },
+    /// A step finished
+    Step,
+}
+
+enum Step {
+    None,
+    /// Run until the subroutine returns to `addr`
+    Over {
+        addr: u16,
+        sp: u8,
+    },
+    /// Run until a RTS or RTI pops the stack above `sp`
+    Out {
+        sp: u8,
+    },
+}
+
+/**
+Breakpoints and stepping by whole instructions.
+
+The debugger isn't a part of the Nes, so it survives loading savestates. While it has
+some breakpoints or a step in progress, `Debugger::run_frame` should be used instead of
+`Nes::run_frame`. After a break, the next run continues without breaking on the current
+instruction.
+**/
+pub struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+    step: Step,
+    /// The current instruction was already stopped at
+    resuming: bool,
+    last_break: Option<BreakReason>,
+    trace: Option<TraceLogger>,
+    /// Tracing stops when the log can't be written
+    trace_error: Option<io::Error>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            step: Step::None,
+            resuming: false,
+            last_break: None,
+            trace: None,
+            trace_error: None,
+        }
+    }
+
+    /// True if `run_frame` has to be used
+    pub fn is_active(&self) -> bool {
+        self.is_stepping() || self.is_tracing() || self.breakpoints.iter().any(|bp| bp.enabled)
+    }
+
+    /// Every instruction executed by the debugger is logged until `stop_trace` is called
+    pub fn start_trace(&mut self, logger: TraceLogger) {
+        self.trace = Some(logger);
+        self.trace_error = None;
+    }
+
+    /// Flushes the trace log and closes it
+    pub fn stop_trace(&mut self) -> io::Result<()> {
+        match self.trace.take() {
+            Some(mut trace) => trace.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.trace.is_some()
+    }
+
+    /// The error that stopped the trace, if any
+    pub fn take_trace_error(&mut self) -> Option<io::Error> {
+        self.trace_error.take()
+    }
+
+    fn log_trace(&mut self, nes: &Nes) {
+        // Interrupt sequences aren't instructions
+        if nes.cpu_interrupt_pending() {
+            return;
+        }
+
+        if let Some(trace) = &mut self.trace {
+            if let Err(e) = trace.log(nes) {
+                self.trace = None;
+                self.trace_error = Some(e);
+            }
+        }
+    }
+
+    /// A step over or step out is waiting for the subroutine to return
+    pub fn is_stepping(&self) -> bool {
+        !matches!(self.step, Step::None)
+    }
+
+    pub fn cancel_step(&mut self) {
+        self.step = Step::None;
+    }
+
+    /// The reason of the last break, until `resume` is called
+    pub fn last_break(&self) -> Option<BreakReason> {
+        self.last_break
+    }
+
+    pub fn resume(&mut self) {
+        self.last_break = None;
+    }
+
+    /// Runs until the end of the frame, returns early if a breakpoint is hit or a step finishes
+    pub fn run_frame(&mut self, nes: &mut Nes) -> Option<BreakReason> {
+        nes.access_log.enabled = self.breakpoints.iter().any(|bp| bp.read || bp.write);
+        let result = self.run_until_break(nes);
+
+        nes.access_log.enabled = false;
+        nes.access_log.accesses.clear();
+
+        if let Some(reason) = result {
+            self.step = Step::None;
+            self.resuming = true;
+            self.last_break = Some(reason);
+        }
+
+        result
+    }
+
+    fn run_until_break(&mut self, nes: &mut Nes) -> Option<BreakReason> {
+        loop {
+            if !self.resuming && !nes.cpu_interrupt_pending() {
+                let regs = nes.cpu_registers();
+
+                if let Some(reason) = self.check_breakpoints(&[(regs.pc, Access::Execute)], nes) {
+                    return Some(reason);
+                }
+
+                if let Step::Over { addr, sp } = self.step {
+                    if regs.pc == addr && regs.sp >= sp {
+                        return Some(BreakReason::Step);
+                    }
+                }
+            }
+            self.resuming = false;
+
+            let opcode = self.next_opcode(nes);
+            self.log_trace(nes);
+            nes.access_log.accesses.clear();
+            let frame_ended = nes.run_instruction();
+
+            if let Some(reason) = self.check_breakpoints(&nes.access_log.accesses, nes) {
+                return Some(reason);
+            }
+
+            if let Step::Out { sp } = self.step {
+                if (opcode == Some(RTS) || opcode == Some(RTI)) && nes.cpu.sp > sp {
+                    return Some(BreakReason::Step);
+                }
+            }
+
+            if frame_ended {
+                return None;
+            }
+        }
+    }
+
+    fn check_breakpoints(&self, accesses: &[(u16, Access)], nes: &Nes) -> Option<BreakReason> {
+        let regs = nes.cpu_registers();
+
+        for &(addr, access) in accesses {
+            let index = self
+                .breakpoints
+                .iter()
+                .position(|bp| bp.triggers(addr, access, &regs));
+
+            if let Some(index) = index {
+                return Some(BreakReason::Breakpoint {
+                    index,
+                    addr,
+                    access,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// None if an interrupt is taken instead
+    fn next_opcode(&self, nes: &Nes) -> Option<u8> {
+        match nes.cpu_interrupt_pending() {
+            true => None,
+            false => Some(nes.cpu.current_instruction),
+        }
+    }
+
+    /// Executes a single instruction (or the interrupt sequence)
+    pub fn step_into(&mut self, nes: &mut Nes) {
+        self.log_trace(nes);
+        nes.run_instruction();
+
+        self.step = Step::None;
+        self.resuming = true;
+        self.last_break = Some(BreakReason::Step);
+    }
+
+    /// Executes a single instruction. If it's a JSR, the step continues until the subroutine
+    /// returns, so the emulation has to keep running with `run_frame`.
+    pub fn step_over(&mut self, nes: &mut Nes) {
+        match self.next_opcode(nes) {
+            Some(JSR) => {
+                let regs = nes.cpu_registers();
+                self.step = Step::Over {
+                    addr: regs.pc.wrapping_add(3),
+                    sp: regs.sp,
+                };
+                self.resuming = true;
+                self.last_break = None;
+            }
+            _ => self.step_into(nes),
+        }
+    }
+
+    /// Runs until the current subroutine (or interrupt handler) returns, the emulation has
+    /// to keep running with `run_frame`.
+    pub fn step_out(&mut self, nes: &Nes) {
+        self.step = Step::Out {
+            sp: nes.cpu_registers().sp,
+        };
+        self.resuming = true;
+        self.last_break = None;
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Nes {
+    /// Runs a whole instruction, or the whole OAM DMA if the instruction started one.
+    /// Returns true if a frame has ended.
+    pub(crate) fn run_instruction(&mut self) -> bool {
+        self.cpu_tick();
+        while !self.cpu_at_instruction_boundary() {
+            self.cpu_tick();
+        }
+
+        let frame_ready = self.frame_ready;
+        if frame_ready {
+            self.on_frame_ended();
+        }
+        frame_ready
+    }
+}
@@ -0,0 +1,490 @@
+use std::fmt;
+
+use crate::Nes;
+
+use AddressingMode::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AddressingMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+impl AddressingMode {
+    /// Number of operand bytes following the opcode
+    pub fn operand_len(self) -> u16 {
+        match self {
+            Implied | Accumulator => 0,
+            Immediate | ZeroPage | ZeroPageX | ZeroPageY | IndirectX | IndirectY | Relative => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub mode: AddressingMode,
+    /// Base cycle count, 0 for the opcodes that halt the CPU
+    pub cycles: u8,
+    /// Takes 1 more cycle when the indexed address crosses a page.
+    /// Branches take 1 more cycle when taken, and 2 when the target is on another page.
+    pub page_cycle: bool,
+    /// Unofficial opcode
+    pub illegal: bool,
+}
+
+impl Opcode {
+    const fn new(mnemonic: &'static str, mode: AddressingMode, cycles: u8) -> Self {
+        Self {
+            mnemonic,
+            mode,
+            cycles,
+            page_cycle: false,
+            illegal: false,
+        }
+    }
+
+    const fn page(mut self) -> Self {
+        self.page_cycle = true;
+        self
+    }
+
+    const fn illegal(mut self) -> Self {
+        self.illegal = true;
+        self
+    }
+}
+
+const fn op(mnemonic: &'static str, mode: AddressingMode, cycles: u8) -> Opcode {
+    Opcode::new(mnemonic, mode, cycles)
+}
+
+const fn kil() -> Opcode {
+    Opcode::new("KIL", Implied, 0).illegal()
+}
+
+/*
+The unofficial mnemonics are the ones used by nestest.log
+https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+*/
+pub static OPCODES: [Opcode; 256] = [
+    // 0x00
+    op("BRK", Implied, 7),
+    op("ORA", IndirectX, 6),
+    kil(),
+    op("SLO", IndirectX, 8).illegal(),
+    op("NOP", ZeroPage, 3).illegal(),
+    op("ORA", ZeroPage, 3),
+    op("ASL", ZeroPage, 5),
+    op("SLO", ZeroPage, 5).illegal(),
+    op("PHP", Implied, 3),
+    op("ORA", Immediate, 2),
+    op("ASL", Accumulator, 2),
+    op("ANC", Immediate, 2).illegal(),
+    op("NOP", Absolute, 4).illegal(),
+    op("ORA", Absolute, 4),
+    op("ASL", Absolute, 6),
+    op("SLO", Absolute, 6).illegal(),
+    // 0x10
+    op("BPL", Relative, 2).page(),
+    op("ORA", IndirectY, 5).page(),
+    kil(),
+    op("SLO", IndirectY, 8).illegal(),
+    op("NOP", ZeroPageX, 4).illegal(),
+    op("ORA", ZeroPageX, 4),
+    op("ASL", ZeroPageX, 6),
+    op("SLO", ZeroPageX, 6).illegal(),
+    op("CLC", Implied, 2),
+    op("ORA", AbsoluteY, 4).page(),
+    op("NOP", Implied, 2).illegal(),
+    op("SLO", AbsoluteY, 7).illegal(),
+    op("NOP", AbsoluteX, 4).page().illegal(),
+    op("ORA", AbsoluteX, 4).page(),
+    op("ASL", AbsoluteX, 7),
+    op("SLO", AbsoluteX, 7).illegal(),
+    // 0x20
+    op("JSR", Absolute, 6),
+    op("AND", IndirectX, 6),
+    kil(),
+    op("RLA", IndirectX, 8).illegal(),
+    op("BIT", ZeroPage, 3),
+    op("AND", ZeroPage, 3),
+    op("ROL", ZeroPage, 5),
+    op("RLA", ZeroPage, 5).illegal(),
+    op("PLP", Implied, 4),
+    op("AND", Immediate, 2),
+    op("ROL", Accumulator, 2),
+    op("ANC", Immediate, 2).illegal(),
+    op("BIT", Absolute, 4),
+    op("AND", Absolute, 4),
+    op("ROL", Absolute, 6),
+    op("RLA", Absolute, 6).illegal(),
+    // 0x30
+    op("BMI", Relative, 2).page(),
+    op("AND", IndirectY, 5).page(),
+    kil(),
+    op("RLA", IndirectY, 8).illegal(),
+    op("NOP", ZeroPageX, 4).illegal(),
+    op("AND", ZeroPageX, 4),
+    op("ROL", ZeroPageX, 6),
+    op("RLA", ZeroPageX, 6).illegal(),
+    op("SEC", Implied, 2),
+    op("AND", AbsoluteY, 4).page(),
+    op("NOP", Implied, 2).illegal(),
+    op("RLA", AbsoluteY, 7).illegal(),
+    op("NOP", AbsoluteX, 4).page().illegal(),
+    op("AND", AbsoluteX, 4).page(),
+    op("ROL", AbsoluteX, 7),
+    op("RLA", AbsoluteX, 7).illegal(),
+    // 0x40
+    op("RTI", Implied, 6),
+    op("EOR", IndirectX, 6),
+    kil(),
+    op("SRE", IndirectX, 8).illegal(),
+    op("NOP", ZeroPage, 3).illegal(),
+    op("EOR", ZeroPage, 3),
+    op("LSR", ZeroPage, 5),
+    op("SRE", ZeroPage, 5).illegal(),
+    op("PHA", Implied, 3),
+    op("EOR", Immediate, 2),
+    op("LSR", Accumulator, 2),
+    op("ALR", Immediate, 2).illegal(),
+    op("JMP", Absolute, 3),
+    op("EOR", Absolute, 4),
+    op("LSR", Absolute, 6),
+    op("SRE", Absolute, 6).illegal(),
+    // 0x50
+    op("BVC", Relative, 2).page(),
+    op("EOR", IndirectY, 5).page(),
+    kil(),
+    op("SRE", IndirectY, 8).illegal(),
+    op("NOP", ZeroPageX, 4).illegal(),
+    op("EOR", ZeroPageX, 4),
+    op("LSR", ZeroPageX, 6),
+    op("SRE", ZeroPageX, 6).illegal(),
+    op("CLI", Implied, 2),
+    op("EOR", AbsoluteY, 4).page(),
+    op("NOP", Implied, 2).illegal(),
+    op("SRE", AbsoluteY, 7).illegal(),
+    op("NOP", AbsoluteX, 4).page().illegal(),
+    op("EOR", AbsoluteX, 4).page(),
+    op("LSR", AbsoluteX, 7),
+    op("SRE", AbsoluteX, 7).illegal(),
+    // 0x60
+    op("RTS", Implied, 6),
+    op("ADC", IndirectX, 6),
+    kil(),
+    op("RRA", IndirectX, 8).illegal(),
+    op("NOP", ZeroPage, 3).illegal(),
+    op("ADC", ZeroPage, 3),
+    op("ROR", ZeroPage, 5),
+    op("RRA", ZeroPage, 5).illegal(),
+    op("PLA", Implied, 4),
+    op("ADC", Immediate, 2),
+    op("ROR", Accumulator, 2),
+    op("ARR", Immediate, 2).illegal(),
+    op("JMP", Indirect, 5),
+    op("ADC", Absolute, 4),
+    op("ROR", Absolute, 6),
+    op("RRA", Absolute, 6).illegal(),
+    // 0x70
+    op("BVS", Relative, 2).page(),
+    op("ADC", IndirectY, 5).page(),
+    kil(),
+    op("RRA", IndirectY, 8).illegal(),
+    op("NOP", ZeroPageX, 4).illegal(),
+    op("ADC", ZeroPageX, 4),
+    op("ROR", ZeroPageX, 6),
+    op("RRA", ZeroPageX, 6).illegal(),
+    op("SEI", Implied, 2),
+    op("ADC", AbsoluteY, 4).page(),
+    op("NOP", Implied, 2).illegal(),
+    op("RRA", AbsoluteY, 7).illegal(),
+    op("NOP", AbsoluteX, 4).page().illegal(),
+    op("ADC", AbsoluteX, 4).page(),
+    op("ROR", AbsoluteX, 7),
+    op("RRA", AbsoluteX, 7).illegal(),
+    // 0x80
+    op("NOP", Immediate, 2).illegal(),
+    op("STA", IndirectX, 6),
+    op("NOP", Immediate, 2).illegal(),
+    op("SAX", IndirectX, 6).illegal(),
+    op("STY", ZeroPage, 3),
+    op("STA", ZeroPage, 3),
+    op("STX", ZeroPage, 3),
+    op("SAX", ZeroPage, 3).illegal(),
+    op("DEY", Implied, 2),
+    op("NOP", Immediate, 2).illegal(),
+    op("TXA", Implied, 2),
+    op("XAA", Immediate, 2).illegal(),
+    op("STY", Absolute, 4),
+    op("STA", Absolute, 4),
+    op("STX", Absolute, 4),
+    op("SAX", Absolute, 4).illegal(),
+    // 0x90
+    op("BCC", Relative, 2).page(),
+    op("STA", IndirectY, 6),
+    kil(),
+    op("AHX", IndirectY, 6).illegal(),
+    op("STY", ZeroPageX, 4),
+    op("STA", ZeroPageX, 4),
+    op("STX", ZeroPageY, 4),
+    op("SAX", ZeroPageY, 4).illegal(),
+    op("TYA", Implied, 2),
+    op("STA", AbsoluteY, 5),
+    op("TXS", Implied, 2),
+    op("TAS", AbsoluteY, 5).illegal(),
+    op("SHY", AbsoluteX, 5).illegal(),
+    op("STA", AbsoluteX, 5),
+    op("SHX", AbsoluteY, 5).illegal(),
+    op("AHX", AbsoluteY, 5).illegal(),
+    // 0xA0
+    op("LDY", Immediate, 2),
+    op("LDA", IndirectX, 6),
+    op("LDX", Immediate, 2),
+    op("LAX", IndirectX, 6).illegal(),
+    op("LDY", ZeroPage, 3),
+    op("LDA", ZeroPage, 3),
+    op("LDX", ZeroPage, 3),
+    op("LAX", ZeroPage, 3).illegal(),
+    op("TAY", Implied, 2),
+    op("LDA", Immediate, 2),
+    op("TAX", Implied, 2),
+    op("LAX", Immediate, 2).illegal(),
+    op("LDY", Absolute, 4),
+    op("LDA", Absolute, 4),
+    op("LDX", Absolute, 4),
+    op("LAX", Absolute, 4).illegal(),
+    // 0xB0
+    op("BCS", Relative, 2).page(),
+    op("LDA", IndirectY, 5).page(),
+    kil(),
+    op("LAX", IndirectY, 5).page().illegal(),
+    op("LDY", ZeroPageX, 4),
+    op("LDA", ZeroPageX, 4),
+    op("LDX", ZeroPageY, 4),
+    op("LAX", ZeroPageY, 4).illegal(),
+    op("CLV", Implied, 2),
+    op("LDA", AbsoluteY, 4).page(),
+    op("TSX", Implied, 2),
+    op("LAS", AbsoluteY, 4).page().illegal(),
+    op("LDY", AbsoluteX, 4).page(),
+    op("LDA", AbsoluteX, 4).page(),
+    op("LDX", AbsoluteY, 4).page(),
+    op("LAX", AbsoluteY, 4).page().illegal(),
+    // 0xC0
+    op("CPY", Immediate, 2),
+    op("CMP", IndirectX, 6),
+    op("NOP", Immediate, 2).illegal(),
+    op("DCP", IndirectX, 8).illegal(),
+    op("CPY", ZeroPage, 3),
+    op("CMP", ZeroPage, 3),
+    op("DEC", ZeroPage, 5),
+    op("DCP", ZeroPage, 5).illegal(),
+    op("INY", Implied, 2),
+    op("CMP", Immediate, 2),
+    op("DEX", Implied, 2),
+    op("AXS", Immediate, 2).illegal(),
+    op("CPY", Absolute, 4),
+    op("CMP", Absolute, 4),
+    op("DEC", Absolute, 6),
+    op("DCP", Absolute, 6).illegal(),
+    // 0xD0
+    op("BNE", Relative, 2).page(),
+    op("CMP", IndirectY, 5).page(),
+    kil(),
+    op("DCP", IndirectY, 8).illegal(),
+    op("NOP", ZeroPageX, 4).illegal(),
+    op("CMP", ZeroPageX, 4),
+    op("DEC", ZeroPageX, 6),
+    op("DCP", ZeroPageX, 6).illegal(),
+    op("CLD", Implied, 2),
+    op("CMP", AbsoluteY, 4).page(),
+    op("NOP", Implied, 2).illegal(),
+    op("DCP", AbsoluteY, 7).illegal(),
+    op("NOP", AbsoluteX, 4).page().illegal(),
+    op("CMP", AbsoluteX, 4).page(),
+    op("DEC", AbsoluteX, 7),
+    op("DCP", AbsoluteX, 7).illegal(),
+    // 0xE0
+    op("CPX", Immediate, 2),
+    op("SBC", IndirectX, 6),
+    op("NOP", Immediate, 2).illegal(),
+    op("ISB", IndirectX, 8).illegal(),
+    op("CPX", ZeroPage, 3),
+    op("SBC", ZeroPage, 3),
+    op("INC", ZeroPage, 5),
+    op("ISB", ZeroPage, 5).illegal(),
+    op("INX", Implied, 2),
+    op("SBC", Immediate, 2),
+    op("NOP", Implied, 2),
+    op("SBC", Immediate, 2).illegal(),
+    op("CPX", Absolute, 4),
+    op("SBC", Absolute, 4),
+    op("INC", Absolute, 6),
+    op("ISB", Absolute, 6).illegal(),
+    // 0xF0
+    op("BEQ", Relative, 2).page(),
+    op("SBC", IndirectY, 5).page(),
+    kil(),
+    op("ISB", IndirectY, 8).illegal(),
+    op("NOP", ZeroPageX, 4).illegal(),
+    op("SBC", ZeroPageX, 4),
+    op("INC", ZeroPageX, 6),
+    op("ISB", ZeroPageX, 6).illegal(),
+    op("SED", Implied, 2),
+    op("SBC", AbsoluteY, 4).page(),
+    op("NOP", Implied, 2).illegal(),
+    op("ISB", AbsoluteY, 7).illegal(),
+    op("NOP", AbsoluteX, 4).page().illegal(),
+    op("SBC", AbsoluteX, 4).page(),
+    op("INC", AbsoluteX, 7),
+    op("ISB", AbsoluteX, 7).illegal(),
+];
+
+/// A decoded instruction
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Instruction {
+    pub addr: u16,
+    pub opcode: u8,
+    /// The operand bytes, little-endian
+    pub operand: u16,
+    /// Offset into the PRG ROM, None if the instruction isn't in ROM
+    pub prg_offset: Option<usize>,
+}
+
+impl Instruction {
+    pub fn info(&self) -> &'static Opcode {
+        &OPCODES[self.opcode as usize]
+    }
+
+    /// Size in bytes
+    pub fn size(&self) -> u16 {
+        1 + self.info().mode.operand_len()
+    }
+
+    /// The opcode followed by the operand bytes
+    pub fn bytes(&self) -> Vec<u8> {
+        let [lo, hi] = self.operand.to_le_bytes();
+        [self.opcode, lo, hi][..self.size() as usize].to_vec()
+    }
+
+    /// Address of the next instruction in memory
+    pub fn next_addr(&self) -> u16 {
+        self.addr.wrapping_add(self.size())
+    }
+
+    /// The address written in the operand, with branch offsets already applied
+    pub fn target(&self) -> Option<u16> {
+        match self.info().mode {
+            Implied | Accumulator | Immediate => None,
+            Relative => Some(
+                self.next_addr()
+                    .wrapping_add(self.operand as u8 as i8 as u16),
+            ),
+            _ => Some(self.operand),
+        }
+    }
+
+    /// Formats the instruction, replacing the target address with its label if there is one
+    pub fn format_with_labels<'a>(&self, label: impl Fn(u16) -> Option<&'a str>) -> String {
+        let info = self.info();
+        let addr = |width: usize| match self.target().and_then(&label) {
+            Some(label) => label.to_string(),
+            None => format!("${:01$X}", self.target().unwrap_or(0), width),
+        };
+
+        let operand = match info.mode {
+            Implied => String::new(),
+            Accumulator => " A".to_string(),
+            Immediate => format!(" #${:02X}", self.operand),
+            ZeroPage => format!(" {}", addr(2)),
+            ZeroPageX => format!(" {},X", addr(2)),
+            ZeroPageY => format!(" {},Y", addr(2)),
+            Absolute | Relative => format!(" {}", addr(4)),
+            AbsoluteX => format!(" {},X", addr(4)),
+            AbsoluteY => format!(" {},Y", addr(4)),
+            Indirect => format!(" ({})", addr(4)),
+            IndirectX => format!(" ({},X)", addr(2)),
+            IndirectY => format!(" ({}),Y", addr(2)),
+        };
+
+        format!("{}{}", info.mnemonic, operand)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format_with_labels(|_| None))
+    }
+}
+
+impl Nes {
+    /// Decodes the instruction at addr, as currently mapped in the CPU address space.
+    /// The memory is read without side effects.
+    pub fn disassemble(&self, addr: u16) -> Instruction {
+        let opcode = self.peek_cpu(addr);
+        let operand = match OPCODES[opcode as usize].mode.operand_len() {
+            0 => 0,
+            1 => self.peek_cpu(addr.wrapping_add(1)) as u16,
+            _ => u16::from_le_bytes([
+                self.peek_cpu(addr.wrapping_add(1)),
+                self.peek_cpu(addr.wrapping_add(2)),
+            ]),
+        };
+
+        Instruction {
+            addr,
+            opcode,
+            operand,
+            prg_offset: self.mapper.prg_rom_offset(addr as usize),
+        }
+    }
+
+    /// Decodes count instructions, starting at addr
+    pub fn disassemble_range(&self, addr: u16, count: usize) -> Vec<Instruction> {
+        let mut instructions = Vec::with_capacity(count);
+        let mut addr = addr;
+        for _ in 0..count {
+            let instruction = self.disassemble(addr);
+            addr = instruction.next_addr();
+            instructions.push(instruction);
+        }
+
+        instructions
+    }
+
+    /// Decodes up to count instructions that end right before addr. Since instructions
+    /// have different lengths, this picks the longest run of instructions that lands on addr.
+    pub fn disassemble_before(&self, addr: u16, count: usize) -> Vec<Instruction> {
+        for distance in (1..=count as u16 * 3).rev() {
+            let mut start = addr.wrapping_sub(distance);
+            let mut instructions = Vec::new();
+
+            while start != addr && addr.wrapping_sub(start) <= distance {
+                let instruction = self.disassemble(start);
+                start = instruction.next_addr();
+                instructions.push(instruction);
+            }
+
+            if start == addr {
+                let skip = instructions.len().saturating_sub(count);
+                return instructions.split_off(skip);
+            }
+        }
+
+        Vec::new()
+    }
+}
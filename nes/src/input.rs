@@ -0,0 +1,388 @@
+use bincode::{Decode, Encode};
+
+use crate::{
+    controller::{self, Button, Controller},
+    ppu::Ppu,
+};
+
+mod arkanoid_vaus;
+mod power_pad;
+mod zapper;
+
+use arkanoid_vaus::ArkanoidVaus;
+use power_pad::{FamilyTrainer, PowerPad};
+use zapper::Zapper;
+
+/// A device plugged into one of the two controller ports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub enum InputDevice {
+    Unplugged,
+    Controller,
+    /// The NES Four Score / Satellite adapter. Port 1 reads players 1 and 3, port 2 reads players 2 and 4.
+    FourScore,
+    Zapper,
+    ArkanoidVaus,
+    /// The 12-button Power Pad / Family Fun Fitness mat
+    PowerPad,
+}
+
+/// A device plugged into the Famicom expansion port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub enum ExpansionDevice {
+    Unplugged,
+    /// Two additional controllers for players 3 and 4, connected through a simple adapter
+    Famicom4p,
+    ArkanoidVaus,
+    /// The Famicom version of the Power Pad
+    FamilyTrainer,
+}
+
+/*
+NES 2.0 default expansion devices:
+$01 - Standard NES/Famicom controllers
+$02 - NES Four Score/Satellite with two additional standard controllers
+$03 - Famicom Four Players Adapter with two additional standard controllers
+$08 - Zapper ($4017)
+$0B - Power Pad Side A
+$0C - Power Pad Side B
+$0D - Family Trainer Side A
+$0E - Family Trainer Side B
+$0F - Arkanoid Vaus Controller (NES)
+$10 - Arkanoid Vaus Controller (Famicom)
+
+<https://wiki.nesdev.org/w/index.php?title=NES_2.0#Default_Expansion_Device>
+*/
+fn devices_from_expansion(expansion: u32) -> ([InputDevice; 2], ExpansionDevice) {
+    match expansion {
+        0x02 => ([InputDevice::FourScore; 2], ExpansionDevice::Unplugged),
+        0x03 => ([InputDevice::Controller; 2], ExpansionDevice::Famicom4p),
+        0x08 => (
+            [InputDevice::Controller, InputDevice::Zapper],
+            ExpansionDevice::Unplugged,
+        ),
+        0x0B | 0x0C => (
+            [InputDevice::Controller, InputDevice::PowerPad],
+            ExpansionDevice::Unplugged,
+        ),
+        0x0D | 0x0E => ([InputDevice::Controller; 2], ExpansionDevice::FamilyTrainer),
+        0x0F => (
+            [InputDevice::Controller, InputDevice::ArkanoidVaus],
+            ExpansionDevice::Unplugged,
+        ),
+        0x10 => ([InputDevice::Controller; 2], ExpansionDevice::ArkanoidVaus),
+        _ => ([InputDevice::Controller; 2], ExpansionDevice::Unplugged),
+    }
+}
+
+/**
+Handles the $4016 / $4017 input registers and all of the devices connected to them.
+
+$4016 write:
+7  bit  0
+---- ----
+xxxx xEES
+      |||
+      ||+- Controller port latch bit
+      ++-- Expansion port latch bits
+
+$4016 / $4017 read:
+7  bit  0
+---- ----
+xxxD DDDD
+|||+-++++- Input data from the devices
++++------- Open bus
+
+<https://wiki.nesdev.org/w/index.php?title=Input_devices>
+**/
+#[derive(Decode, Encode)]
+pub struct Inputs {
+    ports: [Port; 2],
+    expansion: Expansion,
+
+    /// Button state of the 4 players, in the report order of the standard controller
+    buttons: [u8; 4],
+    strobe: bool,
+}
+
+impl Inputs {
+    pub fn new(expansion: u32) -> Self {
+        let (ports, expansion) = devices_from_expansion(expansion);
+
+        Self {
+            ports: ports.map(Port::new),
+            expansion: Expansion::new(expansion),
+
+            buttons: [0; 4],
+            strobe: false,
+        }
+    }
+
+    pub fn port_device(&self, port: usize) -> InputDevice {
+        self.ports
+            .get(port)
+            .map_or(InputDevice::Unplugged, Port::device)
+    }
+
+    pub fn set_port_device(&mut self, port: usize, device: InputDevice) {
+        if let Some(p) = self.ports.get_mut(port) {
+            *p = Port::new(device);
+        }
+    }
+
+    pub fn expansion_device(&self) -> ExpansionDevice {
+        self.expansion.device()
+    }
+
+    pub fn set_expansion_device(&mut self, device: ExpansionDevice) {
+        self.expansion = Expansion::new(device);
+    }
+
+    pub fn set_button(&mut self, player: usize, button: Button, state: bool) {
+        if let Some(buttons) = self.buttons.get_mut(player) {
+            *buttons = controller::set_button(*buttons, button, state);
+        }
+    }
+
+    /// Updates all of the plugged Zappers
+    pub fn set_zapper(&mut self, aim: Option<(usize, usize)>, trigger: bool) {
+        for port in &mut self.ports {
+            if let Port::Zapper(zapper) = port {
+                zapper.aim = aim;
+                zapper.trigger = trigger;
+            }
+        }
+    }
+
+    /// Updates all of the plugged Arkanoid controllers
+    pub fn set_vaus(&mut self, position: u8, fire: bool) {
+        let vauses = self.ports.iter_mut().filter_map(|port| match port {
+            Port::ArkanoidVaus(vaus) => Some(vaus),
+            _ => None,
+        });
+
+        let expansion = match &mut self.expansion {
+            Expansion::ArkanoidVaus(vaus) => Some(vaus),
+            _ => None,
+        };
+
+        for vaus in vauses.chain(expansion) {
+            vaus.position = position;
+            vaus.fire = fire;
+        }
+    }
+
+    /// Updates all of the plugged Power Pads / Family Trainers, the mats only have 12 buttons
+    pub fn set_power_pad_button(&mut self, button: usize, state: bool) {
+        if button >= 12 {
+            return;
+        }
+
+        let set = |buttons: &mut u16| {
+            *buttons = (*buttons & !(1 << button)) | ((state as u16) << button);
+        };
+
+        for port in &mut self.ports {
+            if let Port::PowerPad(pad) = port {
+                set(&mut pad.buttons);
+            }
+        }
+
+        if let Expansion::FamilyTrainer(mat) = &mut self.expansion {
+            set(&mut mat.buttons);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn write_reg(&mut self, val: u8) {
+        if self.strobe && (val & 1) == 0 {
+            for (index, port) in self.ports.iter_mut().enumerate() {
+                port.latch(index, &self.buttons);
+            }
+
+            self.expansion.latch(&self.buttons);
+        }
+
+        self.strobe = (val & 1) != 0;
+        self.expansion.write(val);
+    }
+
+    /// Returns the low 5 bits of $4016 (port 0) or $4017 (port 1)
+    #[inline]
+    pub(crate) fn read_reg(&mut self, port: usize, ppu: &Ppu) -> u8 {
+        let data = self.ports[port].read(port, self.strobe, &self.buttons, ppu);
+        data | self.expansion.read(port, self.strobe, &self.buttons)
+    }
+}
+
+#[derive(Decode, Encode)]
+enum Port {
+    Unplugged,
+    Controller(Controller),
+    FourScore(FourScore),
+    Zapper(Zapper),
+    ArkanoidVaus(ArkanoidVaus),
+    PowerPad(PowerPad),
+}
+
+impl Port {
+    fn new(device: InputDevice) -> Self {
+        match device {
+            InputDevice::Unplugged => Port::Unplugged,
+            InputDevice::Controller => Port::Controller(Controller::new()),
+            InputDevice::FourScore => Port::FourScore(FourScore::new()),
+            InputDevice::Zapper => Port::Zapper(Zapper::new()),
+            InputDevice::ArkanoidVaus => Port::ArkanoidVaus(ArkanoidVaus::new()),
+            InputDevice::PowerPad => Port::PowerPad(PowerPad::new()),
+        }
+    }
+
+    fn device(&self) -> InputDevice {
+        match self {
+            Port::Unplugged => InputDevice::Unplugged,
+            Port::Controller(_) => InputDevice::Controller,
+            Port::FourScore(_) => InputDevice::FourScore,
+            Port::Zapper(_) => InputDevice::Zapper,
+            Port::ArkanoidVaus(_) => InputDevice::ArkanoidVaus,
+            Port::PowerPad(_) => InputDevice::PowerPad,
+        }
+    }
+
+    fn latch(&mut self, port: usize, buttons: &[u8; 4]) {
+        match self {
+            Port::Unplugged | Port::Zapper(_) => (),
+            Port::Controller(c) => c.latch(buttons[port]),
+            Port::FourScore(f) => f.latch(port, buttons),
+            Port::ArkanoidVaus(v) => v.latch(),
+            Port::PowerPad(p) => p.latch(),
+        }
+    }
+
+    /// Standard devices only drive D0
+    fn read(&mut self, port: usize, strobe: bool, buttons: &[u8; 4], ppu: &Ppu) -> u8 {
+        match self {
+            Port::Unplugged => 0,
+            Port::Controller(c) => c.read(strobe, buttons[port]),
+            Port::FourScore(f) => f.read(strobe, buttons[port]),
+            Port::Zapper(z) => z.read(ppu),
+            Port::ArkanoidVaus(v) => (v.read_data(strobe) << 4) | (v.read_fire() << 3),
+            Port::PowerPad(p) => p.read(strobe),
+        }
+    }
+}
+
+/**
+The Four Score reports 24 bits per port: the first controller, the second controller
+and then a signature that identifies the port.
+
+<https://wiki.nesdev.org/w/index.php?title=Four_player_adapters>
+**/
+#[derive(Decode, Encode)]
+struct FourScore {
+    shifter: u32,
+    /// Number of bits read since the last latch
+    reads: u8,
+}
+
+impl FourScore {
+    fn new() -> Self {
+        Self {
+            shifter: 0,
+            reads: 0,
+        }
+    }
+
+    fn latch(&mut self, port: usize, buttons: &[u8; 4]) {
+        // The signature is read as 0001 0000 from $4016 and as 0010 0000 from $4017
+        let signature: u32 = match port {
+            0 => 0x08,
+            _ => 0x04,
+        };
+
+        self.shifter = buttons[port] as u32 | (buttons[port + 2] as u32) << 8 | signature << 16;
+        self.reads = 0;
+    }
+
+    fn read(&mut self, strobe: bool, buttons: u8) -> u8 {
+        if strobe {
+            return buttons & 1;
+        }
+
+        // All bits after the signature read as 1
+        if self.reads >= 24 {
+            return 1;
+        }
+
+        let bit = (self.shifter & 1) as u8;
+        self.shifter >>= 1;
+        self.reads += 1;
+
+        bit
+    }
+}
+
+#[derive(Decode, Encode)]
+enum Expansion {
+    Unplugged,
+    /// The controllers of players 3 and 4
+    Famicom4p([Controller; 2]),
+    ArkanoidVaus(ArkanoidVaus),
+    FamilyTrainer(FamilyTrainer),
+}
+
+impl Expansion {
+    fn new(device: ExpansionDevice) -> Self {
+        match device {
+            ExpansionDevice::Unplugged => Expansion::Unplugged,
+            ExpansionDevice::Famicom4p => {
+                Expansion::Famicom4p([Controller::new(), Controller::new()])
+            }
+            ExpansionDevice::ArkanoidVaus => Expansion::ArkanoidVaus(ArkanoidVaus::new()),
+            ExpansionDevice::FamilyTrainer => Expansion::FamilyTrainer(FamilyTrainer::new()),
+        }
+    }
+
+    fn device(&self) -> ExpansionDevice {
+        match self {
+            Expansion::Unplugged => ExpansionDevice::Unplugged,
+            Expansion::Famicom4p(_) => ExpansionDevice::Famicom4p,
+            Expansion::ArkanoidVaus(_) => ExpansionDevice::ArkanoidVaus,
+            Expansion::FamilyTrainer(_) => ExpansionDevice::FamilyTrainer,
+        }
+    }
+
+    fn write(&mut self, val: u8) {
+        if let Expansion::FamilyTrainer(mat) = self {
+            mat.write(val);
+        }
+    }
+
+    fn latch(&mut self, buttons: &[u8; 4]) {
+        match self {
+            Expansion::Unplugged | Expansion::FamilyTrainer(_) => (),
+            Expansion::ArkanoidVaus(vaus) => vaus.latch(),
+            Expansion::Famicom4p(controllers) => {
+                controllers[0].latch(buttons[2]);
+                controllers[1].latch(buttons[3]);
+            }
+        }
+    }
+
+    fn read(&mut self, port: usize, strobe: bool, buttons: &[u8; 4]) -> u8 {
+        match self {
+            Expansion::Unplugged => 0,
+            // Player 3 is read from D1 of $4016 and player 4 from D1 of $4017
+            Expansion::Famicom4p(controllers) => {
+                controllers[port].read(strobe, buttons[port + 2]) << 1
+            }
+            // The fire button is read from D1 of $4016 and the knob position from D1 of $4017
+            Expansion::ArkanoidVaus(vaus) => match port {
+                0 => vaus.read_fire() << 1,
+                _ => vaus.read_data(strobe) << 1,
+            },
+            Expansion::FamilyTrainer(mat) => match port {
+                0 => 0,
+                _ => mat.read(),
+            },
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use bincode::{Decode, Encode};
+
+/// The range of the potentiometer values reported by the controller
+const KNOB_MIN: u16 = 0x62;
+const KNOB_MAX: u16 = 0xF2;
+
+/**
+The Arkanoid Vaus controller reports the position of its knob as an 8-bit value,
+which is latched on strobe and then shifted out MSB-first and inverted.
+
+NES ($4016 / $4017 read):
+7  bit  0
+---- ----
+xxxD Fxxx
+   | |
+   | +---- Fire button (1: pressed)
+   +------ Serial knob position data
+
+Famicom (expansion port):
+$4016 read D1: Fire button
+$4017 read D1: Serial knob position data
+
+<https://wiki.nesdev.org/w/index.php?title=Arkanoid_controller>
+**/
+#[derive(Decode, Encode)]
+pub struct ArkanoidVaus {
+    /// 0 is the leftmost knob position and 255 is the rightmost
+    pub position: u8,
+    pub fire: bool,
+
+    shifter: u8,
+}
+
+impl ArkanoidVaus {
+    pub fn new() -> Self {
+        Self {
+            position: 0x80,
+            fire: false,
+
+            shifter: 0,
+        }
+    }
+
+    pub fn latch(&mut self) {
+        let value = KNOB_MIN + (self.position as u16 * (KNOB_MAX - KNOB_MIN)) / 255;
+        self.shifter = !(value as u8);
+    }
+
+    pub fn read_fire(&self) -> u8 {
+        self.fire as u8
+    }
+
+    pub fn read_data(&mut self, strobe: bool) -> u8 {
+        let bit = self.shifter >> 7;
+        if !strobe {
+            self.shifter <<= 1;
+        }
+
+        bit
+    }
+}
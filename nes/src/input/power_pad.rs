@@ -0,0 +1,124 @@
+use bincode::{Decode, Encode};
+
+/*
+The mat has 12 buttons, numbered like this on side B:
+
+ 1  2  3  4
+ 5  6  7  8
+ 9 10 11 12
+
+Side A only exposes 8 of them with a different numbering, but the buttons are wired the same.
+*/
+
+/**
+The NES Power Pad reports its buttons through 2 serial lines at once.
+
+7  bit  0
+---- ----
+xxxH Lxxx
+   | |
+   | +---- Buttons 2, 1, 5, 9, 6, 10, 11, 7 (1: pressed)
+   +------ Buttons 4, 3, 12, 8, followed by 4 1s
+
+<https://wiki.nesdev.org/w/index.php?title=Power_Pad>
+**/
+#[derive(Decode, Encode)]
+pub struct PowerPad {
+    /// Bit N is set if the button N + 1 is pressed
+    pub buttons: u16,
+
+    shifter_low: u8,
+    shifter_high: u8,
+}
+
+impl PowerPad {
+    pub fn new() -> Self {
+        Self {
+            buttons: 0,
+
+            shifter_low: 0,
+            shifter_high: 0,
+        }
+    }
+
+    pub fn latch(&mut self) {
+        let pressed = |button: u16| ((self.buttons >> (button - 1)) & 1) as u8;
+
+        self.shifter_low = pressed(2)
+            | pressed(1) << 1
+            | pressed(5) << 2
+            | pressed(9) << 3
+            | pressed(6) << 4
+            | pressed(10) << 5
+            | pressed(11) << 6
+            | pressed(7) << 7;
+
+        self.shifter_high =
+            pressed(4) | pressed(3) << 1 | pressed(12) << 2 | pressed(8) << 3 | 0xF0;
+    }
+
+    pub fn read(&mut self, strobe: bool) -> u8 {
+        let data = ((self.shifter_low & 1) << 3) | ((self.shifter_high & 1) << 4);
+
+        if !strobe {
+            // Both lines return 1 after all of the buttons were read
+            self.shifter_low = 0x80 | (self.shifter_low >> 1);
+            self.shifter_high = 0x80 | (self.shifter_high >> 1);
+        }
+
+        data
+    }
+}
+
+/**
+The Family Trainer is the Famicom version of the Power Pad. It's connected to the
+expansion port and reports a row of 4 buttons at once.
+
+$4016 write:
+7  bit  0
+---- ----
+xxxx xRRR
+      |||
+      +++- Ignore rows 1 (D2), 2 (D1) and 3 (D0)
+
+$4017 read:
+7  bit  0
+---- ----
+xxxB BBBx
+   | |||
+   +-+++-- Buttons 1, 2, 3, 4 (+4 or +8 for the other rows) of the selected rows (0: pressed)
+
+<https://wiki.nesdev.org/w/index.php?title=Family_Trainer_Mat>
+**/
+#[derive(Decode, Encode)]
+pub struct FamilyTrainer {
+    /// Bit N is set if the button N + 1 is pressed
+    pub buttons: u16,
+
+    ignored_rows: u8,
+}
+
+impl FamilyTrainer {
+    pub fn new() -> Self {
+        Self {
+            buttons: 0,
+            ignored_rows: 0x7,
+        }
+    }
+
+    pub fn write(&mut self, val: u8) {
+        self.ignored_rows = val & 0x7;
+    }
+
+    pub fn read(&self) -> u8 {
+        let mut columns = 0;
+        for row in 0..3 {
+            if (self.ignored_rows >> (2 - row)) & 1 == 0 {
+                columns |= (self.buttons >> (row * 4)) & 0xF;
+            }
+        }
+
+        let data = (columns & 1) << 4 | (columns & 2) << 2 | (columns & 4) | (columns & 8) >> 2;
+        !(data as u8) & 0x1E
+    }
+}
@@ -0,0 +1,79 @@
+use bincode::{Decode, Encode};
+
+use crate::ppu::{Ppu, NES_HEIGHT, NES_WIDTH, PALETTE};
+
+/// How many pixels around the aimed pixel the photodiode sees
+const ZAPPER_RADIUS: usize = 2;
+/// The photodiode keeps detecting light for some time after the beam has passed
+const ZAPPER_LIGHT_SCANLINES: usize = 20;
+/// Minimal luminance of a pixel that is detected as light
+const ZAPPER_BRIGHTNESS: u32 = 0x80;
+
+/**
+7  bit  0
+---- ----
+xxxT WxxS
+   | |  |
+   | |  +- Serial data (unused)
+   | +---- Light sense (0: detected, 1: not detected)
+   +------ Trigger (0: released, 1: pulled)
+
+<https://wiki.nesdev.org/w/index.php?title=Zapper>
+**/
+#[derive(Decode, Encode)]
+pub struct Zapper {
+    /// The aimed pixel, None if the Zapper points off-screen
+    pub aim: Option<(usize, usize)>,
+    pub trigger: bool,
+}
+
+impl Zapper {
+    pub fn new() -> Self {
+        Self {
+            aim: None,
+            trigger: false,
+        }
+    }
+
+    pub fn read(&self, ppu: &Ppu) -> u8 {
+        ((!self.light_sensed(ppu) as u8) << 3) | ((self.trigger as u8) << 4)
+    }
+
+    /// Checks if a bright pixel around the aimed pixel was drawn recently
+    fn light_sensed(&self, ppu: &Ppu) -> bool {
+        let (x, y) = match self.aim {
+            Some(aim) => aim,
+            None => return false,
+        };
+
+        let scanline = ppu.scanline as usize;
+        // The first pixel is drawn on dot 1
+        let dot = ppu.xpos as usize;
+
+        let rows = y.saturating_sub(ZAPPER_RADIUS)..=(y + ZAPPER_RADIUS).min(NES_HEIGHT - 1);
+        for row in rows.filter(|&row| row <= scanline && scanline - row <= ZAPPER_LIGHT_SCANLINES) {
+            let columns = x.saturating_sub(ZAPPER_RADIUS)..=(x + ZAPPER_RADIUS).min(NES_WIDTH - 1);
+            for column in columns {
+                // This pixel hasn't been drawn yet in this frame
+                if row == scanline && column >= dot {
+                    break;
+                }
+
+                if Self::is_bright(ppu.output_buffer[row * NES_WIDTH + column]) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    fn is_bright(color: u8) -> bool {
+        let index = color as usize * 3;
+        let r = PALETTE[index] as u32;
+        let g = PALETTE[index + 1] as u32;
+        let b = PALETTE[index + 2] as u32;
+
+        (r * 299 + g * 587 + b * 114) / 1000 >= ZAPPER_BRIGHTNESS
+    }
+}
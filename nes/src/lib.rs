@@ -1,30 +1,83 @@
 use bincode::{config::Configuration, Decode, Encode};
+#[cfg(feature = "debug_tools")]
 use debug_events::DebugEvent;
+#[cfg(feature = "debug_tools")]
+use debugger::AccessLog;
 use thiserror::Error;
 
+/// For the parts of Nes that aren't a part of the savestates (user settings and debugging
+/// state). Nothing is encoded, the decoded value is `new()`.
+macro_rules! impl_not_saved {
+    ($ty:ty) => {
+        impl bincode::Encode for $ty {
+            fn encode<E: bincode::enc::Encoder>(
+                &self,
+                _: &mut E,
+            ) -> Result<(), bincode::error::EncodeError> {
+                Ok(())
+            }
+        }
+
+        impl<Context> bincode::Decode<Context> for $ty {
+            fn decode<D: bincode::de::Decoder<Context = Context>>(
+                _: &mut D,
+            ) -> Result<Self, bincode::error::DecodeError> {
+                Ok(Self::new())
+            }
+        }
+
+        bincode::impl_borrow_decode!($ty);
+    };
+}
+
 mod apu;
 mod cartridge;
 mod controller;
 mod cpu;
 #[cfg(feature = "debug_tools")]
 mod debug_events;
+#[cfg(feature = "debug_tools")]
+mod debugger;
+#[cfg(feature = "debug_tools")]
+mod disassembler;
+mod input;
 mod mapper;
+mod memory;
+mod movie;
 mod ppu;
 mod replay;
+mod rewind;
+mod timing;
+#[cfg(feature = "debug_tools")]
+mod trace;
 
 use apu::Apu;
-use cartridge::{ConsoleType, Region};
-use controller::Controller;
 use cpu::Cpu;
+use input::Inputs;
 use mapper::BaseMapper;
 use ppu::Ppu;
 
-pub use cartridge::{BankSize, Cartridge, Header};
+pub use apu::{ApuChannel, ApuChannelState};
+pub use cartridge::{BankSize, Cartridge, ConsoleType, Header, HeaderSource, Region};
 pub use controller::Button;
+pub use cpu::CpuRegisters;
 #[cfg(feature = "debug_tools")]
 pub use debug_events::{DebugEvents, EventKind};
-pub use ppu::{FRAMEBUFFER_SIZE, NES_HEIGHT, NES_WIDTH, PALETTE};
+#[cfg(feature = "debug_tools")]
+pub use debugger::{Access, BreakReason, Breakpoint, Comparison, Condition, Debugger, Register};
+#[cfg(feature = "debug_tools")]
+pub use disassembler::{AddressingMode, Instruction, Opcode, OPCODES};
+pub use input::{ExpansionDevice, InputDevice};
+pub use memory::MemoryRegion;
+pub use movie::{Fm2, Movie, MovieAnchor, MovieMode, MoviePlayer};
+pub use ppu::{
+    Mirroring, OamEntry, PpuRegisters, FRAMEBUFFER_SIZE, NES_HEIGHT, NES_WIDTH, PALETTE,
+};
 pub use replay::ReplayInputs;
+pub use rewind::Rewind;
+pub use timing::TimingMode;
+#[cfg(feature = "debug_tools")]
+pub use trace::TraceLogger;
 
 #[derive(Encode, Decode)]
 pub struct Nes {
@@ -34,7 +87,9 @@ pub struct Nes {
 
     mapper: BaseMapper,
 
-    controller: controller::Controller,
+    inputs: Inputs,
+
+    timing: TimingMode,
 
     frame_ready: bool,
     /// CPU cycle count
@@ -43,20 +98,26 @@ pub struct Nes {
 
     #[cfg(feature = "debug_tools")]
     debug_events: DebugEvents,
+    #[cfg(feature = "debug_tools")]
+    access_log: AccessLog,
 }
 
 impl Nes {
     pub fn new(rom: &[u8]) -> Result<Nes, NesError> {
         let cartridge = Cartridge::from_rom(rom)?;
+        let timing = TimingMode::from_region(&cartridge.header.region);
+        let inputs = Inputs::new(cartridge.header.expansion);
 
         let mut nes = Nes {
             cpu: Cpu::new(),
-            ppu: Ppu::new(),
-            apu: Apu::new(),
+            ppu: Ppu::new(timing),
+            apu: Apu::new(timing),
 
             mapper: BaseMapper::new(cartridge)?,
 
-            controller: Controller::new(),
+            inputs,
+
+            timing,
 
             frame_ready: false,
             cycle_count: 0,
@@ -65,14 +126,62 @@ impl Nes {
 
             #[cfg(feature = "debug_tools")]
             debug_events: DebugEvents::new(),
+            #[cfg(feature = "debug_tools")]
+            access_log: AccessLog::new(),
         };
 
         nes.cpu_gen_reset();
         Ok(nes)
     }
 
+    /// Sets the button state of the first player
     pub fn set_button_state(&mut self, button: controller::Button, state: bool) {
-        self.controller.set_button(button, state);
+        self.inputs.set_button(0, button, state);
+    }
+
+    /// Sets the button state of a player (0 - 3). Players 3 and 4 are only
+    /// connected through the Four Score or the Famicom 4-player adapter.
+    /// Other players are ignored.
+    pub fn set_player_button_state(&mut self, player: usize, button: Button, state: bool) {
+        self.inputs.set_button(player, button, state);
+    }
+
+    /// Aims the Zapper at a pixel of the picture, None aims it off-screen
+    pub fn set_zapper_state(&mut self, aim: Option<(usize, usize)>, trigger: bool) {
+        self.inputs.set_zapper(aim, trigger);
+    }
+
+    /// Sets the knob position (0 is the leftmost, 255 is the rightmost) and the fire button
+    /// of the Arkanoid controller
+    pub fn set_vaus_state(&mut self, position: u8, fire: bool) {
+        self.inputs.set_vaus(position, fire);
+    }
+
+    /// Sets the state of a Power Pad / Family Trainer button (0 - 11).
+    /// The buttons are numbered left to right, top to bottom, as on the side B of the mat.
+    /// Other buttons are ignored.
+    pub fn set_power_pad_button(&mut self, button: usize, state: bool) {
+        self.inputs.set_power_pad_button(button, state);
+    }
+
+    /// The devices are chosen from the cartridge's default expansion device,
+    /// but can be changed at any time.
+    /// Ports other than 0 and 1 are always unplugged.
+    pub fn port_device(&self, port: usize) -> InputDevice {
+        self.inputs.port_device(port)
+    }
+
+    /// Plugs the device into port 0 or 1, other ports are ignored
+    pub fn set_port_device(&mut self, port: usize, device: InputDevice) {
+        self.inputs.set_port_device(port, device);
+    }
+
+    pub fn expansion_device(&self) -> ExpansionDevice {
+        self.inputs.expansion_device()
+    }
+
+    pub fn set_expansion_device(&mut self, device: ExpansionDevice) {
+        self.inputs.set_expansion_device(device);
     }
 
     pub fn reset(&mut self) {
@@ -89,7 +198,7 @@ impl Nes {
 
     /// Runs a scanline and returns true if the next frame is ready
     pub fn run_scanline(&mut self) -> bool {
-        for _ in 0..114 {
+        for _ in 0..self.timing.cycles_per_scanline() {
             self.cpu_tick();
         }
 
@@ -123,6 +232,32 @@ impl Nes {
         &self.mapper.cartridge
     }
 
+    /// SHA-1 of the PRG ROM and CHR ROM (without the header), used to identify the game
+    pub fn rom_sha1(&self) -> [u8; 20] {
+        self.mapper.cartridge.rom_sha1
+    }
+
+    /// MD5 of the PRG ROM and CHR ROM, FCEUX uses it to identify the game
+    pub fn rom_md5(&self) -> [u8; 16] {
+        self.mapper.cartridge.rom_md5
+    }
+
+    /// Returns the contents of the battery-backed PRG RAM, if the cartridge has one.
+    /// This is the same raw format as the .sav files used by other emulators.
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        self.mapper.cartridge.battery_ram()
+    }
+
+    /// Restores the battery-backed PRG RAM. Should be called right after the Nes is created.
+    pub fn load_battery_ram(&mut self, save: &[u8]) -> Result<(), NesError> {
+        self.mapper.cartridge.load_battery_ram(save)
+    }
+
+    /// The 2KB of internal RAM ($0000 - $07FF)
+    pub fn cpu_ram(&self) -> &[u8] {
+        &self.cpu.ram
+    }
+
     pub fn frame_count(&self) -> u64 {
         self.frame_count
     }
@@ -136,41 +271,39 @@ impl Nes {
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f64) {
-        self.apu
-            .blip_buf
-            .set_rates(apu::Apu::CLOCK_RATE, sample_rate);
+        self.apu.set_sample_rate(sample_rate);
     }
 
-    #[cfg(feature = "debug_tools")]
-    pub fn debug_events(&self) -> &[DebugEvent] {
-        self.debug_events.events()
+    pub fn timing_mode(&self) -> TimingMode {
+        self.timing
     }
 
-    // TODO: Move this back to tests and have some public API for debug cpu_reads...
-    pub fn run_blargg_test(&mut self) -> String {
-        let mut test_running = false;
-
-        loop {
-            self.cpu_tick();
+    /// Overrides the timing mode chosen from the cartridge's region.
+    /// This resets the PPU and the CPU, so it should be called right after the Nes is created.
+    pub fn set_timing_mode(&mut self, timing: TimingMode) {
+        self.timing = timing;
+        self.ppu = Ppu::new(timing);
+        self.apu.set_timing(timing);
+        self.cycle_count = 0;
+        self.frame_ready = false;
 
-            let test_state = self.cpu_read(0x6000);
-            if test_state == 0x80 {
-                test_running = true;
-            }
+        self.cpu_gen_reset();
+    }
 
-            if test_running && test_state <= 81 {
-                break;
-            }
-        }
+    #[cfg(feature = "debug_tools")]
+    pub fn debug_events(&self) -> &[DebugEvent] {
+        self.debug_events.events()
+    }
 
-        let mut s = String::new();
-        let mut p: usize = 0x6004;
-        while self.cpu_read(p) != 0 {
-            s.push(self.cpu_read(p) as char);
-            p += 1;
-        }
+    /// Events that are disabled aren't recorded, they are all enabled by default
+    #[cfg(feature = "debug_tools")]
+    pub fn set_debug_event_enabled(&mut self, kind: EventKind, enabled: bool) {
+        self.debug_events.set_enabled(kind, enabled);
+    }
 
-        s
+    #[cfg(feature = "debug_tools")]
+    pub fn debug_event_enabled(&self, kind: EventKind) -> bool {
+        self.debug_events.is_enabled(kind)
     }
 }
 
@@ -178,7 +311,7 @@ impl Nes {
     fn clock_components(&mut self) {
         self.cpu.odd_cycle = !self.cpu.odd_cycle;
         self.cycle_count += 1;
-        if self.cycle_count == 29658 {
+        if self.cycle_count == self.timing.ppu_warmup_cycles() {
             self.ppu_enable_writes();
         }
 
@@ -186,6 +319,10 @@ impl Nes {
             self.ppu_tick();
         }
 
+        if self.timing.has_extra_ppu_dot(self.cycle_count) {
+            self.ppu_tick();
+        }
+
         self.apu_tick();
 
         self.mapper.cpu_clock(&mut self.cpu.irq_mapper_signal);
@@ -202,16 +339,10 @@ impl Nes {
 
 #[derive(Error, Debug)]
 pub enum NesError {
-    #[error("iNES 2.0 binary format is not supported")]
-    Ines2Unsupported,
-    #[error("iNES trainers are not supported")]
-    TrainerUnsupported,
     #[error("mapper {0} is not supported")]
     UnSupportedMapper(u32),
     #[error("console type {0} is not supported")]
     ConsoleUnsupported(ConsoleType),
-    #[error("the {0} region is not supported")]
-    RegionUnsupported(Region),
     #[error("the provided file is not a valid iNES ROM")]
     InvalidInesFormat,
     #[error("games with both CHR RAM and ROM are not supported")]
@@ -222,6 +353,17 @@ pub enum NesError {
     InvalidSaveState,
     #[error("the NES 2.0 XML Game Database contains invalid data")]
     GameDbFormat,
+    #[error("the battery save doesn't match the cartridge's battery-backed RAM")]
+    InvalidBatterySave,
+    #[error("the movie file is corrupted, or it has been created by a incompatible version of Fearless-NES")]
+    InvalidMovie,
+    #[error("the movie was recorded with a different ROM")]
+    MovieRomMismatch,
+    #[error("the movie uses an unsupported feature: {0}")]
+    MovieUnsupported(&'static str),
+    #[cfg(feature = "debug_tools")]
+    #[error("invalid breakpoint condition, expected something like 'A == $10'")]
+    InvalidCondition,
 }
 
 const BINCODE_CONFIG: Configuration = bincode::config::standard();
@@ -3,18 +3,27 @@ use bincode::{Decode, Encode};
 use super::{cartridge::Cartridge, ppu::Mirroring, NesError};
 
 mod _0_nrom;
+mod _19_namco_163;
 mod _1_mmc1;
+mod _21_vrc4;
+mod _24_vrc6;
 mod _2_uxrom;
 mod _3_cnrom;
 mod _4_mmc3;
+mod _5_mmc5;
 mod _69_fme_7;
 mod _7_axrom;
+mod vrc_irq;
 
 use _0_nrom::_0Nrom;
+use _19_namco_163::_19Namco163;
 use _1_mmc1::_1Mmc1;
+use _21_vrc4::_21Vrc4;
+use _24_vrc6::_24Vrc6;
 use _2_uxrom::_2Uxrom;
 use _3_cnrom::_3Cnrom;
 use _4_mmc3::_4Mmc3;
+use _5_mmc5::_5Mmc5;
 use _69_fme_7::_69Fme7;
 use _7_axrom::_7Axrom;
 
@@ -29,14 +38,18 @@ pub struct BaseMapper {
 }
 
 impl BaseMapper {
-    pub fn new(cartridge: Cartridge) -> Result<Self, NesError> {
+    pub fn new(mut cartridge: Cartridge) -> Result<Self, NesError> {
         let chip = match cartridge.header.mapper {
             0 => MapperChip::_0Nrom(_0Nrom::new(&cartridge)),
             1 => MapperChip::_1Mmc1(_1Mmc1::new(&cartridge)),
             2 => MapperChip::_2Uxrom(_2Uxrom::new(&cartridge)),
             3 => MapperChip::_3Cnrom(_3Cnrom::new(&cartridge)),
             4 => MapperChip::_4Mmc3(_4Mmc3::new(&cartridge)),
+            5 => MapperChip::_5Mmc5(_5Mmc5::new(&cartridge)),
             7 => MapperChip::_7Axrom(_7Axrom::new(&cartridge)),
+            19 => MapperChip::_19Namco163(_19Namco163::new(&mut cartridge)),
+            21 | 22 | 23 | 25 => MapperChip::_21Vrc4(_21Vrc4::new(&cartridge)),
+            24 | 26 => MapperChip::_24Vrc6(_24Vrc6::new(&cartridge)),
             69 => MapperChip::_69Fme7(_69Fme7::new(&cartridge)),
             mapper_id => return Err(NesError::UnSupportedMapper(mapper_id)),
         };
@@ -50,18 +63,69 @@ impl BaseMapper {
 
     /// Return None if addr isn't mapped to anything on the cartridge, Some(_) otherwise
     #[inline]
-    pub fn cpu_read(&self, addr: usize) -> Option<u8> {
+    pub fn cpu_read(&mut self, addr: usize, cpu_irq: &mut bool) -> Option<u8> {
+        match &mut self.chip {
+            MapperChip::_0Nrom(nrom) => nrom.cpu_read(&self.cartridge, addr),
+            MapperChip::_1Mmc1(mmc1) => mmc1.cpu_read(&self.cartridge, addr),
+            MapperChip::_2Uxrom(uxrom) => uxrom.cpu_read(&self.cartridge, addr),
+            MapperChip::_3Cnrom(cnrom) => cnrom.cpu_read(&self.cartridge, addr),
+            MapperChip::_4Mmc3(mmc3) => mmc3.cpu_read(&self.cartridge, addr),
+            MapperChip::_5Mmc5(mmc5) => mmc5.cpu_read(&self.cartridge, addr, cpu_irq),
+            MapperChip::_7Axrom(axrom) => axrom.cpu_read(&self.cartridge, addr),
+            MapperChip::_19Namco163(n163) => n163.cpu_read(&self.cartridge, addr),
+            MapperChip::_21Vrc4(vrc4) => vrc4.cpu_read(&self.cartridge, addr),
+            MapperChip::_24Vrc6(vrc6) => vrc6.cpu_read(&self.cartridge, addr),
+            MapperChip::_69Fme7(fme_7) => fme_7.cpu_read(&self.cartridge, addr),
+        }
+    }
+
+    /// Same as cpu_read, but without any side effects (acknowledging IRQs...)
+    pub fn peek_cpu(&self, addr: usize) -> Option<u8> {
         match &self.chip {
             MapperChip::_0Nrom(nrom) => nrom.cpu_read(&self.cartridge, addr),
             MapperChip::_1Mmc1(mmc1) => mmc1.cpu_read(&self.cartridge, addr),
             MapperChip::_2Uxrom(uxrom) => uxrom.cpu_read(&self.cartridge, addr),
             MapperChip::_3Cnrom(cnrom) => cnrom.cpu_read(&self.cartridge, addr),
             MapperChip::_4Mmc3(mmc3) => mmc3.cpu_read(&self.cartridge, addr),
+            MapperChip::_5Mmc5(mmc5) => mmc5.peek_cpu(&self.cartridge, addr),
             MapperChip::_7Axrom(axrom) => axrom.cpu_read(&self.cartridge, addr),
+            MapperChip::_19Namco163(n163) => n163.peek_cpu(&self.cartridge, addr),
+            MapperChip::_21Vrc4(vrc4) => vrc4.cpu_read(&self.cartridge, addr),
+            MapperChip::_24Vrc6(vrc6) => vrc6.cpu_read(&self.cartridge, addr),
             MapperChip::_69Fme7(fme_7) => fme_7.cpu_read(&self.cartridge, addr),
         }
     }
 
+    /// Writes to the PRG RAM at $6000 - $7FFF, even if it's disabled or write-protected
+    pub fn poke_prg_ram(&mut self, addr: usize, val: u8) {
+        match &self.chip {
+            MapperChip::_5Mmc5(mmc5) => mmc5.poke_prg_ram(&mut self.cartridge, addr, val),
+            _ => {
+                if addr - 0x6000 < self.cartridge.prg_ram_len() {
+                    self.cartridge.write_prg_ram(addr - 0x6000, val);
+                }
+            }
+        }
+    }
+
+    /// Offset into the PRG ROM that is currently mapped at addr, None if it isn't ROM
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match &self.chip {
+            MapperChip::_0Nrom(nrom) => nrom.prg_rom_offset(addr),
+            MapperChip::_1Mmc1(mmc1) => mmc1.prg_rom_offset(addr),
+            MapperChip::_2Uxrom(uxrom) => uxrom.prg_rom_offset(addr),
+            MapperChip::_3Cnrom(cnrom) => cnrom.prg_rom_offset(addr),
+            MapperChip::_4Mmc3(mmc3) => mmc3.prg_rom_offset(addr),
+            MapperChip::_5Mmc5(mmc5) => mmc5.prg_rom_offset(addr),
+            MapperChip::_7Axrom(axrom) => axrom.prg_rom_offset(addr),
+            MapperChip::_19Namco163(n163) => n163.prg_rom_offset(addr),
+            MapperChip::_21Vrc4(vrc4) => vrc4.prg_rom_offset(addr),
+            MapperChip::_24Vrc6(vrc6) => vrc6.prg_rom_offset(addr),
+            MapperChip::_69Fme7(fme_7) => fme_7.prg_rom_offset(addr),
+        }
+    }
+
     #[inline]
     pub fn cpu_write(&mut self, addr: usize, val: u8, cpu_cycle: u64, cpu_irq: &mut bool) {
         match &mut self.chip {
@@ -70,7 +134,13 @@ impl BaseMapper {
             MapperChip::_2Uxrom(uxrom) => uxrom.cpu_write(addr, val),
             MapperChip::_3Cnrom(cnrom) => cnrom.cpu_write(addr, val),
             MapperChip::_4Mmc3(mmc3) => mmc3.cpu_write(&mut self.cartridge, addr, val, cpu_irq),
+            MapperChip::_5Mmc5(mmc5) => mmc5.cpu_write(&mut self.cartridge, addr, val, cpu_irq),
             MapperChip::_7Axrom(axrom) => axrom.cpu_write(addr, val),
+            MapperChip::_19Namco163(n163) => {
+                n163.cpu_write(&mut self.cartridge, addr, val, cpu_irq)
+            }
+            MapperChip::_21Vrc4(vrc4) => vrc4.cpu_write(&mut self.cartridge, addr, val, cpu_irq),
+            MapperChip::_24Vrc6(vrc6) => vrc6.cpu_write(&mut self.cartridge, addr, val, cpu_irq),
             MapperChip::_69Fme7(fme_7) => fme_7.cpu_write(&mut self.cartridge, addr, val),
         }
     }
@@ -83,7 +153,11 @@ impl BaseMapper {
             MapperChip::_2Uxrom(uxrom) => uxrom.read_chr(&self.cartridge, addr),
             MapperChip::_3Cnrom(cnrom) => cnrom.read_chr(&self.cartridge, addr),
             MapperChip::_4Mmc3(mmc3) => mmc3.read_chr(&self.cartridge, addr),
+            MapperChip::_5Mmc5(mmc5) => mmc5.read_chr(&self.cartridge, addr),
             MapperChip::_7Axrom(axrom) => axrom.read_chr(&self.cartridge, addr),
+            MapperChip::_19Namco163(n163) => n163.read_chr(&self.cartridge, &self.nt_ram, addr),
+            MapperChip::_21Vrc4(vrc4) => vrc4.read_chr(&self.cartridge, addr),
+            MapperChip::_24Vrc6(vrc6) => vrc6.read_chr(&self.cartridge, addr),
             MapperChip::_69Fme7(fme_7) => fme_7.read_chr(&self.cartridge, addr),
         }
     }
@@ -96,31 +170,78 @@ impl BaseMapper {
             MapperChip::_2Uxrom(uxrom) => uxrom.write_chr(&mut self.cartridge, addr, val),
             MapperChip::_3Cnrom(cnrom) => cnrom.write_chr(&mut self.cartridge, addr, val),
             MapperChip::_4Mmc3(mmc3) => mmc3.write_chr(&mut self.cartridge, addr, val),
+            MapperChip::_5Mmc5(mmc5) => mmc5.write_chr(&mut self.cartridge, addr, val),
             MapperChip::_7Axrom(axrom) => axrom.write_chr(&mut self.cartridge, addr, val),
+            MapperChip::_19Namco163(n163) => {
+                n163.write_chr(&mut self.cartridge, &mut self.nt_ram, addr, val)
+            }
+            MapperChip::_21Vrc4(vrc4) => vrc4.write_chr(&mut self.cartridge, addr, val),
+            MapperChip::_24Vrc6(vrc6) => vrc6.write_chr(&mut self.cartridge, addr, val),
             MapperChip::_69Fme7(fme_7) => fme_7.write_chr(&mut self.cartridge, addr, val),
         }
     }
 
-    // Some mappers have custom nametable memory...
+    /// addr is relative to $2000. The MMC5 has its own nametable mapping.
     #[inline]
     pub fn read_nametable(&self, addr: usize) -> u8 {
-        self.nt_ram[addr]
+        match &self.chip {
+            MapperChip::_5Mmc5(mmc5) => mmc5.read_nametable(&self.nt_ram, addr),
+            MapperChip::_19Namco163(n163) => {
+                n163.read_nametable(&self.cartridge, &self.nt_ram, addr)
+            }
+            _ => self.nt_ram[self.mirror_nametable(addr)],
+        }
     }
 
     #[inline]
     pub fn write_nametable(&mut self, addr: usize, val: u8) {
+        match &mut self.chip {
+            MapperChip::_5Mmc5(mmc5) => mmc5.write_nametable(&mut self.nt_ram, addr, val),
+            MapperChip::_19Namco163(n163) => {
+                n163.write_nametable(&mut self.cartridge, &mut self.nt_ram, addr, val)
+            }
+            _ => {
+                let addr = self.mirror_nametable(addr);
+                self.nt_ram[addr] = val;
+            }
+        }
+    }
+
+    /// Reads the nametable RAM directly, without the mirroring
+    pub fn peek_nt_ram(&self, addr: usize) -> u8 {
+        self.nt_ram[addr]
+    }
+
+    pub fn poke_nt_ram(&mut self, addr: usize, val: u8) {
         self.nt_ram[addr] = val;
     }
 
+    /// The first 2KB of nt_ram are the console's CIRAM, four-screen cartridges also use the rest
+    #[inline]
+    fn mirror_nametable(&self, addr: usize) -> usize {
+        match self.mirroring() {
+            Mirroring::Vertical => addr & 0x7FF,
+            Mirroring::Horizontal => ((addr >> 1) & 0x400) | (addr & 0x3FF),
+            Mirroring::SingleScreenLow => addr & 0x3FF,
+            Mirroring::SingleScreenHigh => 0x400 | (addr & 0x3FF),
+            Mirroring::FourScreen => addr,
+        }
+    }
+
     #[inline]
     pub fn mirroring(&self) -> Mirroring {
         match &self.chip {
-            MapperChip::_0Nrom(_) | MapperChip::_2Uxrom(_) | MapperChip::_3Cnrom(_) => {
-                self.cartridge.header.mirroring
-            }
+            // The MMC5 and the Namco 163 map their nametables themselves
+            MapperChip::_0Nrom(_)
+            | MapperChip::_2Uxrom(_)
+            | MapperChip::_3Cnrom(_)
+            | MapperChip::_5Mmc5(_)
+            | MapperChip::_19Namco163(_) => self.cartridge.header.mirroring,
             MapperChip::_1Mmc1(mmc1) => mmc1.mirroring(),
             MapperChip::_4Mmc3(mmc3) => mmc3.mirroring(),
             MapperChip::_7Axrom(axrom) => axrom.mirroring(),
+            MapperChip::_21Vrc4(vrc4) => vrc4.mirroring(),
+            MapperChip::_24Vrc6(vrc6) => vrc6.mirroring(),
             MapperChip::_69Fme7(fme_7) => fme_7.mirroring(),
         }
     }
@@ -134,7 +255,11 @@ impl BaseMapper {
             | MapperChip::_1Mmc1(_)
             | MapperChip::_2Uxrom(_)
             | MapperChip::_3Cnrom(_)
+            | MapperChip::_5Mmc5(_)
             | MapperChip::_7Axrom(_)
+            | MapperChip::_19Namco163(_)
+            | MapperChip::_21Vrc4(_)
+            | MapperChip::_24Vrc6(_)
             | MapperChip::_69Fme7(_) => (),
             MapperChip::_4Mmc3(mmc3) => mmc3.notify_a12(a12, cpu_irq),
         }
@@ -144,10 +269,42 @@ impl BaseMapper {
     pub fn cpu_clock(&mut self, cpu_irq: &mut bool) {
         match &mut self.chip {
             MapperChip::_4Mmc3(mmc3) => mmc3.cpu_clock(),
+            MapperChip::_5Mmc5(mmc5) => mmc5.cpu_clock(cpu_irq),
+            MapperChip::_19Namco163(n163) => n163.cpu_clock(&mut self.cartridge, cpu_irq),
+            MapperChip::_21Vrc4(vrc4) => vrc4.cpu_clock(cpu_irq),
+            MapperChip::_24Vrc6(vrc6) => vrc6.cpu_clock(cpu_irq),
             MapperChip::_69Fme7(fme_7) => fme_7.clock(cpu_irq),
             _ => (),
         }
     }
+
+    /// The MMC5 detects scanlines and tile fetches by watching the PPU bus
+    #[inline]
+    pub fn notify_ppu_read(&mut self, addr: usize, cpu_irq: &mut bool) {
+        if let MapperChip::_5Mmc5(mmc5) = &mut self.chip {
+            mmc5.notify_ppu_read(addr, cpu_irq);
+        }
+    }
+
+    #[inline]
+    pub fn notify_ppu_reg_write(&mut self, addr: usize, val: u8) {
+        if let MapperChip::_5Mmc5(mmc5) = &mut self.chip {
+            mmc5.ppu_reg_write(addr, val);
+        }
+    }
+
+    /// Output of the expansion audio chip, if the cartridge has one.
+    /// The expansion audio is clocked by cpu_clock and mixed with the APU output.
+    #[inline]
+    pub fn audio_output(&self) -> i32 {
+        match &self.chip {
+            MapperChip::_5Mmc5(mmc5) => mmc5.audio_output(),
+            MapperChip::_19Namco163(n163) => n163.audio_output(&self.cartridge),
+            MapperChip::_24Vrc6(vrc6) => vrc6.audio_output(),
+            MapperChip::_69Fme7(fme_7) => fme_7.audio_output(),
+            _ => 0,
+        }
+    }
 }
 
 #[derive(Decode, Encode)]
@@ -157,6 +314,10 @@ pub enum MapperChip {
     _2Uxrom(_2Uxrom),
     _3Cnrom(_3Cnrom),
     _4Mmc3(_4Mmc3),
+    _5Mmc5(_5Mmc5),
     _7Axrom(_7Axrom),
+    _19Namco163(_19Namco163),
+    _21Vrc4(_21Vrc4),
+    _24Vrc6(_24Vrc6),
     _69Fme7(_69Fme7),
 }
@@ -25,6 +25,15 @@ impl _0Nrom {
         }
     }
 
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0xBFFF => Some(addr - 0x8000),
+            0xC000..=0xFFFF => Some(self.prg_1 + addr - 0xC000),
+            _ => None,
+        }
+    }
+
     pub fn cpu_write(&mut self, cartridge: &mut Cartridge, addr: usize, val: u8) {
         if let 0x6000..=0x7FFF = addr {
             cartridge.write_prg_ram(addr - 0x6000, val);
@@ -0,0 +1,345 @@
+use bincode::{Decode, Encode};
+
+use crate::cartridge::{BankSize, Cartridge};
+
+/// Size of the internal sound RAM, which is also used for saves by some games
+const SOUND_RAM_SIZE: usize = 0x80;
+/// The 163 updates one channel every 15 CPU cycles
+const CHANNEL_UPDATE_CYCLES: u8 = 15;
+const MIX_SCALE: i32 = 4;
+
+/** <https://wiki.nesdev.org/w/index.php?title=Namco_163> **/
+#[derive(Decode, Encode)]
+pub struct _19Namco163 {
+    prg_0: usize,
+    prg_1: usize,
+    prg_2: usize,
+    prg_end: usize,
+    /// $F800 bits 4-7 have to be 0100 to enable writes, bits 0-3 protect the 2KB pages
+    prg_ram_protect: u8,
+
+    /// $8000 - $BFFF for the pattern tables and $C000 - $DFFF for the nametables
+    chr_regs: [u8; 12],
+    /// Values $E0-$FF select the CIRAM instead of CHR ROM in the pattern tables,
+    /// unless disabled for the lower or upper 4KB
+    ciram_disabled_low: bool,
+    ciram_disabled_high: bool,
+    chr_len: usize,
+
+    irq_counter: u16,
+    irq_enabled: bool,
+
+    sound_addr: u8,
+    sound_auto_increment: bool,
+    sound_disabled: bool,
+    sound_cycles: u8,
+    /// The channel that is updated next, counts down from 7
+    sound_channel: u8,
+    channel_outputs: [i8; 8],
+}
+
+impl _19Namco163 {
+    pub fn new(cartridge: &mut Cartridge) -> Self {
+        cartridge.add_mapper_ram(SOUND_RAM_SIZE);
+
+        let prg_banks = cartridge.prg_rom_count(BankSize::Kb8) as u8;
+
+        Self {
+            prg_0: 0,
+            prg_1: 0,
+            prg_2: 0,
+            prg_end: cartridge.map_bank_prg_wrap(prg_banks - 1, BankSize::Kb8),
+            prg_ram_protect: 0,
+
+            chr_regs: [0; 12],
+            ciram_disabled_low: false,
+            ciram_disabled_high: false,
+            chr_len: cartridge.chr_len(),
+
+            irq_counter: 0,
+            irq_enabled: false,
+
+            sound_addr: 0,
+            sound_auto_increment: false,
+            sound_disabled: false,
+            sound_cycles: 0,
+            sound_channel: 7,
+            channel_outputs: [0; 8],
+        }
+    }
+
+    /*
+    CPU $6000-$7FFF: 8 KB PRG RAM bank, if WRAM is present
+    CPU $8000-$9FFF: 8 KB switchable PRG ROM bank
+    CPU $A000-$BFFF: 8 KB switchable PRG ROM bank
+    CPU $C000-$DFFF: 8 KB switchable PRG ROM bank
+    CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+    */
+    pub fn cpu_read(&mut self, cartridge: &Cartridge, addr: usize) -> Option<u8> {
+        let val = self.peek_cpu(cartridge, addr);
+        if let 0x4800..=0x4FFF = addr {
+            self.increment_sound_addr();
+        }
+
+        val
+    }
+
+    /// Reading the sound RAM doesn't increment the address
+    pub fn peek_cpu(&self, cartridge: &Cartridge, addr: usize) -> Option<u8> {
+        match addr {
+            0x4800..=0x4FFF => Some(cartridge.read_mapper_ram(self.sound_addr as usize)),
+            0x5000..=0x57FF => Some(self.irq_counter as u8),
+            0x5800..=0x5FFF => Some((self.irq_counter >> 8) as u8 | (self.irq_enabled as u8) << 7),
+            0x6000..=0x7FFF if cartridge.prg_ram_len() > 0 => cartridge.read_prg_ram(addr - 0x6000),
+            0x8000..=0x9FFF => Some(cartridge.read_prg_rom(self.prg_0 + addr - 0x8000)),
+            0xA000..=0xBFFF => Some(cartridge.read_prg_rom(self.prg_1 + addr - 0xA000)),
+            0xC000..=0xDFFF => Some(cartridge.read_prg_rom(self.prg_2 + addr - 0xC000)),
+            0xE000..=0xFFFF => Some(cartridge.read_prg_rom(self.prg_end + addr - 0xE000)),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0x9FFF => Some(self.prg_0 + addr - 0x8000),
+            0xA000..=0xBFFF => Some(self.prg_1 + addr - 0xA000),
+            0xC000..=0xDFFF => Some(self.prg_2 + addr - 0xC000),
+            0xE000..=0xFFFF => Some(self.prg_end + addr - 0xE000),
+            _ => None,
+        }
+    }
+
+    pub fn cpu_write(
+        &mut self,
+        cartridge: &mut Cartridge,
+        addr: usize,
+        val: u8,
+        cpu_irq: &mut bool,
+    ) {
+        match addr {
+            0x4800..=0x4FFF => {
+                cartridge.write_mapper_ram(self.sound_addr as usize, val);
+                self.increment_sound_addr();
+            }
+            // Writing to the IRQ counter acknowledges the IRQ
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | val as u16;
+                *cpu_irq = false;
+            }
+            /*
+            7  bit  0
+            ---- ----
+            EHHH HHHH
+            |||| ||||
+            |+++-++++- High 7 bits of the IRQ counter
+            +--------- IRQ Enable
+            */
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0xFF) | ((val as u16 & 0x7F) << 8);
+                self.irq_enabled = val & 0x80 != 0;
+                *cpu_irq = false;
+            }
+            0x6000..=0x7FFF if cartridge.prg_ram_len() > 0 => {
+                let page = (addr - 0x6000) / BankSize::Kb2 as usize;
+                if self.prg_ram_protect & 0xF0 == 0x40 && self.prg_ram_protect & (1 << page) == 0 {
+                    cartridge.write_prg_ram(addr - 0x6000, val);
+                }
+            }
+            0x8000..=0xDFFF => self.chr_regs[(addr - 0x8000) / 0x800] = val,
+            /*
+            7  bit  0
+            ---- ----
+            .SPP PPPP
+             ||| ||||
+             |++-++++- Select 8KB page of PRG-ROM at $8000
+             +-------- Disable sound if set
+            */
+            0xE000..=0xE7FF => {
+                self.sound_disabled = val & 0x40 != 0;
+                self.prg_0 = cartridge.map_bank_prg_wrap(val & 0x3F, BankSize::Kb8);
+            }
+            /*
+            7  bit  0
+            ---- ----
+            HLPP PPPP
+            |||| ||||
+            ||++-++++- Select 8KB page of PRG-ROM at $A000
+            |+-------- Disable CHR-RAM at $0000-$0FFF
+            +--------- Disable CHR-RAM at $1000-$1FFF
+            */
+            0xE800..=0xEFFF => {
+                self.ciram_disabled_low = val & 0x40 != 0;
+                self.ciram_disabled_high = val & 0x80 != 0;
+                self.prg_1 = cartridge.map_bank_prg_wrap(val & 0x3F, BankSize::Kb8);
+            }
+            0xF000..=0xF7FF => self.prg_2 = cartridge.map_bank_prg_wrap(val & 0x3F, BankSize::Kb8),
+            /*
+            The write protect for PRG RAM and the address port of the sound RAM share a register
+            7  bit  0
+            ---- ----
+            IAAA AAAA
+            |||| ||||
+            |+++-++++- Address
+            +--------- Auto-increment
+            */
+            0xF800..=0xFFFF => {
+                self.prg_ram_protect = val;
+                self.sound_addr = val & 0x7F;
+                self.sound_auto_increment = val & 0x80 != 0;
+            }
+            _ => (),
+        }
+    }
+
+    fn increment_sound_addr(&mut self) {
+        if self.sound_auto_increment {
+            self.sound_addr = (self.sound_addr + 1) & 0x7F;
+        }
+    }
+
+    /*
+    Each 1KB of the PPU address space has its own register.
+    Values below $E0 select a 1KB CHR ROM page, values $E0-$FF select
+    the CIRAM page given by the lowest bit.
+    */
+    fn map_ppu_addr(&self, addr: usize) -> PpuPage {
+        let page = (addr >> 10) & 0xF;
+        let val = self.chr_regs[page];
+
+        let ciram_allowed = match page {
+            0..=3 => !self.ciram_disabled_low,
+            4..=7 => !self.ciram_disabled_high,
+            _ => true,
+        };
+
+        match val >= 0xE0 && ciram_allowed {
+            true => PpuPage::Ciram(((val as usize & 1) << 10) | (addr & 0x3FF)),
+            false => PpuPage::Chr(
+                (val as usize * BankSize::Kb1 as usize + (addr & 0x3FF)) % self.chr_len,
+            ),
+        }
+    }
+
+    pub fn read_chr(&self, cartridge: &Cartridge, nt_ram: &[u8], addr: usize) -> u8 {
+        match self.map_ppu_addr(addr) {
+            PpuPage::Chr(addr) => cartridge.read_chr(addr),
+            PpuPage::Ciram(addr) => nt_ram[addr],
+        }
+    }
+
+    pub fn write_chr(
+        &mut self,
+        cartridge: &mut Cartridge,
+        nt_ram: &mut [u8],
+        addr: usize,
+        val: u8,
+    ) {
+        match self.map_ppu_addr(addr) {
+            PpuPage::Chr(addr) => cartridge.write_chr(addr, val),
+            PpuPage::Ciram(addr) => nt_ram[addr] = val,
+        }
+    }
+
+    /// addr is relative to $2000, the nametables use the registers at $C000 - $DFFF
+    pub fn read_nametable(&self, cartridge: &Cartridge, nt_ram: &[u8], addr: usize) -> u8 {
+        self.read_chr(cartridge, nt_ram, 0x2000 + (addr & 0xFFF))
+    }
+
+    pub fn write_nametable(
+        &mut self,
+        cartridge: &mut Cartridge,
+        nt_ram: &mut [u8],
+        addr: usize,
+        val: u8,
+    ) {
+        self.write_chr(cartridge, nt_ram, 0x2000 + (addr & 0xFFF), val);
+    }
+
+    pub fn cpu_clock(&mut self, cartridge: &mut Cartridge, cpu_irq: &mut bool) {
+        // The counter stops counting at $7FFF
+        if self.irq_enabled && self.irq_counter < 0x7FFF {
+            self.irq_counter += 1;
+            if self.irq_counter == 0x7FFF {
+                *cpu_irq = true;
+            }
+        }
+
+        if !self.sound_disabled {
+            self.sound_cycles += 1;
+            if self.sound_cycles == CHANNEL_UPDATE_CYCLES {
+                self.sound_cycles = 0;
+                self.update_channel(cartridge);
+            }
+        }
+    }
+
+    /// $7F bits 4-6: number of enabled channels - 1. The channels are enabled from 7 down.
+    fn enabled_channels(cartridge: &Cartridge) -> u8 {
+        ((cartridge.read_mapper_ram(0x7F) >> 4) & 7) + 1
+    }
+
+    /*
+    Each channel has 8 bytes of registers, channel 0 at $40 - $47, channel 7 at $78 - $7F
+    +0: frequency, low 8 bits
+    +1: phase, low 8 bits
+    +2: frequency, middle 8 bits
+    +3: phase, middle 8 bits
+    +4: bits 2-7: wave length (256 - 4 * L samples), bits 0-1: frequency, high 2 bits
+    +5: phase, high 8 bits
+    +6: wave address (in 4-bit samples)
+    +7: bits 0-3: volume
+
+    The waveforms are stored as 4-bit samples, low nibble first.
+    */
+    fn update_channel(&mut self, cartridge: &mut Cartridge) {
+        let channel = self.sound_channel as usize;
+        let base = 0x40 + channel * 8;
+        let reg = |offset: usize| cartridge.read_mapper_ram(base + offset) as u32;
+
+        let freq = reg(0) | reg(2) << 8 | (reg(4) & 3) << 16;
+        let phase = reg(1) | reg(3) << 8 | reg(5) << 16;
+        let length = 256 - (reg(4) & 0xFC);
+        let wave_addr = reg(6);
+        let volume = (reg(7) & 0xF) as i8;
+
+        let phase = (phase + freq) % (length << 16);
+
+        let sample_addr = ((phase >> 16) + wave_addr) & 0xFF;
+        let sample = cartridge.read_mapper_ram(sample_addr as usize / 2) >> ((sample_addr & 1) * 4);
+        self.channel_outputs[channel] = ((sample & 0xF) as i8 - 8) * volume;
+
+        cartridge.write_mapper_ram(base + 1, phase as u8);
+        cartridge.write_mapper_ram(base + 3, (phase >> 8) as u8);
+        cartridge.write_mapper_ram(base + 5, (phase >> 16) as u8);
+
+        let enabled = Self::enabled_channels(cartridge);
+        self.sound_channel = match self.sound_channel <= 8 - enabled {
+            true => 7,
+            false => self.sound_channel - 1,
+        };
+    }
+
+    /*
+    The 163 outputs the channels one after the other, so with more channels enabled
+    each channel is quieter (and there's more aliasing). This averages the channels.
+    */
+    pub fn audio_output(&self, cartridge: &Cartridge) -> i32 {
+        if self.sound_disabled {
+            return 0;
+        }
+
+        let enabled = Self::enabled_channels(cartridge) as usize;
+        let sum: i32 = self.channel_outputs[8 - enabled..]
+            .iter()
+            .map(|&output| output as i32)
+            .sum();
+
+        sum * MIX_SCALE / enabled as i32
+    }
+}
+
+enum PpuPage {
+    Chr(usize),
+    Ciram(usize),
+}
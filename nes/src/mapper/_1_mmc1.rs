@@ -65,6 +65,15 @@ impl _1Mmc1 {
         }
     }
 
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0xBFFF => Some(self.prg_0 + addr - 0x8000),
+            0xC000..=0xFFFF => Some(self.prg_1 + addr - 0xC000),
+            _ => None,
+        }
+    }
+
     pub fn cpu_write(&mut self, cartridge: &mut Cartridge, addr: usize, val: u8, cpu_cycle: u64) {
         match addr {
             0x6000..=0x7FFF if self.enable_ram => cartridge.write_prg_ram(addr - 0x6000, val),
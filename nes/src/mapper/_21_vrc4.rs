@@ -0,0 +1,268 @@
+use bincode::{Decode, Encode};
+
+use super::vrc_irq::VrcIrq;
+use crate::{
+    cartridge::{BankSize, Cartridge, HeaderSource},
+    ppu::Mirroring,
+};
+
+/**
+Konami VRC2 and VRC4 (mappers 21, 22, 23 and 25). The VRC2 is mostly a subset of the VRC4.
+
+<https://wiki.nesdev.org/w/index.php?title=VRC2_and_VRC4>
+**/
+#[derive(Decode, Encode)]
+pub struct _21Vrc4 {
+    vrc4: bool,
+    /// VRC2a ignores the lowest bit of the CHR bank registers
+    chr_shift: u8,
+    /// Which CPU address lines are connected to the A0 and A1 register select pins
+    a0_mask: usize,
+    a1_mask: usize,
+
+    prg_swap_mode: bool,
+    prg_0: usize,
+    prg_1: usize,
+    prg_end_1: usize,
+    prg_end_2: usize,
+
+    /// 1KB units, VRC4 uses all 9 bits
+    chr_regs: [u16; 8],
+    chr_banks: [usize; 8],
+    chr_len: usize,
+
+    mirroring: Mirroring,
+
+    /// The VRC2 boards without PRG RAM have a 1-bit latch at $6000-$6FFF
+    has_vrc2_latch: bool,
+    vrc2_latch: u8,
+
+    irq: VrcIrq,
+}
+
+impl _21Vrc4 {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let header = &cartridge.header;
+
+        /*
+        The boards connect different CPU address lines to the register select pins.
+        Submapper 0 means that the variant is unknown, so both address lines are used.
+        */
+        let (vrc4, a0_mask, a1_mask) = match (header.mapper, header.submapper) {
+            // VRC4a
+            (21, 1) => (true, 0x02, 0x04),
+            // VRC4c
+            (21, 2) => (true, 0x40, 0x80),
+            (21, _) => (true, 0x42, 0x84),
+            // VRC2a
+            (22, _) => (false, 0x02, 0x01),
+            // VRC4f
+            (23, 1) => (true, 0x01, 0x02),
+            // VRC4e
+            (23, 2) => (true, 0x04, 0x08),
+            // VRC2b
+            (23, 3) => (false, 0x01, 0x02),
+            (23, _) => (true, 0x05, 0x0A),
+            // VRC4b
+            (25, 1) => (true, 0x02, 0x01),
+            // VRC4d
+            (25, 2) => (true, 0x08, 0x04),
+            // VRC2c
+            (25, 3) => (false, 0x02, 0x01),
+            (25, _) => (true, 0x0A, 0x05),
+            (mapper, _) => unreachable!("mapper {} isn't a VRC2 / VRC4", mapper),
+        };
+
+        /*
+        iNES 1.0 headers can't say that there's no PRG RAM, the emulator allocates it anyway.
+        Only the VRC2 games with a battery have it.
+        */
+        let has_vrc2_latch = !vrc4
+            && match header.source {
+                HeaderSource::Ines1 => !header.battery,
+                HeaderSource::Ines2 | HeaderSource::GameDb => cartridge.prg_ram_len() == 0,
+            };
+
+        let prg_banks = cartridge.prg_rom_count(BankSize::Kb8) as u8;
+
+        Self {
+            vrc4,
+            chr_shift: (header.mapper == 22) as u8,
+            a0_mask,
+            a1_mask,
+
+            prg_swap_mode: false,
+            prg_0: 0,
+            prg_1: 0,
+            prg_end_1: cartridge.map_bank_prg_wrap(prg_banks - 1, BankSize::Kb8),
+            prg_end_2: cartridge.map_bank_prg_wrap(prg_banks.wrapping_sub(2), BankSize::Kb8),
+
+            chr_regs: [0; 8],
+            chr_banks: [0; 8],
+            chr_len: cartridge.chr_len(),
+
+            mirroring: header.mirroring,
+
+            has_vrc2_latch,
+            vrc2_latch: 0,
+
+            irq: VrcIrq::new(),
+        }
+    }
+
+    /*
+    CPU $6000-$7FFF: 8 KB PRG RAM bank, if WRAM is present
+    CPU $8000-$9FFF (or $C000-$DFFF): 8 KB switchable PRG ROM bank
+    CPU $A000-$BFFF: 8 KB switchable PRG ROM bank
+    CPU $C000-$DFFF (or $8000-$9FFF): 8 KB PRG ROM bank, fixed to the second-last bank
+    CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+    */
+    pub fn cpu_read(&self, cartridge: &Cartridge, addr: usize) -> Option<u8> {
+        let (low, high) = match self.prg_swap_mode {
+            false => (self.prg_0, self.prg_end_2),
+            true => (self.prg_end_2, self.prg_0),
+        };
+
+        match addr {
+            // Only the lowest bit is driven, the rest is open bus
+            0x6000..=0x6FFF if self.has_vrc2_latch => Some(self.vrc2_latch),
+            0x6000..=0x7FFF => cartridge.read_prg_ram(addr - 0x6000),
+            0x8000..=0x9FFF => Some(cartridge.read_prg_rom(low + addr - 0x8000)),
+            0xA000..=0xBFFF => Some(cartridge.read_prg_rom(self.prg_1 + addr - 0xA000)),
+            0xC000..=0xDFFF => Some(cartridge.read_prg_rom(high + addr - 0xC000)),
+            0xE000..=0xFFFF => Some(cartridge.read_prg_rom(self.prg_end_1 + addr - 0xE000)),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        let (low, high) = match self.prg_swap_mode {
+            false => (self.prg_0, self.prg_end_2),
+            true => (self.prg_end_2, self.prg_0),
+        };
+
+        match addr {
+            0x8000..=0x9FFF => Some(low + addr - 0x8000),
+            0xA000..=0xBFFF => Some(self.prg_1 + addr - 0xA000),
+            0xC000..=0xDFFF => Some(high + addr - 0xC000),
+            0xE000..=0xFFFF => Some(self.prg_end_1 + addr - 0xE000),
+            _ => None,
+        }
+    }
+
+    pub fn cpu_write(
+        &mut self,
+        cartridge: &mut Cartridge,
+        addr: usize,
+        val: u8,
+        cpu_irq: &mut bool,
+    ) {
+        match addr {
+            0x6000..=0x6FFF if self.has_vrc2_latch => self.vrc2_latch = val & 1,
+            0x6000..=0x7FFF => cartridge.write_prg_ram(addr - 0x6000, val),
+            0x8000..=0xFFFF => self.write_reg(cartridge, self.decode_reg(addr), val, cpu_irq),
+            _ => (),
+        }
+    }
+
+    /// Translates the address to the canonical $x000 - $x003 register address
+    fn decode_reg(&self, addr: usize) -> usize {
+        let a0 = (addr & self.a0_mask != 0) as usize;
+        let a1 = (addr & self.a1_mask != 0) as usize;
+
+        (addr & 0xF000) | (a1 << 1) | a0
+    }
+
+    fn write_reg(&mut self, cartridge: &Cartridge, reg: usize, val: u8, cpu_irq: &mut bool) {
+        match reg {
+            0x8000..=0x8003 => self.prg_0 = cartridge.map_bank_prg_wrap(val & 0x1F, BankSize::Kb8),
+            0x9000..=0x9003 if !self.vrc4 => self.select_mirroring(val & 1),
+            0x9000 | 0x9001 => self.select_mirroring(val & 3),
+            /*
+            7  bit  0
+            ---------
+            .... ..MW
+                   ||
+                   |+- WRAM control (ignored, like on the MMC3)
+                   +-- PRG swap mode
+            */
+            0x9002 | 0x9003 => self.prg_swap_mode = val & 2 != 0,
+            0xA000..=0xA003 => self.prg_1 = cartridge.map_bank_prg_wrap(val & 0x1F, BankSize::Kb8),
+            0xB000..=0xE003 => self.select_chr(reg, val),
+            0xF000..=0xF003 if self.vrc4 => self.write_irq(reg, val, cpu_irq),
+            _ => (),
+        }
+    }
+
+    /*
+    0 - Vertical
+    1 - Horizontal
+    2 - One-screen, lower bank (VRC4 only)
+    3 - One-screen, upper bank (VRC4 only)
+    */
+    fn select_mirroring(&mut self, val: u8) {
+        self.mirroring = match val {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLow,
+            3 => Mirroring::SingleScreenHigh,
+            _ => unreachable!(),
+        };
+    }
+
+    /*
+    Each 1KB CHR bank has 2 registers, the even one holds the low 4 bits of the bank number
+    and the odd one holds the high 5 bits (only 4 bits on VRC2).
+
+    $B000 / $B001 - PPU $0000-$03FF
+    $B002 / $B003 - PPU $0400-$07FF
+    $C000 / $C001 - PPU $0800-$0BFF
+    ...
+    $E002 / $E003 - PPU $1C00-$1FFF
+    */
+    fn select_chr(&mut self, reg: usize, val: u8) {
+        let bank = ((reg - 0xB000) >> 12) * 2 + ((reg >> 1) & 1);
+        let val = val as u16;
+
+        self.chr_regs[bank] = match reg & 1 {
+            0 => (self.chr_regs[bank] & 0x1F0) | (val & 0xF),
+            _ => (self.chr_regs[bank] & 0xF) | ((val & 0x1F) << 4),
+        };
+
+        let chr_bank = (self.chr_regs[bank] >> self.chr_shift) as usize;
+        self.chr_banks[bank] = (chr_bank * BankSize::Kb1 as usize) % self.chr_len;
+    }
+
+    /*
+    $F000: IRQ latch, low 4 bits
+    $F001: IRQ latch, high 4 bits
+    $F002: IRQ control
+    $F003: IRQ acknowledge
+    */
+    fn write_irq(&mut self, reg: usize, val: u8, cpu_irq: &mut bool) {
+        match reg {
+            0xF000 => self.irq.write_latch_low(val),
+            0xF001 => self.irq.write_latch_high(val),
+            0xF002 => self.irq.write_control(val, cpu_irq),
+            0xF003 => self.irq.acknowledge(cpu_irq),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn read_chr(&self, cartridge: &Cartridge, addr: usize) -> u8 {
+        cartridge.read_chr(self.chr_banks[addr >> 10] + (addr & 0x3FF))
+    }
+
+    pub fn write_chr(&mut self, cartridge: &mut Cartridge, addr: usize, val: u8) {
+        cartridge.write_chr(self.chr_banks[addr >> 10] + (addr & 0x3FF), val);
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    pub fn cpu_clock(&mut self, cpu_irq: &mut bool) {
+        self.irq.cpu_clock(cpu_irq);
+    }
+}
@@ -0,0 +1,417 @@
+use bincode::{Decode, Encode};
+
+use super::vrc_irq::VrcIrq;
+use crate::{
+    cartridge::{BankSize, Cartridge},
+    ppu::Mirroring,
+};
+
+/// The VRC6 pulses are about as loud as the APU pulses at the same volume
+const MIX_SCALE: i32 = 50;
+
+/**
+Konami VRC6 (mappers 24 and 26). VRC6b swaps the A0 and A1 register select lines.
+
+<https://wiki.nesdev.org/w/index.php?title=VRC6>
+**/
+#[derive(Decode, Encode)]
+pub struct _24Vrc6 {
+    swap_a0_a1: bool,
+
+    prg_0: usize,
+    prg_1: usize,
+    prg_end: usize,
+    prg_ram_enabled: bool,
+
+    /// 1KB units
+    chr_regs: [u8; 8],
+    chr_banks: [usize; 8],
+    chr_len: usize,
+    banking_style: u8,
+
+    mirroring: Mirroring,
+
+    irq: VrcIrq,
+
+    pulse_1: Vrc6Pulse,
+    pulse_2: Vrc6Pulse,
+    saw: Vrc6Saw,
+    halt_audio: bool,
+    /// The frequency control register can speed up all channels 16 or 256 times
+    freq_shift: u8,
+}
+
+impl _24Vrc6 {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let prg_banks = cartridge.prg_rom_count(BankSize::Kb8) as u8;
+
+        Self {
+            swap_a0_a1: cartridge.header.mapper == 26,
+
+            prg_0: 0,
+            prg_1: 0,
+            prg_end: cartridge.map_bank_prg_wrap(prg_banks - 1, BankSize::Kb8),
+            prg_ram_enabled: false,
+
+            chr_regs: [0; 8],
+            chr_banks: [0; 8],
+            chr_len: cartridge.chr_len(),
+            banking_style: 0,
+
+            mirroring: cartridge.header.mirroring,
+
+            irq: VrcIrq::new(),
+
+            pulse_1: Vrc6Pulse::new(),
+            pulse_2: Vrc6Pulse::new(),
+            saw: Vrc6Saw::new(),
+            halt_audio: false,
+            freq_shift: 0,
+        }
+    }
+
+    /*
+    CPU $6000-$7FFF: 8 KB PRG RAM
+    CPU $8000-$BFFF: 16 KB switchable PRG ROM bank
+    CPU $C000-$DFFF: 8 KB switchable PRG ROM bank
+    CPU $E000-$FFFF: 8 KB PRG ROM bank, fixed to the last bank
+    */
+    pub fn cpu_read(&self, cartridge: &Cartridge, addr: usize) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled => cartridge.read_prg_ram(addr - 0x6000),
+            0x8000..=0xBFFF => Some(cartridge.read_prg_rom(self.prg_0 + addr - 0x8000)),
+            0xC000..=0xDFFF => Some(cartridge.read_prg_rom(self.prg_1 + addr - 0xC000)),
+            0xE000..=0xFFFF => Some(cartridge.read_prg_rom(self.prg_end + addr - 0xE000)),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0xBFFF => Some(self.prg_0 + addr - 0x8000),
+            0xC000..=0xDFFF => Some(self.prg_1 + addr - 0xC000),
+            0xE000..=0xFFFF => Some(self.prg_end + addr - 0xE000),
+            _ => None,
+        }
+    }
+
+    pub fn cpu_write(
+        &mut self,
+        cartridge: &mut Cartridge,
+        addr: usize,
+        val: u8,
+        cpu_irq: &mut bool,
+    ) {
+        let reg = match self.swap_a0_a1 {
+            true => (addr & 0xF000) | ((addr & 1) << 1) | ((addr >> 1) & 1),
+            false => addr & 0xF003,
+        };
+
+        match reg {
+            0x6000..=0x7FFF if self.prg_ram_enabled => cartridge.write_prg_ram(addr - 0x6000, val),
+            0x8000..=0x8003 => self.prg_0 = cartridge.map_bank_prg_wrap(val & 0xF, BankSize::Kb16),
+            0x9000..=0x9002 => self.pulse_1.write_reg(reg, val),
+            /*
+            7  bit  0
+            ---------
+            .... .ABH
+                  |||
+                  ||+- Halt
+                  |+-- 16x frequency (4 octaves up)
+                  +--- 256x frequency (8 octaves up)
+            */
+            0x9003 => {
+                self.halt_audio = val & 1 != 0;
+                self.freq_shift = match val & 6 {
+                    0 => 0,
+                    2 => 4,
+                    _ => 8,
+                };
+            }
+            0xA000..=0xA002 => self.pulse_2.write_reg(reg, val),
+            0xB000..=0xB002 => self.saw.write_reg(reg, val),
+            0xB003 => {
+                self.banking_style = val;
+                self.prg_ram_enabled = val & 0x80 != 0;
+                self.update_chr_banks();
+            }
+            0xC000..=0xC003 => self.prg_1 = cartridge.map_bank_prg_wrap(val & 0x1F, BankSize::Kb8),
+            0xD000..=0xE003 => {
+                self.chr_regs[((reg - 0xD000) >> 10) | (reg & 3)] = val;
+                self.update_chr_banks();
+            }
+            0xF000 => self.irq.write_latch(val),
+            0xF001 => self.irq.write_control(val, cpu_irq),
+            0xF002 => self.irq.acknowledge(cpu_irq),
+            _ => (),
+        }
+    }
+
+    /*
+    $B003 - PPU Banking Style
+    7  bit  0
+    ---------
+    W.PN MMDD
+    | || ||||
+    | || ||++- PPU banking mode
+    | || ++--- Mirroring
+    | |+------ Nametable source (0: CIRAM, 1: CHR ROM)
+    | +------- CHR A10 rule (1: the 2KB banks use the lowest bit of the PPU address)
+    +--------- PRG RAM enable
+
+    Mode 0: 8 x 1KB banks (R0 - R7)
+    Mode 1: 4 x 2KB banks (R0 - R3)
+    Mode 2 / 3: 4 x 1KB banks (R0 - R3), then 2 x 2KB banks (R4, R5)
+
+    Almost all games use mode 0 with CIRAM nametables.
+    */
+    // TODO: nametables from CHR ROM aren't supported (no known game uses them)
+    fn update_chr_banks(&mut self) {
+        let (mask, or) = match self.banking_style & 0x20 != 0 {
+            true => (0xFE, 1),
+            false => (0xFF, 0),
+        };
+
+        let r = self.chr_regs;
+        let banks = match self.banking_style & 3 {
+            0 => r,
+            1 => [
+                r[0] & mask,
+                r[0] | or,
+                r[1] & mask,
+                r[1] | or,
+                r[2] & mask,
+                r[2] | or,
+                r[3] & mask,
+                r[3] | or,
+            ],
+            _ => [
+                r[0],
+                r[1],
+                r[2],
+                r[3],
+                r[4] & mask,
+                r[4] | or,
+                r[5] & mask,
+                r[5] | or,
+            ],
+        };
+
+        for (offset, bank) in self.chr_banks.iter_mut().zip(banks) {
+            *offset = (bank as usize * BankSize::Kb1 as usize) % self.chr_len;
+        }
+
+        self.mirroring = match (self.banking_style >> 2) & 3 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLow,
+            3 => Mirroring::SingleScreenHigh,
+            _ => unreachable!(),
+        };
+    }
+
+    pub fn read_chr(&self, cartridge: &Cartridge, addr: usize) -> u8 {
+        cartridge.read_chr(self.chr_banks[addr >> 10] + (addr & 0x3FF))
+    }
+
+    pub fn write_chr(&mut self, cartridge: &mut Cartridge, addr: usize, val: u8) {
+        cartridge.write_chr(self.chr_banks[addr >> 10] + (addr & 0x3FF), val);
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    pub fn cpu_clock(&mut self, cpu_irq: &mut bool) {
+        self.irq.cpu_clock(cpu_irq);
+
+        if !self.halt_audio {
+            self.pulse_1.clock(self.freq_shift);
+            self.pulse_2.clock(self.freq_shift);
+            self.saw.clock(self.freq_shift);
+        }
+    }
+
+    pub fn audio_output(&self) -> i32 {
+        let output = self.pulse_1.output() + self.pulse_2.output() + self.saw.output();
+        output as i32 * MIX_SCALE
+    }
+}
+
+/** <https://wiki.nesdev.org/w/index.php?title=VRC6_audio#Pulse_Channels> **/
+#[derive(Decode, Encode)]
+struct Vrc6Pulse {
+    /// Ignore the duty and output the volume constantly
+    digitized: bool,
+    duty: u8,
+    volume: u8,
+    period: u16,
+    enabled: bool,
+
+    timer: u16,
+    /// Counts down from 15 to 0, the channel outputs its volume when step <= duty
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    fn new() -> Self {
+        Self {
+            digitized: false,
+            duty: 0,
+            volume: 0,
+            period: 0,
+            enabled: false,
+
+            timer: 0,
+            step: 15,
+        }
+    }
+
+    /*
+    $9000 / $A000
+    7  bit  0
+    ---------
+    MDDD VVVV
+    |||| ||||
+    |||| ++++- Volume
+    |+++------ Duty Cycle
+    +--------- Mode (1: ignore duty)
+
+    $9001 / $A001: period low 8 bits
+
+    $9002 / $A002
+    7  bit  0
+    ---------
+    E... PPPP
+    |    ||||
+    |    ++++- Period high 4 bits
+    +--------- Enable (0: the channel is silenced and the duty cycle is reset)
+    */
+    fn write_reg(&mut self, reg: usize, val: u8) {
+        match reg & 3 {
+            0 => {
+                self.digitized = val & 0x80 != 0;
+                self.duty = (val >> 4) & 7;
+                self.volume = val & 0xF;
+            }
+            1 => self.period = (self.period & 0xF00) | val as u16,
+            2 => {
+                self.period = (self.period & 0xFF) | ((val as u16 & 0xF) << 8);
+                self.enabled = val & 0x80 != 0;
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn clock(&mut self, freq_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> freq_shift;
+            self.step = self.step.wrapping_sub(1) & 0xF;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        match self.enabled && (self.digitized || self.step <= self.duty) {
+            true => self.volume,
+            false => 0,
+        }
+    }
+}
+
+/** <https://wiki.nesdev.org/w/index.php?title=VRC6_audio#Sawtooth_Channel> **/
+#[derive(Decode, Encode)]
+struct Vrc6Saw {
+    rate: u8,
+    period: u16,
+    enabled: bool,
+
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Saw {
+    fn new() -> Self {
+        Self {
+            rate: 0,
+            period: 0,
+            enabled: false,
+
+            timer: 0,
+            step: 0,
+            accumulator: 0,
+        }
+    }
+
+    /*
+    $B000
+    7  bit  0
+    ---------
+    ..AA AAAA
+      ++-++++- Accumulator Rate (controls volume)
+
+    $B001: period low 8 bits
+
+    $B002
+    7  bit  0
+    ---------
+    E... PPPP
+    |    ||||
+    |    ++++- Period high 4 bits
+    +--------- Enable (0: the accumulator is reset)
+    */
+    fn write_reg(&mut self, reg: usize, val: u8) {
+        match reg & 3 {
+            0 => self.rate = val & 0x3F,
+            1 => self.period = (self.period & 0xF00) | val as u16,
+            2 => {
+                self.period = (self.period & 0xFF) | ((val as u16 & 0xF) << 8);
+                self.enabled = val & 0x80 != 0;
+                if !self.enabled {
+                    self.accumulator = 0;
+                    self.step = 0;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /*
+    The accumulator is increased by the rate on every other clock of the divider.
+    On the 14th clock, it is reset to 0.
+    */
+    fn clock(&mut self, freq_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> freq_shift;
+
+            self.step += 1;
+            if self.step == 14 {
+                self.step = 0;
+                self.accumulator = 0;
+            } else if self.step & 1 == 0 {
+                self.accumulator = self.accumulator.wrapping_add(self.rate);
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Only the high 5 bits of the accumulator are output
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
@@ -26,6 +26,15 @@ impl _2Uxrom {
         }
     }
 
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0xBFFF => Some(self.prg_0 + addr - 0x8000),
+            0xC000..=0xFFFF => Some(self.prg_1 + addr - 0xC000),
+            _ => None,
+        }
+    }
+
     pub fn cpu_write(&mut self, addr: usize, val: u8) {
         if let 0x8000..=0xFFFF = addr {
             self.prg_0 = Cartridge::map_bank(val, BankSize::Kb16);
@@ -30,6 +30,15 @@ impl _3Cnrom {
         }
     }
 
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0xBFFF => Some(addr - 0x8000),
+            0xC000..=0xFFFF => Some(self.prg_1 + addr - 0xC000),
+            _ => None,
+        }
+    }
+
     pub fn cpu_write(&mut self, addr: usize, val: u8) {
         if let 0x8000..=0xFFFF = addr {
             self.chr_0 = Cartridge::map_bank(val & 3, BankSize::Kb8);
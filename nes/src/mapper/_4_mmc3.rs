@@ -100,6 +100,22 @@ impl _4Mmc3 {
         }
     }
 
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        let (low, high) = match self.prg_bank_mode {
+            0 => (self.prg_0, self.prg_end_2),
+            _ => (self.prg_end_2, self.prg_0),
+        };
+
+        match addr {
+            0x8000..=0x9FFF => Some(low + addr - 0x8000),
+            0xA000..=0xBFFF => Some(self.prg_1 + addr - 0xA000),
+            0xC000..=0xDFFF => Some(high + addr - 0xC000),
+            0xE000..=0xFFFF => Some(self.prg_end_1 + addr - 0xE000),
+            _ => None,
+        }
+    }
+
     pub fn cpu_write(
         &mut self,
         cartridge: &mut Cartridge,
@@ -0,0 +1,712 @@
+use bincode::{Decode, Encode};
+
+use crate::{apu::Pulse, cartridge::Cartridge, BankSize};
+
+const EXRAM_SIZE: usize = 0x400;
+
+/** <https://wiki.nesdev.org/w/index.php?title=MMC5> **/
+#[derive(Decode, Encode)]
+pub struct _5Mmc5 {
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect_1: u8,
+    prg_ram_protect_2: u8,
+
+    /// $5113 - $5117
+    prg_regs: [u8; 5],
+    /// The 8KB windows at $6000, $8000, $A000, $C000 and $E000
+    prg_banks: [PrgBank; 5],
+    prg_rom_len: usize,
+    prg_ram_len: usize,
+
+    /// $5120 - $5127 (set A) and $5128 - $512B (set B), including the upper bits from $5130
+    chr_regs: [u16; 12],
+    chr_upper_bits: u8,
+    /// Whether $5128 - $512B were written to last
+    last_chr_set_b: bool,
+    chr_len: usize,
+
+    exram_mode: u8,
+    exram: Vec<u8>,
+    nt_mapping: u8,
+    fill_tile: u8,
+    fill_attr: u8,
+
+    /// Snooped from $2000
+    sprites_8x16: bool,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+
+    /// Scanline detection state
+    in_frame: bool,
+    scanline: u8,
+    last_nt_addr: usize,
+    nt_read_count: u8,
+    ppu_idle_cycles: u8,
+    /// Counts the PPU reads since the start of the scanline (4 reads per tile / sprite)
+    fetch_count: u8,
+    fetch: Fetch,
+    /// The ExRAM byte belonging to the background tile currently being fetched
+    ex_attr: u8,
+    split_tile_x: u8,
+    split_y: u8,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    pulse_1: Pulse<0>,
+    pulse_2: Pulse<0>,
+    /// Divides the CPU clock to the 240 Hz envelope / length counter clock
+    audio_frame_cycles: u16,
+    audio_odd_cycle: bool,
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+    pcm: u8,
+}
+
+#[derive(Clone, Copy, Decode, Encode)]
+struct PrgBank {
+    rom: bool,
+    offset: usize,
+}
+
+/// What kind of data the PPU is fetching, derived by counting the PPU reads
+#[derive(Clone, Copy, PartialEq, Decode, Encode)]
+enum Fetch {
+    Background,
+    SplitBackground,
+    Sprite,
+    /// PPUDATA accesses, the garbage nametable fetches or rendering is disabled
+    Other,
+}
+
+impl _5Mmc5 {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let mut mmc5 = Self {
+            // Castlevania III relies on the power-on state of PRG mode 3 and $5117 = $FF
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect_1: 0,
+            prg_ram_protect_2: 0,
+
+            prg_regs: [0, 0xFF, 0xFF, 0xFF, 0xFF],
+            prg_banks: [PrgBank {
+                rom: false,
+                offset: 0,
+            }; 5],
+            prg_rom_len: cartridge.prg_rom_count(BankSize::Kb8) as usize * BankSize::Kb8 as usize,
+            prg_ram_len: cartridge.prg_ram_len(),
+
+            chr_regs: [0; 12],
+            chr_upper_bits: 0,
+            last_chr_set_b: false,
+            chr_len: cartridge.chr_len(),
+
+            exram_mode: 0,
+            exram: vec![0; EXRAM_SIZE],
+            nt_mapping: 0,
+            fill_tile: 0,
+            fill_attr: 0,
+
+            sprites_8x16: false,
+
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+
+            in_frame: false,
+            scanline: 0,
+            last_nt_addr: 0,
+            nt_read_count: 0,
+            ppu_idle_cycles: 0,
+            fetch_count: 0,
+            fetch: Fetch::Other,
+            ex_attr: 0,
+            split_tile_x: 0,
+            split_y: 0,
+
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+
+            pulse_1: Pulse::new(),
+            pulse_2: Pulse::new(),
+            audio_frame_cycles: 0,
+            audio_odd_cycle: false,
+            pcm_read_mode: false,
+            pcm_irq_enabled: false,
+            pcm_irq: false,
+            pcm: 0,
+        };
+
+        mmc5.update_prg_banks();
+        mmc5
+    }
+
+    pub fn cpu_read(
+        &mut self,
+        cartridge: &Cartridge,
+        addr: usize,
+        cpu_irq: &mut bool,
+    ) -> Option<u8> {
+        let val = self.peek_cpu(cartridge, addr);
+
+        match addr {
+            0x5010 => {
+                self.pcm_irq = false;
+                self.update_irq(cpu_irq);
+            }
+            0x5204 => {
+                self.irq_pending = false;
+                self.update_irq(cpu_irq);
+            }
+            0x8000..=0xBFFF if self.pcm_read_mode => {
+                if let Some(val) = val {
+                    self.pcm_read(val, cpu_irq);
+                }
+            }
+            _ => (),
+        }
+
+        val
+    }
+
+    /// Reads without acknowledging the IRQs or feeding the PCM channel
+    pub fn peek_cpu(&self, cartridge: &Cartridge, addr: usize) -> Option<u8> {
+        match addr {
+            0x5010 => Some((self.pcm_irq as u8) << 7),
+            0x5015 => Some(
+                self.pulse_1.length_counter_active() as u8
+                    | (self.pulse_2.length_counter_active() as u8) << 1,
+            ),
+            /*
+            7  bit  0
+            ---- ----
+            SVxx xxxx  MMC5A default power-on value = $00
+            ||
+            |+-------- "In Frame" flag
+            +--------- Scanline IRQ Pending flag
+
+            Reading this register acknowledges the IRQ
+            */
+            0x5204 => Some((self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6),
+            0x5205 => Some((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            0x5206 => Some(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            // ExRAM is only readable in modes 2 and 3
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Some(self.exram[addr - 0x5C00]),
+            0x6000..=0xFFFF => self.read_prg(cartridge, addr),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x6000..=0xFFFF => {
+                let bank = self.prg_banks[(addr - 0x6000) / BankSize::Kb8 as usize];
+                bank.rom.then_some(bank.offset + (addr & 0x1FFF))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn cpu_write(
+        &mut self,
+        cartridge: &mut Cartridge,
+        addr: usize,
+        val: u8,
+        cpu_irq: &mut bool,
+    ) {
+        match addr {
+            0x5000 => self.pulse_1.set_dlcv(val),
+            0x5002 => self.pulse_1.set_timer_low(val),
+            0x5003 => self.pulse_1.set_lh(val),
+            0x5004 => self.pulse_2.set_dlcv(val),
+            0x5006 => self.pulse_2.set_timer_low(val),
+            0x5007 => self.pulse_2.set_lh(val),
+            /*
+            $5010 - PCM Mode/IRQ (r/w)
+            7  bit  0
+            ---- ----
+            Ixxx xxxM
+            |       |
+            |       +- Mode select (0 = write mode. 1 = read mode.)
+            +--------- PCM IRQ enable (1 = enabled.)
+            */
+            0x5010 => {
+                self.pcm_read_mode = val & 1 != 0;
+                self.pcm_irq_enabled = val & 0x80 != 0;
+                self.update_irq(cpu_irq);
+            }
+            // Writing $00 has no effect
+            0x5011 if !self.pcm_read_mode && val != 0 => self.pcm = val,
+            0x5015 => {
+                self.pulse_1.set_enabled(val & 1 != 0);
+                self.pulse_2.set_enabled(val & 2 != 0);
+            }
+            0x5100 => {
+                self.prg_mode = val & 3;
+                self.update_prg_banks();
+            }
+            0x5101 => self.chr_mode = val & 3,
+            0x5102 => self.prg_ram_protect_1 = val & 3,
+            0x5103 => self.prg_ram_protect_2 = val & 3,
+            0x5104 => self.exram_mode = val & 3,
+            0x5105 => self.nt_mapping = val,
+            0x5106 => self.fill_tile = val,
+            0x5107 => self.fill_attr = val & 3,
+            0x5113..=0x5117 => {
+                self.prg_regs[addr - 0x5113] = val;
+                self.update_prg_banks();
+            }
+            0x5120..=0x512B => {
+                self.chr_regs[addr - 0x5120] = val as u16 | (self.chr_upper_bits as u16) << 8;
+                self.last_chr_set_b = addr >= 0x5128;
+            }
+            0x5130 => self.chr_upper_bits = val & 3,
+            0x5200 => self.split_control = val,
+            0x5201 => self.split_scroll = val,
+            0x5202 => self.split_bank = val,
+            0x5203 => self.irq_compare = val,
+            0x5204 => {
+                self.irq_enabled = val & 0x80 != 0;
+                self.update_irq(cpu_irq);
+            }
+            0x5205 => self.multiplicand = val,
+            0x5206 => self.multiplier = val,
+            /*
+            Mode 0/1 - Not readable (returns open bus), can only be written while the PPU is
+            rendering (otherwise, 0 is written)
+            Mode 2 - Readable and writable
+            Mode 3 - Read-only
+            */
+            0x5C00..=0x5FFF => match self.exram_mode {
+                0 | 1 => self.exram[addr - 0x5C00] = if self.in_frame { val } else { 0 },
+                2 => self.exram[addr - 0x5C00] = val,
+                _ => (),
+            },
+            0x6000..=0xFFFF => self.write_prg(cartridge, addr, val),
+            _ => (),
+        }
+    }
+
+    /// The MMC5 snoops PPUCTRL writes to know the sprite size
+    pub fn ppu_reg_write(&mut self, addr: usize, val: u8) {
+        if addr & 0x2007 == 0x2000 {
+            self.sprites_8x16 = val & 0x20 != 0;
+        }
+    }
+
+    /*
+    PRG mode 0: CPU $8000-$FFFF: 32 KB switchable PRG ROM bank
+    PRG mode 1: CPU $8000-$BFFF: 16 KB switchable PRG ROM/RAM bank
+                CPU $C000-$FFFF: 16 KB switchable PRG ROM bank
+    PRG mode 2: CPU $8000-$BFFF: 16 KB switchable PRG ROM/RAM bank
+                CPU $C000-$DFFF: 8 KB switchable PRG ROM/RAM bank
+                CPU $E000-$FFFF: 8 KB switchable PRG ROM bank
+    PRG mode 3: CPU $8000-$9FFF: 8 KB switchable PRG ROM/RAM bank
+                CPU $A000-$BFFF: 8 KB switchable PRG ROM/RAM bank
+                CPU $C000-$DFFF: 8 KB switchable PRG ROM/RAM bank
+                CPU $E000-$FFFF: 8 KB switchable PRG ROM bank
+
+    $6000-$7FFF is always an 8 KB switchable PRG RAM bank ($5113).
+    Bit 7 of $5114-$5116 selects ROM (1) or RAM (0), $5117 always selects ROM.
+    */
+    fn update_prg_banks(&mut self) {
+        let [r_6000, r_8000, r_a000, r_c000, r_e000] = self.prg_regs;
+
+        // (register, offset of this 8KB window inside of the bank, is ROM)
+        let windows = match self.prg_mode {
+            0 => [
+                (r_e000 & 0x7C, 0, true),
+                (r_e000 & 0x7C, 1, true),
+                (r_e000 & 0x7C, 2, true),
+                (r_e000 & 0x7C, 3, true),
+            ],
+            1 => [
+                (r_a000 & 0xFE, 0, false),
+                (r_a000 & 0xFE, 1, false),
+                (r_e000 & 0x7E, 0, true),
+                (r_e000 & 0x7E, 1, true),
+            ],
+            2 => [
+                (r_a000 & 0xFE, 0, false),
+                (r_a000 & 0xFE, 1, false),
+                (r_c000, 0, false),
+                (r_e000 & 0x7F, 0, true),
+            ],
+            3 => [
+                (r_8000, 0, false),
+                (r_a000, 0, false),
+                (r_c000, 0, false),
+                (r_e000 & 0x7F, 0, true),
+            ],
+            _ => unreachable!(),
+        };
+
+        self.prg_banks[0] = self.prg_bank(r_6000 & 0x7F, 0, false);
+        for (i, (reg, window, fixed_rom)) in windows.into_iter().enumerate() {
+            self.prg_banks[i + 1] = self.prg_bank(reg, window, fixed_rom);
+        }
+    }
+
+    fn prg_bank(&self, reg: u8, window: usize, fixed_rom: bool) -> PrgBank {
+        let rom = fixed_rom || reg & 0x80 != 0;
+        let bank = (reg & 0x7F) as usize + window;
+
+        let offset = match rom {
+            true => (bank * BankSize::Kb8 as usize) % self.prg_rom_len,
+            // Only the low 3 bits select the RAM bank (up to 64KB of PRG RAM)
+            false => match self.prg_ram_len {
+                0 => 0,
+                len => ((bank & 7) * BankSize::Kb8 as usize) % len,
+            },
+        };
+
+        PrgBank { rom, offset }
+    }
+
+    fn read_prg(&self, cartridge: &Cartridge, addr: usize) -> Option<u8> {
+        let bank = self.prg_banks[(addr - 0x6000) / BankSize::Kb8 as usize];
+        let addr = bank.offset + (addr & 0x1FFF);
+
+        match bank.rom {
+            true => Some(cartridge.read_prg_rom(addr)),
+            false => cartridge.read_prg_ram(addr),
+        }
+    }
+
+    /*
+    PRG RAM Protect 1 ($5102): must be %10 to allow writing
+    PRG RAM Protect 2 ($5103): must be %01 to allow writing
+    */
+    fn write_prg(&mut self, cartridge: &mut Cartridge, addr: usize, val: u8) {
+        let bank = self.prg_banks[(addr - 0x6000) / BankSize::Kb8 as usize];
+
+        if !bank.rom && self.prg_ram_protect_1 == 2 && self.prg_ram_protect_2 == 1 {
+            cartridge.write_prg_ram(bank.offset + (addr & 0x1FFF), val);
+        }
+    }
+
+    /// Ignores the write protection
+    pub fn poke_prg_ram(&self, cartridge: &mut Cartridge, addr: usize, val: u8) {
+        let bank = self.prg_banks[(addr - 0x6000) / BankSize::Kb8 as usize];
+
+        if !bank.rom {
+            cartridge.write_prg_ram(bank.offset + (addr & 0x1FFF), val);
+        }
+    }
+
+    /*
+    MMC5 scanline detection: the PPU reads the same nametable address 3 times in a row at the end
+    of each rendered scanline (dots 337, 339 and 1 of the next scanline).
+    If no PPU reads occur for 3 CPU cycles, the PPU is considered to be idle (vblank or rendering
+    disabled) and the "in frame" flag is cleared.
+    */
+    pub fn notify_ppu_read(&mut self, addr: usize, cpu_irq: &mut bool) {
+        self.ppu_idle_cycles = 0;
+        self.fetch_count = self.fetch_count.saturating_add(1);
+
+        if (0x2000..=0x2FFF).contains(&addr) && addr == self.last_nt_addr {
+            self.nt_read_count += 1;
+        } else {
+            self.nt_read_count = 0;
+        }
+        self.last_nt_addr = addr;
+
+        if self.nt_read_count == 2 {
+            self.scanline_start(cpu_irq);
+        }
+
+        self.classify_fetch(addr);
+    }
+
+    fn scanline_start(&mut self, cpu_irq: &mut bool) {
+        if !self.in_frame {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+        } else {
+            self.scanline = self.scanline.wrapping_add(1);
+            if self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        }
+
+        self.fetch_count = 0;
+        self.update_irq(cpu_irq);
+    }
+
+    /*
+    Each scanline the PPU does these reads (4 reads per tile and per sprite):
+    0   - 127: background tiles 2 - 33 of the current scanline
+    128 - 159: sprite tiles for the next scanline
+    160 - 167: background tiles 0 - 1 of the next scanline
+    168 - 169: garbage nametable reads
+    */
+    fn classify_fetch(&mut self, addr: usize) {
+        if !self.in_frame {
+            self.fetch = Fetch::Other;
+            return;
+        }
+
+        let (tile_x, next_line) = match self.fetch_count {
+            0..=127 => (self.fetch_count / 4 + 2, false),
+            128..=159 => {
+                self.fetch = Fetch::Sprite;
+                return;
+            }
+            160..=167 => ((self.fetch_count - 160) / 4, true),
+            _ => {
+                self.fetch = Fetch::Other;
+                return;
+            }
+        };
+
+        // The nametable fetch decides what the following attribute and pattern fetches do
+        if self.fetch_count & 3 == 0 {
+            self.fetch = match self.in_split(tile_x) {
+                true => {
+                    let line = self.scanline as u16 + next_line as u16;
+                    self.split_y = ((self.split_scroll as u16 + line) % 240) as u8;
+                    self.split_tile_x = tile_x & 0x1F;
+                    Fetch::SplitBackground
+                }
+                false => {
+                    self.ex_attr = self.exram[addr & 0x3FF];
+                    Fetch::Background
+                }
+            };
+        }
+    }
+
+    /*
+    $5200 - Vertical Split Mode
+    7  bit  0
+    ---- ----
+    ERxT TTTT
+    || | ||||
+    || +-++++- Specify vertical split start/stop tile
+    |+-------- Specify vertical split screen side (0:left; 1:right)
+    +--------- Enable vertical split mode
+    */
+    fn in_split(&self, tile_x: u8) -> bool {
+        let threshold = self.split_control & 0x1F;
+
+        self.split_control & 0x80 != 0
+            && self.exram_mode <= 1
+            && match self.split_control & 0x40 != 0 {
+                true => tile_x >= threshold,
+                false => tile_x < threshold,
+            }
+    }
+
+    fn update_irq(&self, cpu_irq: &mut bool) {
+        *cpu_irq = (self.irq_enabled && self.irq_pending) || (self.pcm_irq_enabled && self.pcm_irq);
+    }
+
+    /*
+    $5105 - Nametable mapping
+    7  bit  0
+    ---- ----
+    DDCC BBAA
+    |||| ||||
+    |||| ||++- Select nametable at PPU $2000-$23FF
+    |||| ++--- Select nametable at PPU $2400-$27FF
+    ||++------ Select nametable at PPU $2800-$2BFF
+    ++-------- Select nametable at PPU $2C00-$2FFF
+
+    Nametable values:
+    0 - CIRAM page 0
+    1 - CIRAM page 1
+    2 - Internal Expansion RAM, only if the Extended RAM mode allows it ($5104 is 00/01);
+        otherwise, the nametable will read as all zeros
+    3 - Fill-mode data
+    */
+    pub fn read_nametable(&self, nt_ram: &[u8], addr: usize) -> u8 {
+        let offset = addr & 0x3FF;
+        let is_attr = offset >= 0x3C0;
+
+        match self.fetch {
+            Fetch::SplitBackground => {
+                let coarse_y = self.split_y as usize / 8;
+                let tile_x = self.split_tile_x as usize;
+
+                return match is_attr {
+                    false => self.exram[coarse_y * 32 + tile_x],
+                    true => {
+                        let attr = self.exram[0x3C0 + (coarse_y / 4) * 8 + tile_x / 4];
+                        let shift = ((coarse_y & 2) << 1) | (tile_x & 2);
+                        Self::replicate_attr((attr >> shift) & 3)
+                    }
+                };
+            }
+            // Extended attributes: the palette is taken from the ExRAM byte of the tile
+            Fetch::Background if self.exram_mode == 1 && is_attr => {
+                return Self::replicate_attr(self.ex_attr >> 6);
+            }
+            _ => (),
+        }
+
+        match (self.nt_mapping >> ((addr >> 10) * 2)) & 3 {
+            0 => nt_ram[offset],
+            1 => nt_ram[0x400 | offset],
+            2 if self.exram_mode <= 1 => self.exram[offset],
+            2 => 0,
+            3 if is_attr => Self::replicate_attr(self.fill_attr),
+            3 => self.fill_tile,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn write_nametable(&mut self, nt_ram: &mut [u8], addr: usize, val: u8) {
+        let offset = addr & 0x3FF;
+
+        match (self.nt_mapping >> ((addr >> 10) * 2)) & 3 {
+            0 => nt_ram[offset] = val,
+            1 => nt_ram[0x400 | offset] = val,
+            2 if self.exram_mode <= 1 => self.exram[offset] = val,
+            _ => (),
+        }
+    }
+
+    /// Copies the 2-bit palette number to all 4 quadrants of an attribute byte
+    fn replicate_attr(palette: u8) -> u8 {
+        palette * 0x55
+    }
+
+    pub fn read_chr(&self, cartridge: &Cartridge, addr: usize) -> u8 {
+        cartridge.read_chr(self.map_chr(addr))
+    }
+
+    pub fn write_chr(&mut self, cartridge: &mut Cartridge, addr: usize, val: u8) {
+        cartridge.write_chr(self.map_chr(addr), val);
+    }
+
+    fn map_chr(&self, addr: usize) -> usize {
+        let addr = match self.fetch {
+            Fetch::SplitBackground => {
+                let fine_y = self.split_y as usize & 7;
+                self.split_bank as usize * BankSize::Kb4 as usize + ((addr & 0xFF8) | fine_y)
+            }
+            // Extended attributes: bits 0-5 select a 4KB CHR bank, $5130 supplies the upper bits
+            Fetch::Background if self.exram_mode == 1 => {
+                let bank = (self.ex_attr & 0x3F) as usize | (self.chr_upper_bits as usize) << 6;
+                bank * BankSize::Kb4 as usize + (addr & 0xFFF)
+            }
+            /*
+            With 8x16 sprites, the sprites use set A and the background uses set B.
+            Outside of rendering, the last written set is used.
+            With 8x8 sprites, only set A is used.
+            */
+            fetch => {
+                let set_b = self.sprites_8x16
+                    && match fetch {
+                        Fetch::Background => true,
+                        Fetch::Sprite => false,
+                        _ => self.last_chr_set_b,
+                    };
+
+                self.map_chr_bank(addr, set_b)
+            }
+        };
+
+        addr % self.chr_len
+    }
+
+    /*
+    CHR mode 0: 8KB pages, $5127 (A) / $512B (B)
+    CHR mode 1: 4KB pages, $5123, $5127 (A) / $512B (B)
+    CHR mode 2: 2KB pages, $5121, $5123, $5125, $5127 (A) / $5129, $512B (B)
+    CHR mode 3: 1KB pages, $5120 - $5127 (A) / $5128 - $512B (B)
+
+    Set B only covers 4KB, $1000-$1FFF mirrors $0000-$0FFF
+    */
+    fn map_chr_bank(&self, addr: usize, set_b: bool) -> usize {
+        let (reg, size) = match (set_b, self.chr_mode) {
+            (false, 0) => (7, BankSize::Kb8),
+            (false, 1) => (3 + 4 * ((addr >> 12) & 1), BankSize::Kb4),
+            (false, 2) => (1 + 2 * ((addr >> 11) & 3), BankSize::Kb2),
+            (false, 3) => ((addr >> 10) & 7, BankSize::Kb1),
+            (true, 0) => (11, BankSize::Kb8),
+            (true, 1) => (11, BankSize::Kb4),
+            (true, 2) => (9 + 2 * ((addr >> 11) & 1), BankSize::Kb2),
+            (true, 3) => (8 + ((addr >> 10) & 3), BankSize::Kb1),
+            _ => unreachable!(),
+        };
+
+        let size = size as usize;
+        self.chr_regs[reg] as usize * size + (addr & (size - 1))
+    }
+
+    pub fn cpu_clock(&mut self, cpu_irq: &mut bool) {
+        if self.ppu_idle_cycles < 3 {
+            self.ppu_idle_cycles += 1;
+
+            if self.ppu_idle_cycles == 3 {
+                self.in_frame = false;
+                self.fetch = Fetch::Other;
+                self.last_nt_addr = 0;
+                self.update_irq(cpu_irq);
+            }
+        }
+
+        self.clock_audio();
+    }
+
+    /*
+    The MMC5 pulse channels are identical to the APU pulse channels, but they don't have the sweep
+    unit and their envelopes and length counters are clocked at a fixed rate of 240 Hz.
+    */
+    fn clock_audio(&mut self) {
+        self.audio_odd_cycle = !self.audio_odd_cycle;
+        if self.audio_odd_cycle {
+            self.pulse_1.timer_tick();
+            self.pulse_2.timer_tick();
+        }
+
+        // ~240 Hz on NTSC
+        self.audio_frame_cycles += 1;
+        if self.audio_frame_cycles == 7457 {
+            self.audio_frame_cycles = 0;
+
+            self.pulse_1.quarter_frame_clock();
+            self.pulse_1.half_frame_clock();
+            self.pulse_2.quarter_frame_clock();
+            self.pulse_2.half_frame_clock();
+        }
+    }
+
+    /*
+    In read mode, reading $8000-$BFFF puts the read value on the PCM output.
+    Reading $00 triggers the PCM IRQ instead.
+    */
+    fn pcm_read(&mut self, val: u8, cpu_irq: &mut bool) {
+        match val {
+            0 => {
+                self.pcm_irq = true;
+                self.update_irq(cpu_irq);
+            }
+            val => self.pcm = val,
+        }
+    }
+
+    pub fn audio_output(&self) -> i32 {
+        let pulse_out = (self.pulse_1.sequencer_output() + self.pulse_2.sequencer_output()) as f64;
+        // Same mixing as the APU pulses and DMC (which has only 7 bits)
+        let pulse = (477600. / (8128.0 / pulse_out + 100.0)) as i32;
+        let pcm = (818350. / (24329.0 / (self.pcm as f64 / 2.) + 100.0)) as i32;
+
+        pulse + pcm
+    }
+}
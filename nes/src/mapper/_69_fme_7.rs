@@ -2,6 +2,14 @@ use bincode::{Decode, Encode};
 
 use crate::{ppu::Mirroring, BankSize, Cartridge};
 
+/// Logarithmic 5-bit volume (1.5 dB per step), the loudest level is about as loud
+/// as an APU pulse at full volume
+#[rustfmt::skip]
+const VOLUME_TABLE: [u16; 32] = [
+    0, 4, 5, 6, 7, 8, 10, 12, 14, 17, 20, 24, 28, 34, 40, 47,
+    56, 67, 79, 94, 112, 133, 159, 188, 224, 266, 316, 376, 447, 531, 631, 750,
+];
+
 #[derive(Decode, Encode)]
 pub struct _69Fme7 {
     prg_0: usize,
@@ -30,6 +38,12 @@ pub struct _69Fme7 {
     fire_irq: bool,
 
     cmd: u8,
+
+    /**
+    Only the Sunsoft 5B variant has the expansion audio, but NES 2.0 has no submapper for it.
+    It's emulated for all of the boards, the FME-7 games never write to the audio registers.
+    **/
+    audio: Sunsoft5b,
 }
 
 impl _69Fme7 {
@@ -63,6 +77,8 @@ impl _69Fme7 {
             fire_irq: false,
 
             cmd: 0,
+
+            audio: Sunsoft5b::new(),
         }
     }
 
@@ -92,7 +108,19 @@ impl _69Fme7 {
             0xA000..=0xBFFF => Some(cartridge.read_prg_rom(self.prg_2 + addr - 0xA000)),
             0xC000..=0xDFFF => Some(cartridge.read_prg_rom(self.prg_3 + addr - 0xC000)),
             0xE000..=0xFFFF => Some(cartridge.read_prg_rom(self.prg_4 + addr - 0xE000)),
-            _ => unreachable!(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x6000..=0x7FFF if !self.prg_ram_selected => Some(self.prg_0 + addr - 0x6000),
+            0x8000..=0x9FFF => Some(self.prg_1 + addr - 0x8000),
+            0xA000..=0xBFFF => Some(self.prg_2 + addr - 0xA000),
+            0xC000..=0xDFFF => Some(self.prg_3 + addr - 0xC000),
+            0xE000..=0xFFFF => Some(self.prg_4 + addr - 0xE000),
+            _ => None,
         }
     }
 
@@ -129,6 +157,8 @@ impl _69Fme7 {
                 0xD..=0xF => self.set_irq(val),
                 _ => unreachable!(),
             },
+            0xC000..=0xDFFF => self.audio.select_reg(val),
+            0xE000..=0xFFFF => self.audio.write_reg(val),
             _ => (),
         }
     }
@@ -308,5 +338,224 @@ impl _69Fme7 {
         if self.irq_enable && self.fire_irq {
             *irq_signal = true;
         }
+
+        self.audio.clock();
+    }
+
+    pub fn audio_output(&self) -> i32 {
+        self.audio.output()
+    }
+}
+
+/**
+The Sunsoft 5B is an FME-7 with a YM2149F-compatible sound generator:
+3 square wave channels, a noise generator and an envelope generator.
+
+<https://wiki.nesdev.org/w/index.php?title=Sunsoft_5B_audio>
+**/
+#[derive(Decode, Encode)]
+struct Sunsoft5b {
+    selected_reg: u8,
+    /// The internal clock divides the CPU clock by 16
+    divider: u8,
+
+    tone_periods: [u16; 3],
+    tone_counters: [u16; 3],
+    tone_outputs: [bool; 3],
+
+    noise_period: u8,
+    noise_counter: u8,
+    /// 17-bit LFSR
+    noise_shift: u32,
+
+    /// Register $07, tone disable in bits 0-2 and noise disable in bits 3-5
+    disable: u8,
+    /// Registers $08 - $0A, bit 4 selects the envelope instead of the volume
+    volumes: [u8; 3],
+
+    envelope_period: u16,
+    envelope_counter: u16,
+    envelope_shape: u8,
+    envelope_step: u8,
+    envelope_attack: bool,
+    envelope_holding: bool,
+}
+
+impl Sunsoft5b {
+    fn new() -> Self {
+        Self {
+            selected_reg: 0,
+            divider: 0,
+
+            tone_periods: [0; 3],
+            tone_counters: [0; 3],
+            tone_outputs: [false; 3],
+
+            noise_period: 0,
+            noise_counter: 0,
+            noise_shift: 1,
+
+            disable: 0,
+            volumes: [0; 3],
+
+            envelope_period: 0,
+            envelope_counter: 0,
+            envelope_shape: 0,
+            envelope_step: 0,
+            envelope_attack: false,
+            envelope_holding: false,
+        }
+    }
+
+    /*
+    Audio Register Select ($C000-$DFFF)
+    7  bit  0
+    ---- ----
+    .... RRRR
+         ||||
+         ++++- The register to write to with $E000
+
+    Writes with the high 4 bits set disable the register writes.
+    */
+    fn select_reg(&mut self, val: u8) {
+        self.selected_reg = val;
+    }
+
+    /*
+    $00 - $05: period of the channels A, B and C (8 low bits, 4 high bits)
+    $06: noise period (5 bits)
+    $07: tone and noise disable
+    $08 - $0A: volume / envelope enable of the channels
+    $0B - $0C: envelope period (16 bits)
+    $0D: envelope shape
+    */
+    fn write_reg(&mut self, val: u8) {
+        match self.selected_reg {
+            reg @ 0..=5 => {
+                let ch = reg as usize / 2;
+                self.tone_periods[ch] = match reg & 1 {
+                    0 => (self.tone_periods[ch] & 0xF00) | val as u16,
+                    _ => (self.tone_periods[ch] & 0xFF) | ((val as u16 & 0xF) << 8),
+                };
+            }
+            6 => self.noise_period = val & 0x1F,
+            7 => self.disable = val,
+            reg @ 8..=0xA => self.volumes[reg as usize - 8] = val & 0x1F,
+            0xB => self.envelope_period = (self.envelope_period & 0xFF00) | val as u16,
+            0xC => self.envelope_period = (self.envelope_period & 0xFF) | (val as u16) << 8,
+            0xD => {
+                self.envelope_shape = val & 0xF;
+                self.envelope_attack = val & 4 != 0;
+                self.envelope_step = 0;
+                self.envelope_counter = 0;
+                self.envelope_holding = false;
+            }
+            _ => (),
+        }
+    }
+
+    fn clock(&mut self) {
+        self.divider += 1;
+        if self.divider < 16 {
+            return;
+        }
+        self.divider = 0;
+
+        for ch in 0..3 {
+            self.tone_counters[ch] += 1;
+            if self.tone_counters[ch] >= self.tone_periods[ch].max(1) {
+                self.tone_counters[ch] = 0;
+                self.tone_outputs[ch] = !self.tone_outputs[ch];
+            }
+        }
+
+        // The noise runs at half the rate of the tones
+        self.noise_counter += 1;
+        if self.noise_counter >= self.noise_period.max(1) * 2 {
+            self.noise_counter = 0;
+            let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 1;
+            self.noise_shift = (self.noise_shift >> 1) | (feedback << 16);
+        }
+
+        self.envelope_counter += 1;
+        if self.envelope_counter >= self.envelope_period.max(1) {
+            self.envelope_counter = 0;
+            self.clock_envelope();
+        }
+    }
+
+    /*
+    Envelope Shape ($0D)
+    7  bit  0
+    ---- ----
+    .... CAaH
+         ||||
+         |||+- Hold
+         ||+-- Alternate
+         |+--- Attack
+         +---- Continue
+
+    The envelope goes through 32 steps, up if attacking, down otherwise.
+    At the end it either stops at 0, holds, changes direction or starts over.
+    */
+    fn clock_envelope(&mut self) {
+        if self.envelope_holding {
+            return;
+        }
+
+        self.envelope_step += 1;
+        if self.envelope_step < 32 {
+            return;
+        }
+
+        let shape = self.envelope_shape;
+        if shape & 8 == 0 {
+            self.envelope_holding = true;
+            self.envelope_step = 31;
+            self.envelope_attack = false;
+        } else if shape & 1 != 0 {
+            self.envelope_holding = true;
+            self.envelope_step = 31;
+            if shape & 2 != 0 {
+                self.envelope_attack = !self.envelope_attack;
+            }
+        } else {
+            self.envelope_step = 0;
+            if shape & 2 != 0 {
+                self.envelope_attack = !self.envelope_attack;
+            }
+        }
+    }
+
+    fn envelope_level(&self) -> u8 {
+        match self.envelope_attack {
+            true => self.envelope_step,
+            false => 31 - self.envelope_step,
+        }
+    }
+
+    fn output(&self) -> i32 {
+        let noise = self.noise_shift & 1 != 0;
+
+        (0..3)
+            .map(|ch| {
+                let tone_on = self.tone_outputs[ch] || self.disable & (1 << ch) != 0;
+                let noise_on = noise || self.disable & (8 << ch) != 0;
+
+                // The 4-bit volumes are on the same scale as the 5-bit envelope
+                let level = match self.volumes[ch] & 0x10 != 0 {
+                    true => self.envelope_level(),
+                    false => match self.volumes[ch] & 0xF {
+                        0 => 0,
+                        volume => volume * 2 + 1,
+                    },
+                };
+
+                match tone_on && noise_on {
+                    true => VOLUME_TABLE[level as usize] as i32,
+                    false => 0,
+                }
+            })
+            .sum()
     }
 }
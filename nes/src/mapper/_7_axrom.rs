@@ -28,6 +28,14 @@ impl _7Axrom {
         }
     }
 
+    #[cfg(feature = "debug_tools")]
+    pub fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.prg_0 + addr - 0x8000),
+            _ => None,
+        }
+    }
+
     pub fn cpu_write(&mut self, addr: usize, val: u8) {
         if let 0x8000..=0xFFFF = addr {
             self.prg_0 = Cartridge::map_bank(val & 7, BankSize::Kb32);
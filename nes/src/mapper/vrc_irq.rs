@@ -0,0 +1,103 @@
+use bincode::{Decode, Encode};
+
+/// Prescaler of the scanline mode, in 1/3 CPU cycles (= PPU dots)
+const PRESCALER_RELOAD: i16 = 341;
+
+/**
+The IRQ counter shared by the Konami VRC4, VRC6 and VRC7.
+
+<https://wiki.nesdev.org/w/index.php?title=VRC_IRQ>
+**/
+#[derive(Decode, Encode)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enabled_after_ack: bool,
+    cycle_mode: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> Self {
+        Self {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_RELOAD,
+            enabled: false,
+            enabled_after_ack: false,
+            cycle_mode: false,
+        }
+    }
+
+    pub fn write_latch(&mut self, val: u8) {
+        self.latch = val;
+    }
+
+    /// The VRC4 splits the latch into 2 registers
+    pub fn write_latch_low(&mut self, val: u8) {
+        self.latch = (self.latch & 0xF0) | (val & 0xF);
+    }
+
+    pub fn write_latch_high(&mut self, val: u8) {
+        self.latch = (self.latch & 0xF) | (val << 4);
+    }
+
+    /*
+    7  bit  0
+    ---------
+    .... .MEA
+          |||
+          ||+- IRQ Enable after acknowledgement
+          |+-- IRQ Enable (1 = enabled)
+          +--- IRQ Mode (1 = cycle mode, 0 = scanline mode)
+    */
+    pub fn write_control(&mut self, val: u8, cpu_irq: &mut bool) {
+        self.enabled_after_ack = val & 1 != 0;
+        self.enabled = val & 2 != 0;
+        self.cycle_mode = val & 4 != 0;
+
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_RELOAD;
+        }
+
+        *cpu_irq = false;
+    }
+
+    /// Copies A to E and acknowledges the IRQ
+    pub fn acknowledge(&mut self, cpu_irq: &mut bool) {
+        self.enabled = self.enabled_after_ack;
+        *cpu_irq = false;
+    }
+
+    /*
+    In scanline mode, the prescaler divides the CPU clock by 113.667 (341 / 3) to approximate
+    the length of a scanline. In cycle mode, the counter is clocked on every CPU cycle.
+    */
+    pub fn cpu_clock(&mut self, cpu_irq: &mut bool) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.cycle_mode {
+            self.clock_counter(cpu_irq);
+        } else {
+            self.prescaler -= 3;
+            if self.prescaler <= 0 {
+                self.prescaler += PRESCALER_RELOAD;
+                self.clock_counter(cpu_irq);
+            }
+        }
+    }
+
+    /// When the counter overflows, it's reloaded from the latch and an IRQ is generated
+    fn clock_counter(&mut self, cpu_irq: &mut bool) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            *cpu_irq = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use crate::{ppu::Mirroring, Nes};
+
+/// The memories that can be inspected and edited by a debugger
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MemoryRegion {
+    /// The whole CPU address space, see peek_cpu and poke_cpu
+    CpuBus,
+    /// The 2KB of RAM of the console, mirrored up to $1FFF
+    InternalRam,
+    PrgRom,
+    /// The PRG RAM (volatile or battery-backed) of the cartridge
+    PrgRam,
+    /// CHR ROM or CHR RAM
+    Chr,
+    /// The 2KB of CIRAM, 4KB on four-screen cartridges
+    NametableRam,
+    Oam,
+    Palette,
+}
+
+impl MemoryRegion {
+    pub const ALL: [MemoryRegion; 8] = [
+        MemoryRegion::CpuBus,
+        MemoryRegion::InternalRam,
+        MemoryRegion::PrgRom,
+        MemoryRegion::PrgRam,
+        MemoryRegion::Chr,
+        MemoryRegion::NametableRam,
+        MemoryRegion::Oam,
+        MemoryRegion::Palette,
+    ];
+}
+
+impl fmt::Display for MemoryRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MemoryRegion::CpuBus => "CPU memory",
+            MemoryRegion::InternalRam => "Internal RAM",
+            MemoryRegion::PrgRom => "PRG ROM",
+            MemoryRegion::PrgRam => "PRG RAM",
+            MemoryRegion::Chr => "CHR",
+            MemoryRegion::NametableRam => "Nametable RAM",
+            MemoryRegion::Oam => "OAM",
+            MemoryRegion::Palette => "Palette",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl Nes {
+    /// Size of a memory region in bytes, 0 if the cartridge doesn't have it
+    pub fn memory_len(&self, region: MemoryRegion) -> usize {
+        let cartridge = &self.mapper.cartridge;
+
+        match region {
+            MemoryRegion::CpuBus => 0x10000,
+            MemoryRegion::InternalRam => 0x800,
+            MemoryRegion::PrgRom => cartridge.prg_rom_len(),
+            MemoryRegion::PrgRam => cartridge.prg_ram_len(),
+            MemoryRegion::Chr => cartridge.chr_len(),
+            MemoryRegion::NametableRam => match cartridge.header.mirroring {
+                Mirroring::FourScreen => 0x1000,
+                _ => 0x800,
+            },
+            MemoryRegion::Oam => 0x100,
+            MemoryRegion::Palette => 0x20,
+        }
+    }
+
+    /// Reads a byte of a memory region without any side effects. Out of range addresses read as 0.
+    pub fn peek_memory(&self, region: MemoryRegion, addr: usize) -> u8 {
+        if addr >= self.memory_len(region) {
+            return 0;
+        }
+
+        let cartridge = &self.mapper.cartridge;
+
+        match region {
+            MemoryRegion::CpuBus => self.peek_cpu(addr as u16),
+            MemoryRegion::InternalRam => self.peek_cpu(addr as u16),
+            MemoryRegion::PrgRom => cartridge.read_prg_rom(addr),
+            MemoryRegion::PrgRam => cartridge.read_prg_ram(addr).unwrap_or(0),
+            MemoryRegion::Chr => cartridge.read_chr(addr),
+            MemoryRegion::NametableRam => self.mapper.peek_nt_ram(addr),
+            MemoryRegion::Oam => self.peek_oam(addr as u8),
+            MemoryRegion::Palette => self.peek_palette(addr as u8),
+        }
+    }
+
+    /// Writes a byte of a memory region. Unlike poke_cpu, the PRG ROM and the CHR ROM are
+    /// patched. Out of range addresses are ignored.
+    pub fn poke_memory(&mut self, region: MemoryRegion, addr: usize, val: u8) {
+        if addr >= self.memory_len(region) {
+            return;
+        }
+
+        let cartridge = &mut self.mapper.cartridge;
+
+        match region {
+            MemoryRegion::CpuBus => self.poke_cpu(addr as u16, val),
+            MemoryRegion::InternalRam => self.poke_cpu(addr as u16, val),
+            MemoryRegion::PrgRom => cartridge.poke_prg_rom(addr, val),
+            MemoryRegion::PrgRam => cartridge.write_prg_ram(addr, val),
+            MemoryRegion::Chr => cartridge.poke_chr(addr, val),
+            MemoryRegion::NametableRam => self.mapper.poke_nt_ram(addr, val),
+            MemoryRegion::Oam => self.poke_oam(addr as u8, val),
+            MemoryRegion::Palette => self.poke_palette(addr as u8, val),
+        }
+    }
+}
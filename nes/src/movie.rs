@@ -0,0 +1,252 @@
+use bincode::{Decode, Encode};
+
+use crate::{controller::Button, Nes, NesError, ReplayInputs, TimingMode};
+
+mod fm2;
+
+pub use fm2::Fm2;
+
+/// The Four Score and the Famicom 4-player adapter have the most controllers
+const MAX_PLAYERS: usize = 4;
+
+/// The state the movie starts from
+#[derive(Encode, Decode)]
+pub enum MovieAnchor {
+    /// A freshly powered on console
+    PowerOn {
+        timing: TimingMode,
+    },
+    Savestate(Vec<u8>),
+}
+
+/**
+A recording of the controller inputs that can be played back deterministically.
+
+The input changes are applied at the start of the frames, so playing the movie back
+from the same anchor always gives the same result.
+**/
+#[derive(Encode, Decode)]
+pub struct Movie {
+    /// SHA-1 of the ROM the movie was recorded with, see `Nes::rom_sha1`
+    pub rom_sha1: [u8; 20],
+    pub anchor: MovieAnchor,
+    pub inputs: ReplayInputs,
+    /// How many times a savestate was loaded while recording
+    pub rerecord_count: u32,
+}
+
+impl Movie {
+    /// The movie starts from the power on of the game running in `nes`
+    pub fn from_power_on(nes: &Nes) -> Self {
+        Self::new(
+            nes,
+            MovieAnchor::PowerOn {
+                timing: nes.timing_mode(),
+            },
+        )
+    }
+
+    /// The movie starts from the current state of `nes`
+    pub fn from_savestate(nes: &Nes) -> Result<Self, NesError> {
+        Ok(Self::new(nes, MovieAnchor::Savestate(nes.save_state()?)))
+    }
+
+    fn new(nes: &Nes, anchor: MovieAnchor) -> Self {
+        let mut inputs = ReplayInputs::new();
+        inputs.end_frame = match &anchor {
+            MovieAnchor::PowerOn { .. } => 0,
+            MovieAnchor::Savestate(_) => nes.frame_count(),
+        };
+
+        Self {
+            rom_sha1: nes.rom_sha1(),
+            anchor,
+            inputs,
+            rerecord_count: 0,
+        }
+    }
+
+    /// Creates the Nes at the start of the movie
+    pub fn start(&self, rom: &[u8]) -> Result<Nes, NesError> {
+        let nes = match &self.anchor {
+            MovieAnchor::PowerOn { timing } => {
+                let mut nes = Nes::new(rom)?;
+                if nes.timing_mode() != *timing {
+                    nes.set_timing_mode(*timing);
+                }
+
+                nes
+            }
+            MovieAnchor::Savestate(save) => Nes::load_state(save)?,
+        };
+
+        match nes.rom_sha1() == self.rom_sha1 {
+            true => Ok(nes),
+            false => Err(NesError::MovieRomMismatch),
+        }
+    }
+
+    /// Length of the movie in frames (including the frames before a savestate anchor)
+    pub fn end_frame(&self) -> u64 {
+        self.inputs.end_frame
+    }
+
+    pub fn save(&self) -> Result<Vec<u8>, NesError> {
+        bincode::encode_to_vec(self, crate::BINCODE_CONFIG).map_err(|_| NesError::InvalidMovie)
+    }
+
+    pub fn load(save: &[u8]) -> Result<Movie, NesError> {
+        let (movie, _): (Movie, usize) = bincode::decode_from_slice(save, crate::BINCODE_CONFIG)
+            .map_err(|_| NesError::InvalidMovie)?;
+
+        movie.validate()?;
+        Ok(movie)
+    }
+
+    /// Every input change has to belong to one of the 4 players
+    fn validate(&self) -> Result<(), NesError> {
+        match self.inputs.inputs.iter().all(|ic| ic.player < MAX_PLAYERS) {
+            true => Ok(()),
+            false => Err(NesError::InvalidMovie),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieMode {
+    /// The inputs come from the movie, the player's inputs are ignored
+    ReadOnly,
+    /// The player's inputs are recorded into the movie
+    ReadWrite,
+}
+
+/**
+Drives the inputs of the Nes from a movie, or records them into it.
+
+`apply_inputs` has to be called at the start of every frame and the savestates
+should only be created and loaded between the frames.
+In read-write mode, loading a savestate cuts off the rest of the movie
+and continues recording from there (rerecording).
+**/
+pub struct MoviePlayer {
+    movie: Movie,
+    mode: MovieMode,
+    /// Index of the next input change to apply
+    cursor: usize,
+}
+
+impl MoviePlayer {
+    /// Creates the Nes at the start of the movie and the player driving it
+    pub fn start(movie: Movie, rom: &[u8], mode: MovieMode) -> Result<(Self, Nes), NesError> {
+        movie.validate()?;
+        let nes = movie.start(rom)?;
+
+        let mut player = Self {
+            movie,
+            mode,
+            cursor: 0,
+        };
+        player.seek(&nes);
+
+        if mode == MovieMode::ReadWrite {
+            player.movie.inputs.truncate(nes.frame_count());
+        }
+
+        Ok((player, nes))
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn into_movie(self) -> Movie {
+        self.movie
+    }
+
+    pub fn mode(&self) -> MovieMode {
+        self.mode
+    }
+
+    /// Switching to the read-write mode cuts off the rest of the movie
+    pub fn set_mode(&mut self, nes: &Nes, mode: MovieMode) {
+        if self.mode == MovieMode::ReadOnly && mode == MovieMode::ReadWrite {
+            self.movie.inputs.truncate(nes.frame_count());
+        }
+
+        self.mode = mode;
+    }
+
+    /// Returns true if the whole movie was played back
+    pub fn is_finished(&self, nes: &Nes) -> bool {
+        self.mode == MovieMode::ReadOnly && nes.frame_count() >= self.movie.end_frame()
+    }
+
+    /// Applies the input changes of the frame that is about to run.
+    /// In read-write mode, the frame becomes a part of the movie.
+    pub fn apply_inputs(&mut self, nes: &mut Nes) {
+        let frame = nes.frame_count();
+
+        match self.mode {
+            MovieMode::ReadOnly => {
+                let inputs = &self.movie.inputs.inputs;
+                while let Some(ic) = inputs.get(self.cursor).filter(|ic| ic.frame <= frame) {
+                    nes.set_player_button_state(ic.player, ic.button, ic.state);
+                    self.cursor += 1;
+                }
+            }
+            MovieMode::ReadWrite => {
+                self.movie.inputs.end_frame = self.movie.end_frame().max(frame + 1);
+            }
+        }
+    }
+
+    /// Applies the inputs and runs the frame
+    pub fn run_frame(&mut self, nes: &mut Nes) {
+        self.apply_inputs(nes);
+        nes.run_frame();
+    }
+
+    /// In read-write mode, the button state is recorded into the movie at the current frame.
+    /// In read-only mode, the player's inputs are ignored, and so are the players past the 4th.
+    pub fn set_button_state(&mut self, nes: &mut Nes, player: usize, button: Button, state: bool) {
+        if self.mode == MovieMode::ReadWrite && player < MAX_PLAYERS {
+            let frame = nes.frame_count();
+            self.movie
+                .inputs
+                .add_input_change(frame, player, button, state);
+            self.cursor = self.movie.inputs.inputs.len();
+
+            nes.set_player_button_state(player, button, state);
+        }
+    }
+
+    /// Has to be called after a savestate was loaded (or the game was rewound).
+    /// In read-write mode, this is a rerecord.
+    pub fn state_loaded(&mut self, nes: &Nes) {
+        if self.mode == MovieMode::ReadWrite {
+            self.movie.inputs.truncate(nes.frame_count());
+            self.movie.rerecord_count += 1;
+        }
+
+        self.seek(nes);
+    }
+
+    /// Loads a savestate into the Nes, see `state_loaded`
+    pub fn load_state(&mut self, nes: &mut Nes, save: &[u8]) -> Result<(), NesError> {
+        let mut loaded = Nes::load_state(save)?;
+        loaded.keep_channel_settings(nes);
+        *nes = loaded;
+        self.state_loaded(nes);
+        Ok(())
+    }
+
+    /// Skips the input changes which are already a part of the Nes state
+    fn seek(&mut self, nes: &Nes) {
+        let frame = nes.frame_count();
+        self.cursor = self
+            .movie
+            .inputs
+            .inputs
+            .partition_point(|ic| ic.frame < frame);
+    }
+}
@@ -0,0 +1,290 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::{
+    controller::{set_button, Button},
+    Movie, MovieAnchor, Nes, NesError, ReplayInputs, TimingMode,
+};
+
+/// The order of the buttons in the gamepad fields of the input log
+const FM2_BUTTONS_CHARS: &str = "RLDUTSBA";
+const FM2_BUTTONS: [Button; 8] = [
+    Button::Right,
+    Button::Left,
+    Button::Down,
+    Button::Up,
+    Button::Start,
+    Button::Select,
+    Button::B,
+    Button::A,
+];
+
+/// FCEUX's input device types of the ports
+const SI_NONE: u32 = 0;
+const SI_GAMEPAD: u32 = 1;
+
+/**
+The text movie format of FCEUX. Only movies starting from power on with gamepads are supported.
+
+The header consists of "key value" lines, the input log has a line for every frame:
+
+|c|RLDUTSBA|RLDUTSBA||
+ |    |        |     +--- Expansion port (unsupported)
+ |    |        +--------- Port 1 gamepad (with the Four Score, there are 4 gamepad fields)
+ |    +------------------ Port 0 gamepad, '.' or ' ' is a released button
+ +----------------------- Commands: 1 - soft reset, 2 - power on, 4 / 8 - FDS, 16 - VS coin
+
+<https://fceux.com/web/help/fm2.html>
+**/
+pub struct Fm2 {
+    pub rom_filename: String,
+    /// MD5 of the PRG ROM and CHR ROM, see `Nes::rom_md5`
+    pub rom_md5: [u8; 16],
+    pub rerecord_count: u32,
+    pub pal: bool,
+    pub four_score: bool,
+    pub inputs: ReplayInputs,
+}
+
+impl Fm2 {
+    pub fn parse(text: &str) -> Result<Self, NesError> {
+        let mut fm2 = Fm2 {
+            rom_filename: String::new(),
+            rom_md5: [0; 16],
+            rerecord_count: 0,
+            pal: false,
+            four_score: false,
+            inputs: ReplayInputs::new(),
+        };
+
+        let mut has_checksum = false;
+        let mut ports = [SI_GAMEPAD; 2];
+        let mut buttons = [0u8; 4];
+        let mut frame = 0;
+
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+
+            if !line.starts_with('|') {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                let number = || {
+                    value
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| NesError::InvalidMovie)
+                };
+
+                match key {
+                    "version" if number()? != 3 => return Err(NesError::InvalidMovie),
+                    "romFilename" => fm2.rom_filename = value.to_string(),
+                    "romChecksum" => {
+                        fm2.rom_md5 = parse_checksum(value)?;
+                        has_checksum = true;
+                    }
+                    "rerecordCount" => fm2.rerecord_count = number()?,
+                    "palFlag" => fm2.pal = number()? != 0,
+                    "fourscore" => fm2.four_score = number()? != 0,
+                    "port0" => ports[0] = number()?,
+                    "port1" => ports[1] = number()?,
+                    "port2" if number()? != SI_NONE => {
+                        return Err(NesError::MovieUnsupported("expansion port devices"))
+                    }
+                    "FDS" if number()? != 0 => {
+                        return Err(NesError::MovieUnsupported("Famicom Disk System"))
+                    }
+                    "binary" if number()? != 0 => {
+                        return Err(NesError::MovieUnsupported("binary input log"))
+                    }
+                    "savestate" => return Err(NesError::MovieUnsupported("savestate anchors")),
+                    _ => (),
+                }
+
+                continue;
+            }
+
+            // The Four Score replaces the port devices
+            if frame == 0
+                && !fm2.four_score
+                && ports.iter().any(|&p| p != SI_NONE && p != SI_GAMEPAD)
+            {
+                return Err(NesError::MovieUnsupported("other devices than gamepads"));
+            }
+
+            let mut fields = line.split('|').skip(1);
+
+            let commands: u32 = fields
+                .next()
+                .and_then(|c| c.trim().parse().ok())
+                .ok_or(NesError::InvalidMovie)?;
+            // Movies recorded by older FCEUX versions start with a reset, which is right
+            // after the power on anyway
+            if commands != 0 && frame != 0 {
+                return Err(NesError::MovieUnsupported("resets and other commands"));
+            }
+
+            let players = match fm2.four_score {
+                true => 4,
+                false => 2,
+            };
+
+            for (player, state) in buttons.iter_mut().enumerate().take(players) {
+                let field = fields.next().ok_or(NesError::InvalidMovie)?;
+                if !fm2.four_score && ports[player] == SI_NONE {
+                    continue;
+                }
+
+                let new_state = parse_gamepad(field)?;
+                for (bit, button) in Button::ALL.iter().enumerate() {
+                    let pressed = (new_state >> bit) & 1 != 0;
+                    if pressed != ((*state >> bit) & 1 != 0) {
+                        fm2.inputs.add_input_change(frame, player, *button, pressed);
+                    }
+                }
+
+                *state = new_state;
+            }
+
+            frame += 1;
+        }
+
+        if !has_checksum {
+            return Err(NesError::InvalidMovie);
+        }
+
+        fm2.inputs.end_frame = frame;
+        Ok(fm2)
+    }
+
+    pub fn export(&self) -> String {
+        let mut text = String::new();
+
+        let header = [
+            ("version", "3".to_string()),
+            ("rerecordCount", self.rerecord_count.to_string()),
+            ("palFlag", (self.pal as u8).to_string()),
+            ("romFilename", self.rom_filename.clone()),
+            (
+                "romChecksum",
+                format!("base64:{}", BASE64.encode(self.rom_md5)),
+            ),
+            ("fourscore", (self.four_score as u8).to_string()),
+            ("microphone", "0".to_string()),
+            ("port0", SI_GAMEPAD.to_string()),
+            ("port1", SI_GAMEPAD.to_string()),
+            ("port2", SI_NONE.to_string()),
+            ("FDS", "0".to_string()),
+            ("NewPPU", "0".to_string()),
+        ];
+
+        for (key, value) in header {
+            text.push_str(&format!("{} {}\n", key, value));
+        }
+
+        let players = match self.four_score {
+            true => 4,
+            false => 2,
+        };
+
+        let mut buttons = [0u8; 4];
+        let mut changes = self.inputs.inputs.iter().peekable();
+
+        for frame in 0..self.inputs.end_frame {
+            while let Some(ic) = changes.next_if(|ic| ic.frame <= frame) {
+                if let Some(state) = buttons.get_mut(ic.player) {
+                    *state = set_button(*state, ic.button, ic.state);
+                }
+            }
+
+            text.push_str("|0|");
+            for state in &buttons[..players] {
+                for (c, button) in FM2_BUTTONS_CHARS.chars().zip(FM2_BUTTONS) {
+                    let pressed = set_button(0, button, true) & state != 0;
+                    text.push(if pressed { c } else { '.' });
+                }
+                text.push('|');
+            }
+            text.push_str("|\n");
+        }
+
+        text
+    }
+
+    /// Converts a movie starting from power on
+    pub fn from_movie(movie: &Movie, nes: &Nes) -> Result<Self, NesError> {
+        if movie.rom_sha1 != nes.rom_sha1() {
+            return Err(NesError::MovieRomMismatch);
+        }
+
+        let timing = match movie.anchor {
+            MovieAnchor::PowerOn { timing } => timing,
+            MovieAnchor::Savestate(_) => {
+                return Err(NesError::MovieUnsupported("savestate anchors"))
+            }
+        };
+
+        Ok(Self {
+            rom_filename: String::new(),
+            rom_md5: nes.rom_md5(),
+            rerecord_count: movie.rerecord_count,
+            pal: timing == TimingMode::Pal,
+            four_score: movie.inputs.inputs.iter().any(|ic| ic.player >= 2),
+            inputs: ReplayInputs {
+                inputs: movie.inputs.inputs.clone(),
+                end_frame: movie.inputs.end_frame,
+            },
+        })
+    }
+
+    /// Converts the FM2 into a movie for the game running in `nes`
+    pub fn into_movie(self, nes: &Nes) -> Result<Movie, NesError> {
+        if self.rom_md5 != nes.rom_md5() {
+            return Err(NesError::MovieRomMismatch);
+        }
+
+        let timing = match self.pal {
+            true => TimingMode::Pal,
+            false => TimingMode::Ntsc,
+        };
+
+        Ok(Movie {
+            rom_sha1: nes.rom_sha1(),
+            anchor: MovieAnchor::PowerOn { timing },
+            inputs: self.inputs,
+            rerecord_count: self.rerecord_count,
+        })
+    }
+}
+
+/// Returns the button state in the report order of the standard controller
+fn parse_gamepad(field: &str) -> Result<u8, NesError> {
+    if field.len() != 8 {
+        return Err(NesError::InvalidMovie);
+    }
+
+    let mut buttons = 0;
+    for (c, button) in field.chars().zip(FM2_BUTTONS) {
+        let pressed = c != '.' && c != ' ';
+        buttons = set_button(buttons, button, pressed);
+    }
+
+    Ok(buttons)
+}
+
+/// The checksum is either "base64:..." or hexadecimal digits
+fn parse_checksum(value: &str) -> Result<[u8; 16], NesError> {
+    let value = value.trim();
+
+    let bytes = match value.strip_prefix("base64:") {
+        Some(base64) => BASE64.decode(base64).map_err(|_| NesError::InvalidMovie)?,
+        None => (0..value.len())
+            .step_by(2)
+            .map(|i| {
+                value
+                    .get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(NesError::InvalidMovie)?,
+    };
+
+    bytes.try_into().map_err(|_| NesError::InvalidMovie)
+}
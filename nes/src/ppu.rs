@@ -1,6 +1,9 @@
 use bincode::{Decode, Encode};
 
-use super::Nes;
+#[cfg(feature = "debug_tools")]
+use crate::EventKind;
+
+use super::{timing::TimingMode, Nes};
 
 /// This pallete maps the PPU output to RGB (24 bits RGB format)
 pub static PALETTE: [u8; 192] = [
@@ -24,6 +27,89 @@ pub enum Mirroring {
     FourScreen,
 }
 
+/// A snapshot of the PPU state, for debugging
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PpuRegisters {
+    pub scanline: u16,
+    pub dot: u16,
+    /// The current VRAM address (v)
+    pub vram_addr: u16,
+    /**
+    The temporary VRAM address (t), which is copied to v at the start of the frame:
+
+    yyy NN YYYYY XXXXX
+    ||| || ||||| +++++-- coarse X scroll
+    ||| || +++++-------- coarse Y scroll
+    ||| ++-------------- nametable select
+    +++----------------- fine Y scroll
+    **/
+    pub temp_vram_addr: u16,
+    pub fine_x: u8,
+    pub ppustatus: u8,
+    pub bg_pattern_table: u16,
+    pub sprite_pattern_table: u16,
+    /// 8 or 16
+    pub sprite_height: u8,
+    pub show_bg: bool,
+    pub show_sprites: bool,
+}
+
+impl PpuRegisters {
+    /// The top-left corner of the screen in the 512x480 picture of the 4 nametables
+    pub fn scroll(&self) -> (u16, u16) {
+        let t = self.temp_vram_addr;
+        let x = ((t & 0x1F) << 3 | self.fine_x as u16) + ((t >> 10) & 1) * 256;
+        let y = (((t >> 5) & 0x1F) << 3 | (t >> 12) & 7) + ((t >> 11) & 1) * 240;
+
+        (x, y)
+    }
+}
+
+/// A sprite of the primary OAM, for debugging
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OamEntry {
+    /// 0 - 63
+    pub index: u8,
+    /// The sprite is drawn one scanline lower than its Y coordinate
+    pub y: u8,
+    pub tile: u8,
+    /**
+    76543210
+    ||||||||
+    ||||||++- Palette (4 to 7) of sprite
+    |||+++--- Unimplemented
+    ||+------ Priority (0: in front of background; 1: behind background)
+    |+------- Flip sprite horizontally
+    +-------- Flip sprite vertically
+    **/
+    pub attributes: u8,
+    pub x: u8,
+}
+
+impl OamEntry {
+    /// 4 - 7, the sprite palettes
+    pub fn palette(&self) -> u8 {
+        4 + (self.attributes & 3)
+    }
+
+    pub fn behind_background(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+
+    pub fn horizontal_flip(&self) -> bool {
+        self.attributes & 0x40 != 0
+    }
+
+    pub fn vertical_flip(&self) -> bool {
+        self.attributes & 0x80 != 0
+    }
+
+    /// Whether the sprite evaluation of the scanline copies it to the secondary OAM
+    pub fn in_range(&self, scanline: u16, sprite_height: u8) -> bool {
+        scanline >= self.y as u16 && scanline < self.y as u16 + sprite_height as u16
+    }
+}
+
 #[derive(Clone, Copy, Decode, Encode)]
 struct Sprite {
     y: u8,
@@ -95,6 +181,12 @@ pub struct Ppu {
     pub cycle_count: u32,
     odd_frame: bool,
 
+    /// 261 on NTSC, 311 on PAL and Dendy
+    pre_render_scanline: u16,
+    /// 241 on NTSC and PAL, 291 on Dendy
+    vblank_scanline: u16,
+    skip_odd_frame_dot: bool,
+
     nametable_byte: u8,
     attribute: u8,
     tile_addr: usize,
@@ -136,7 +228,7 @@ pub struct Ppu {
 }
 
 impl Ppu {
-    pub(crate) fn new() -> Ppu {
+    pub(crate) fn new(timing: TimingMode) -> Ppu {
         let palettes = [
             0x09, 0x01, 0x00, 0x01, 0x00, 0x02, 0x02, 0x0D, 0x08, 0x10, 0x08, 0x24, 0x00, 0x00,
             0x04, 0x2C, 0x09, 0x01, 0x34, 0x03, 0x00, 0x04, 0x00, 0x14, 0x08, 0x3A, 0x00, 0x02,
@@ -174,6 +266,10 @@ impl Ppu {
             cycle_count: 0,
             odd_frame: false,
 
+            pre_render_scanline: timing.scanlines() - 1,
+            vblank_scanline: timing.vblank_scanline(),
+            skip_odd_frame_dot: timing.skips_odd_frame_dot(),
+
             nametable_byte: 0,
             attribute: 0,
             tile_addr: 0,
@@ -224,106 +320,229 @@ impl Nes {
 
         match addr {
             0..=0x1FFF => self.mapper.write_chr(addr, val),
-            0x2000..=0x3EFF => self.write_nametable(addr & 0xFFF, val),
+            0x2000..=0x3EFF => self.mapper.write_nametable(addr & 0xFFF, val),
             0x3F00..=0x3FFF => self.palette_write(addr, val),
             _ => unreachable!(),
         }
     }
 
-    #[inline]
-    fn write_nametable(&mut self, addr: usize, val: u8) {
-        match self.mapper.mirroring() {
-            Mirroring::Vertical => match addr {
-                0..=0x3FF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr + 0x800, val);
-                }
-                0x400..=0x7FF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr + 0x800, val);
-                }
-                0x800..=0xBFF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr - 0x400, val);
-                }
-                0xC00..=0xFFF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr - 0x800, val);
-                }
-                _ => unreachable!(),
-            },
-            Mirroring::Horizontal => match addr {
-                0..=0x3FF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr + 0x400, val);
-                }
-                0x400..=0x7FF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr - 0x400, val);
-                }
-                0x800..=0xBFF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr + 0x400, val);
-                }
-                0xC00..=0xFFF => {
-                    self.mapper.write_nametable(addr, val);
-                    self.mapper.write_nametable(addr - 0x400, val);
-                }
-                _ => unreachable!(),
-            },
-            Mirroring::SingleScreenLow => match addr {
-                0..=0x3FF => self.mapper.write_nametable(addr, val),
-                0x400..=0x7FF => self.mapper.write_nametable(addr - 0x400, val),
-                0x800..=0xBFF => self.mapper.write_nametable(addr - 0x800, val),
-                0xC00..=0xFFF => self.mapper.write_nametable(addr - 0xC00, val),
-                _ => unreachable!(),
-            },
-            Mirroring::SingleScreenHigh => match addr {
-                0..=0x3FF => self.mapper.write_nametable(addr + 0x400, val),
-                0x400..=0x7FF => self.mapper.write_nametable(addr, val),
-                0x800..=0xBFF => self.mapper.write_nametable(addr - 0x400, val),
-                0xC00..=0xFFF => self.mapper.write_nametable(addr - 0x800, val),
-                _ => unreachable!(),
-            },
-            Mirroring::FourScreen => self.mapper.write_nametable(addr, val),
-        }
-    }
-
     #[inline]
     fn ppu_read(&mut self, mut addr: usize) -> u8 {
         addr &= 0x3FFF;
 
         self.mapper
             .notify_a12(addr, &mut self.cpu.irq_mapper_signal);
+        self.mapper
+            .notify_ppu_read(addr, &mut self.cpu.irq_mapper_signal);
 
         match addr {
             0..=0x1FFF => self.mapper.read_chr(addr),
-            0x2000..=0x3EFF => self.read_nametable(addr & 0xFFF),
+            0x2000..=0x3EFF => self.mapper.read_nametable(addr & 0xFFF),
             0x3F00..=0x3FFF => self.palette_read(addr),
             _ => unreachable!(),
         }
     }
 
-    #[inline]
-    fn read_nametable(&mut self, addr: usize) -> u8 {
-        match self.mapper.mirroring() {
-            Mirroring::Vertical => self.mapper.read_nametable(addr),
-            Mirroring::Horizontal => self.mapper.read_nametable(addr),
-            Mirroring::SingleScreenLow => match addr {
-                0..=0x3FF => self.mapper.read_nametable(addr),
-                0x400..=0x7FF => self.mapper.read_nametable(addr - 0x400),
-                0x800..=0xBFF => self.mapper.read_nametable(addr - 0x800),
-                0xC00..=0xFFF => self.mapper.read_nametable(addr - 0xC00),
-                _ => unreachable!(),
-            },
-            Mirroring::SingleScreenHigh => match addr {
-                0..=0x3FF => self.mapper.read_nametable(addr + 0x400),
-                0x400..=0x7FF => self.mapper.read_nametable(addr),
-                0x800..=0xBFF => self.mapper.read_nametable(addr - 0x400),
-                0xC00..=0xFFF => self.mapper.read_nametable(addr - 0x800),
-                _ => unreachable!(),
-            },
-            Mirroring::FourScreen => self.mapper.read_nametable(addr),
+    /// Reads a byte from the PPU address space ($0000 - $3FFF) without notifying the mapper
+    pub fn peek_ppu(&self, addr: u16) -> u8 {
+        let addr = addr as usize & 0x3FFF;
+
+        match addr {
+            0..=0x1FFF => self.mapper.read_chr(addr),
+            0x2000..=0x3EFF => self.mapper.read_nametable(addr & 0xFFF),
+            _ => self.peek_palette(addr as u8),
+        }
+    }
+
+    /// Writes a byte to the PPU address space. Writes to CHR ROM are ignored.
+    pub fn poke_ppu(&mut self, addr: u16, val: u8) {
+        let addr = addr as usize & 0x3FFF;
+
+        match addr {
+            0..=0x1FFF => self.mapper.write_chr(addr, val),
+            0x2000..=0x3EFF => self.mapper.write_nametable(addr & 0xFFF, val),
+            _ => self.palette_write(addr, val),
+        }
+    }
+
+    /// A byte of the primary OAM
+    pub fn peek_oam(&self, addr: u8) -> u8 {
+        self.ppu.oam[addr as usize]
+    }
+
+    pub fn poke_oam(&mut self, addr: u8, val: u8) {
+        self.ppu.oam[addr as usize] = val;
+    }
+
+    /// A color index of the palette RAM (addr is mirrored every 32 bytes).
+    /// Unlike a PPU read, the greyscale mode isn't applied.
+    pub fn peek_palette(&self, addr: u8) -> u8 {
+        let mut addr = addr as usize & 0x1F;
+        if addr & 0x13 == 0x10 {
+            addr &= !0x10;
+        }
+
+        self.ppu.palettes[addr]
+    }
+
+    /// $3F10 / $3F14 / $3F18 / $3F1C are mirrors of $3F00 / $3F04 / $3F08 / $3F0C
+    pub fn poke_palette(&mut self, addr: u8, val: u8) {
+        self.palette_write(addr as usize, val);
+    }
+
+    pub fn ppu_registers(&self) -> PpuRegisters {
+        PpuRegisters {
+            scanline: self.ppu.scanline,
+            dot: self.ppu.xpos,
+            vram_addr: self.ppu.vram_addr as u16,
+            temp_vram_addr: self.ppu.temp_vram_addr as u16,
+            fine_x: self.ppu.x_fine_scroll,
+            ppustatus: self.ppu.ppustatus,
+            bg_pattern_table: self.ppu.bg_pattern_table_addr as u16,
+            sprite_pattern_table: self.ppu.sp_pattern_table_addr as u16,
+            sprite_height: self.ppu.sp_size,
+            show_bg: self.ppu.show_bg,
+            show_sprites: self.ppu.show_sp,
+        }
+    }
+
+    /// The current nametable mirroring of the cartridge
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper.mirroring()
+    }
+
+    /// The 2-bit color of the 8x8 pixels of the tile at addr ($0000 - $1FFF), row by row
+    pub fn tile_pixels(&self, addr: u16) -> [u8; 64] {
+        let addr = addr as usize & 0x1FF0;
+        let mut pixels = [0; 64];
+
+        for row in 0..8 {
+            let low = self.mapper.read_chr(addr + row);
+            let high = self.mapper.read_chr(addr + row + 8);
+
+            for col in 0..8 {
+                let bit = 7 - col;
+                pixels[row * 8 + col] = ((low >> bit) & 1) | ((high >> bit) & 1) << 1;
+            }
+        }
+
+        pixels
+    }
+
+    /// Renders a pattern table (0 or 1) as a 128x128 picture of 16x16 tiles with one of the
+    /// 8 palettes. The pixels are indexes into PALETTE.
+    pub fn pattern_table(&self, table: u16, palette: u8) -> Vec<u8> {
+        let mut picture = vec![0; 128 * 128];
+
+        for tile in 0..256 {
+            let pixels = self.tile_pixels((table & 1) * 0x1000 + tile * 16);
+            let (tile_x, tile_y) = ((tile as usize % 16) * 8, (tile as usize / 16) * 8);
+
+            for (i, &color) in pixels.iter().enumerate() {
+                let (x, y) = (tile_x + i % 8, tile_y + i / 8);
+                picture[y * 128 + x] = self.palette_color(palette, color);
+            }
+        }
+
+        picture
+    }
+
+    /// Renders the 4 nametables as a 512x480 picture (with the current mirroring and
+    /// background pattern table). The pixels are indexes into PALETTE.
+    pub fn nametables(&self) -> Vec<u8> {
+        let mut picture = vec![0; 512 * 480];
+        let pattern_table = self.ppu.bg_pattern_table_addr as u16;
+
+        for nametable in 0..4 {
+            let base = 0x2000 + nametable * 0x400;
+            let (nt_x, nt_y) = (
+                (nametable as usize & 1) * 256,
+                (nametable as usize >> 1) * 240,
+            );
+
+            for tile in 0..960 {
+                let (col, row) = (tile % 32, tile / 32);
+                let index = self.peek_ppu(base + tile) as u16;
+                let palette = self.attribute_palette(base, col, row);
+                let pixels = self.tile_pixels(pattern_table + index * 16);
+
+                for (i, &color) in pixels.iter().enumerate() {
+                    let x = nt_x + col as usize * 8 + i % 8;
+                    let y = nt_y + row as usize * 8 + i / 8;
+                    picture[y * 512 + x] = self.palette_color(palette, color);
+                }
+            }
+        }
+
+        picture
+    }
+
+    /// The palette (0 - 3) of a tile of a nametable, from its attribute table
+    pub fn attribute_palette(&self, nametable_base: u16, col: u16, row: u16) -> u8 {
+        let attribute = self.peek_ppu(nametable_base + 0x3C0 + (row / 4) * 8 + col / 4);
+        let shift = ((row & 2) << 1) | (col & 2);
+        (attribute >> shift) & 3
+    }
+
+    pub fn oam_entry(&self, index: u8) -> OamEntry {
+        let addr = (index as usize & 0x3F) * 4;
+        let oam = &self.ppu.oam[addr..addr + 4];
+
+        OamEntry {
+            index: index & 0x3F,
+            y: oam[0],
+            tile: oam[1],
+            attributes: oam[2],
+            x: oam[3],
+        }
+    }
+
+    /// The 2-bit colors of the 8x8 or 8x16 pixels of a sprite (with the current sprite size),
+    /// row by row. The flips are applied.
+    pub fn sprite_pixels(&self, entry: &OamEntry) -> Vec<u8> {
+        let tile = entry.tile as u16;
+        let tiles = match self.ppu.sp_size {
+            8 => vec![self.ppu.sp_pattern_table_addr as u16 + tile * 16],
+            _ => {
+                // 8x16 sprites use the pattern table selected by bit 0 of the tile index
+                let addr = (tile & 1) * 0x1000 + (tile & 0xFE) * 16;
+                vec![addr, addr + 16]
+            }
+        };
+
+        let mut pixels: Vec<u8> = tiles
+            .into_iter()
+            .flat_map(|addr| self.tile_pixels(addr))
+            .collect();
+
+        if entry.horizontal_flip() {
+            pixels.chunks_mut(8).for_each(|row| row.reverse());
+        }
+
+        if entry.vertical_flip() {
+            let rows: Vec<&[u8]> = pixels.chunks(8).rev().collect();
+            pixels = rows.concat();
+        }
+
+        pixels
+    }
+
+    /// The indexes of the sprites in range of the scanline, in OAM order. The first 8 are copied
+    /// to the secondary OAM and drawn on the next scanline, more than 8 is a sprite overflow.
+    /// (The sprite overflow flag itself is buggy on the hardware, see ppustatus)
+    pub fn scanline_sprites(&self, scanline: u16) -> Vec<u8> {
+        (0..64)
+            .map(|index| self.oam_entry(index))
+            .filter(|entry| entry.in_range(scanline, self.ppu.sp_size))
+            .map(|entry| entry.index)
+            .collect()
+    }
+
+    /// Palettes 0 - 3 are for the background, 4 - 7 for the sprites. Color 0 is the backdrop.
+    fn palette_color(&self, palette: u8, color: u8) -> u8 {
+        match color {
+            0 => self.peek_palette(0),
+            _ => self.peek_palette((palette & 7) * 4 + color),
         }
     }
 
@@ -354,7 +573,7 @@ impl Nes {
     }
 
     #[inline]
-    fn palette_read(&mut self, mut addr: usize) -> u8 {
+    fn palette_read(&self, mut addr: usize) -> u8 {
         addr &= 0x1F;
         if addr == 0x10 || addr == 0x14 || addr == 0x18 || addr == 0x1C {
             addr &= !0x10;
@@ -384,6 +603,18 @@ impl Nes {
         self.ppu.latch
     }
 
+    /// What a read of the register would return, without its side effects
+    pub(crate) fn ppu_peek_reg(&self, addr: usize) -> u8 {
+        match addr & 7 {
+            2 => self.ppu.ppustatus,
+            4 if self.ppu.scanline <= 239 && self.ppu.rendering_enabled => self.ppu.oamdata_buffer,
+            4 => self.ppu.oam[self.ppu.oamaddr as usize],
+            7 if (self.ppu.vram_addr & 0x3FFF) >= 0x3F00 => self.palette_read(self.ppu.vram_addr),
+            7 => self.ppu.read_buffer,
+            _ => self.ppu.latch,
+        }
+    }
+
     #[inline]
     pub(crate) fn ppu_write_reg(&mut self, addr: usize, val: u8) {
         self.ppu.latch = val;
@@ -473,16 +704,24 @@ impl Nes {
         // NMI for that frame. Reading on the same PPU clock or one later reads it as set,
         // clears it, and suppresses the NMI for that frame.
 
-        if self.ppu.scanline == 241 && (self.ppu.xpos == 2 || self.ppu.xpos == 3) {
+        if self.ppu.scanline == self.ppu.vblank_scanline
+            && (self.ppu.xpos == 2 || self.ppu.xpos == 3)
+        {
             self.ppu.latch |= 0x80;
             self.ppu.suppress_nmi = true;
             self.cpu.nmi_signal = false;
-        } else if self.ppu.scanline == 241 && self.ppu.xpos == 1 {
+        } else if self.ppu.scanline == self.ppu.vblank_scanline && self.ppu.xpos == 1 {
             self.ppu.latch &= 0x7F;
             self.ppu.suppress_nmi = true;
         }
     }
 
+    /// Visible scanlines and the pre-render scanline
+    #[inline]
+    fn is_render_scanline(&self) -> bool {
+        self.ppu.scanline <= 239 || self.ppu.scanline == self.ppu.pre_render_scanline
+    }
+
     #[inline]
     fn write_oamaddr(&mut self) {
         self.ppu.oamaddr = self.ppu.latch;
@@ -499,7 +738,7 @@ impl Nes {
 
     #[inline]
     fn write_oamdata(&mut self) {
-        if self.ppu.rendering_enabled && (self.ppu.scanline <= 239 || self.ppu.scanline == 261) {
+        if self.ppu.rendering_enabled && self.is_render_scanline() {
             self.ppu.oamaddr = self.ppu.oamaddr.wrapping_add(4);
         } else {
             let val = if self.ppu.oamaddr & 3 == 2 {
@@ -554,12 +793,10 @@ impl Nes {
 
         if (self.ppu.vram_addr & 0x3FFF) >= 0x3F00 {
             self.ppu.latch = self.palette_read(self.ppu.vram_addr);
-            self.ppu.read_buffer = self
-                .mapper
-                .read_nametable((self.ppu.vram_addr & 0x3FFF) - 0x3000);
+            self.ppu.read_buffer = self.mapper.read_nametable(self.ppu.vram_addr & 0xFFF);
         }
 
-        if self.ppu.rendering_enabled && (self.ppu.scanline < 240 || self.ppu.scanline == 261) {
+        if self.ppu.rendering_enabled && self.is_render_scanline() {
             self.coarse_x_increment();
             self.y_increment();
         } else {
@@ -574,7 +811,7 @@ impl Nes {
     fn write_ppudata(&mut self) {
         self.ppu_write(self.ppu.vram_addr, self.ppu.latch);
 
-        if self.ppu.rendering_enabled && (self.ppu.scanline < 240 || self.ppu.scanline == 261) {
+        if self.ppu.rendering_enabled && self.is_render_scanline() {
             self.coarse_x_increment();
             self.y_increment();
         } else {
@@ -607,7 +844,7 @@ impl Nes {
             self.ppu.xpos = 0;
             self.ppu.scanline += 1;
 
-            if self.ppu.scanline > 261 {
+            if self.ppu.scanline > self.ppu.pre_render_scanline {
                 self.ppu.scanline = 0;
             }
         }
@@ -665,8 +902,8 @@ impl Nes {
                 }
                 _ => (),
             },
-            241..=260 => self.vblank(),
-            261 => {
+            s if s >= self.ppu.vblank_scanline && s < self.ppu.pre_render_scanline => self.vblank(),
+            s if s == self.ppu.pre_render_scanline => {
                 match self.ppu.xpos {
                     2..=256 => {
                         self.fetch_bg();
@@ -709,8 +946,8 @@ impl Nes {
 
                         // The skipped tick is implemented by jumping directly from (339, 261)
                         // to (0, 0), meaning the last tick of the last NT fetch takes place at (0, 0)
-                        // on odd frames replacing the idle tick
-                        if self.ppu.odd_frame & self.ppu.show_bg {
+                        // on odd frames replacing the idle tick. PAL PPUs don't skip the tick.
+                        if self.ppu.odd_frame & self.ppu.show_bg & self.ppu.skip_odd_frame_dot {
                             self.ppu.xpos = 340;
                         }
 
@@ -726,7 +963,7 @@ impl Nes {
     #[inline]
     fn vblank(&mut self) {
         match (self.ppu.scanline, self.ppu.xpos) {
-            (241, 1) => {
+            (s, 1) if s == self.ppu.vblank_scanline => {
                 if !self.ppu.suppress_nmi {
                     self.ppu.ppustatus |= 0x80;
                 }
@@ -736,7 +973,7 @@ impl Nes {
                     self.cpu.nmi_signal = true;
                 }
             }
-            (241, 0) => (),
+            (s, 0) if s == self.ppu.vblank_scanline => (),
             _ => {
                 let current_nmi = self.ppu.nmi_on_vblank
                     && ((self.ppu.ppustatus & 0x80) != 0)
@@ -873,7 +1110,7 @@ impl Nes {
         sprite.priority = attributes & 0x20 != 0;
         sprite.palette = ((attributes & 3) << 2) | 0x10;
 
-        let scanline = if self.ppu.scanline == 261 {
+        let scanline = if self.ppu.scanline == self.ppu.pre_render_scanline {
             -1
         } else {
             self.ppu.scanline as i16
@@ -985,6 +1222,15 @@ impl Nes {
                         self.ppu.secondary_oam[self.ppu.secondary_oam_addr as usize & 0x1F];
 
                     if self.ppu.sprite_in_range {
+                        #[cfg(feature = "debug_tools")]
+                        if self.ppu.ppustatus & 0x20 == 0 {
+                            self.debug_events.add(
+                                EventKind::SpriteOverflow,
+                                self.ppu.scanline,
+                                self.ppu.xpos,
+                            );
+                        }
+
                         self.ppu.ppustatus |= 0x20;
                         self.ppu.sprite_fetch_step += 1;
                         if self.ppu.sprite_fetch_step == 4 {
@@ -1152,6 +1398,15 @@ impl Nes {
                             && bg_index != 0
                             && self.ppu.xpos != 256
                         {
+                            #[cfg(feature = "debug_tools")]
+                            if self.ppu.ppustatus & 0x40 == 0 {
+                                self.debug_events.add(
+                                    EventKind::Sprite0Hit,
+                                    self.ppu.scanline,
+                                    self.ppu.xpos,
+                                );
+                            }
+
                             self.ppu.ppustatus |= 0x40;
                         }
 
@@ -3,7 +3,12 @@ use bincode::{
     Decode, Encode,
 };
 
-use crate::{controller::Button, Nes};
+use crate::controller::Button;
+
+/// The saves start with this byte and the format version. The first versions of the format
+/// had no header and start with the varint length of the inputs, which is never 0xFF.
+const FORMAT_MARKER: u8 = 0xFF;
+const FORMAT_VERSION: u8 = 1;
 
 #[derive(Encode, Decode)]
 pub struct ReplayInputs {
@@ -19,44 +24,85 @@ impl ReplayInputs {
         }
     }
 
-    pub fn add_input_change(&mut self, frame: u64, button: Button, state: bool) {
+    pub fn add_input_change(&mut self, frame: u64, player: usize, button: Button, state: bool) {
         self.inputs.push(InputChange {
             frame,
+            player,
             button,
             state,
         });
     }
 
+    /// Removes the input changes from `frame` onwards, the replay then ends at `frame`
+    pub fn truncate(&mut self, frame: u64) {
+        let len = self.inputs.partition_point(|ic| ic.frame < frame);
+        self.inputs.truncate(len);
+        self.end_frame = frame;
+    }
+
     pub fn save_with_end_frame(&mut self, end_frame: u64) -> Result<Vec<u8>, EncodeError> {
         self.end_frame = end_frame;
-        bincode::encode_to_vec(&*self, crate::BINCODE_CONFIG)
+
+        let mut save = vec![FORMAT_MARKER, FORMAT_VERSION];
+        save.extend(bincode::encode_to_vec(&*self, crate::BINCODE_CONFIG)?);
+        Ok(save)
     }
 
+    /// Also loads the saves without a header, which only have the inputs of the first player
     pub fn load_state(save: &[u8]) -> Result<ReplayInputs, DecodeError> {
-        let (replay_inputs, _) = bincode::decode_from_slice(save, crate::BINCODE_CONFIG)?;
-        Ok(replay_inputs)
+        match save {
+            [FORMAT_MARKER, FORMAT_VERSION, save @ ..] => {
+                let (replay_inputs, _) = bincode::decode_from_slice(save, crate::BINCODE_CONFIG)?;
+                Ok(replay_inputs)
+            }
+            [FORMAT_MARKER, ..] => Err(DecodeError::Other("unsupported replay format version")),
+            _ => {
+                let (legacy, _): (LegacyReplayInputs, usize) =
+                    bincode::decode_from_slice(save, crate::BINCODE_CONFIG)?;
+                Ok(legacy.into())
+            }
+        }
     }
 }
 
-#[derive(Encode, Decode)]
+#[derive(Clone, Encode, Decode)]
 pub struct InputChange {
     pub frame: u64,
+    pub player: usize,
     pub button: Button,
     pub state: bool,
 }
 
-impl Nes {
-    pub(crate) fn _drive_replay_inputs(&mut self, inputs: &ReplayInputs) {
-        for ic in &inputs.inputs {
-            while self.frame_count < ic.frame {
-                self.run_frame();
-            }
+/// The layout before the second controller port was added
+#[derive(Decode)]
+struct LegacyReplayInputs {
+    inputs: Vec<LegacyInputChange>,
+    end_frame: u64,
+}
 
-            self.set_button_state(ic.button, ic.state);
-        }
+#[derive(Decode)]
+struct LegacyInputChange {
+    frame: u64,
+    button: Button,
+    state: bool,
+}
+
+impl From<LegacyReplayInputs> for ReplayInputs {
+    fn from(legacy: LegacyReplayInputs) -> Self {
+        let inputs = legacy
+            .inputs
+            .into_iter()
+            .map(|ic| InputChange {
+                frame: ic.frame,
+                player: 0,
+                button: ic.button,
+                state: ic.state,
+            })
+            .collect();
 
-        while self.frame_count < inputs.end_frame {
-            self.run_frame();
+        Self {
+            inputs,
+            end_frame: legacy.end_frame,
         }
     }
 }
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use crate::{Nes, NesError};
+
+/// Number of snapshots that are stored as deltas against the same keyframe
+const CHUNK_SIZE: usize = 60;
+
+/**
+A ring buffer of savestates that can be used to go back in time.
+
+Creating a full savestate every frame would take too much memory, so the snapshots are
+grouped into chunks. The first snapshot in a chunk is a full savestate (the keyframe) and the
+rest are stored as the XOR with the keyframe, run-length encoded.
+Most of the machine state doesn't change between the frames, so the deltas are mostly zeros.
+The oldest chunk is dropped once the newer ones hold `capacity` snapshots, so there are
+always at least `capacity` snapshots (if that many were captured) and at most CHUNK_SIZE - 1 more.
+**/
+pub struct Rewind {
+    /// Number of frames between the snapshots
+    interval: u64,
+    /// Minimum number of snapshots kept
+    capacity: usize,
+
+    chunks: VecDeque<Chunk>,
+    len: usize,
+}
+
+struct Chunk {
+    keyframe: Vec<u8>,
+    keyframe_frame: u64,
+    deltas: Vec<Delta>,
+}
+
+struct Delta {
+    frame: u64,
+    /// Length of the savestate
+    len: usize,
+    data: Vec<u8>,
+}
+
+impl Rewind {
+    /// Keeps `capacity` snapshots, one for every `interval` frames
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+
+            chunks: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    /// Should be called after every frame. Creates a snapshot if it's time for it.
+    pub fn capture(&mut self, nes: &Nes) -> Result<(), NesError> {
+        let frame = nes.frame_count();
+        if !frame.is_multiple_of(self.interval) {
+            return Ok(());
+        }
+
+        match self.newest_frame() {
+            // Right after rewinding, the snapshot of this frame already exists
+            Some(newest) if newest == frame => return Ok(()),
+            // A different state was loaded, the history isn't valid anymore
+            Some(newest) if newest > frame => self.clear(),
+            _ => (),
+        }
+
+        let state = nes.save_state()?;
+
+        match self.chunks.back_mut() {
+            Some(chunk) if chunk.deltas.len() + 1 < CHUNK_SIZE => {
+                chunk.deltas.push(Delta {
+                    frame,
+                    len: state.len(),
+                    data: encode_delta(&chunk.keyframe, &state),
+                });
+            }
+            _ => self.chunks.push_back(Chunk {
+                keyframe: state,
+                keyframe_frame: frame,
+                deltas: Vec::new(),
+            }),
+        }
+
+        self.len += 1;
+        while let Some(oldest) = self.chunks.front() {
+            let oldest_len = oldest.deltas.len() + 1;
+            if self.len - oldest_len < self.capacity {
+                break;
+            }
+
+            self.chunks.pop_front();
+            self.len -= oldest_len;
+        }
+
+        Ok(())
+    }
+
+    /// Restores the newest snapshot that is at least `frames` frames old (or the oldest one
+    /// if there isn't any), newer snapshots are discarded.
+    /// Returns false if there isn't any snapshot to go back to.
+    pub fn rewind(&mut self, nes: &mut Nes, frames: u64) -> Result<bool, NesError> {
+        let target = nes.frame_count().saturating_sub(frames);
+
+        while self.len > 1 && self.newest_frame().is_some_and(|frame| frame > target) {
+            self.pop_newest();
+        }
+
+        let chunk = match self.chunks.back() {
+            Some(chunk) => chunk,
+            None => return Ok(false),
+        };
+
+        let mut rewound = match chunk.deltas.last() {
+            Some(delta) => Nes::load_state(&decode_delta(&chunk.keyframe, delta))?,
+            None => Nes::load_state(&chunk.keyframe)?,
+        };
+        rewound.keep_channel_settings(nes);
+        *nes = rewound;
+
+        Ok(true)
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn newest_frame(&self) -> Option<u64> {
+        let chunk = self.chunks.back()?;
+        let frame = match chunk.deltas.last() {
+            Some(delta) => delta.frame,
+            None => chunk.keyframe_frame,
+        };
+
+        Some(frame)
+    }
+
+    fn pop_newest(&mut self) {
+        if let Some(chunk) = self.chunks.back_mut() {
+            if chunk.deltas.pop().is_none() {
+                self.chunks.pop_back();
+            }
+
+            self.len -= 1;
+        }
+    }
+}
+
+/*
+The delta is a sequence of runs:
+- the number of zero bytes (LEB128)
+- the number of literal bytes (LEB128)
+- the literal bytes
+*/
+fn encode_delta(keyframe: &[u8], state: &[u8]) -> Vec<u8> {
+    let xor = |i: usize| state[i] ^ keyframe.get(i).copied().unwrap_or(0);
+
+    let mut data = Vec::new();
+    let mut i = 0;
+    while i < state.len() {
+        let zeros_start = i;
+        while i < state.len() && xor(i) == 0 {
+            i += 1;
+        }
+
+        let literal_start = i;
+        while i < state.len() && xor(i) != 0 {
+            i += 1;
+        }
+
+        write_leb128(&mut data, literal_start - zeros_start);
+        write_leb128(&mut data, i - literal_start);
+        data.extend((literal_start..i).map(xor));
+    }
+
+    data
+}
+
+fn decode_delta(keyframe: &[u8], delta: &Delta) -> Vec<u8> {
+    let mut state = keyframe.to_vec();
+    state.resize(delta.len, 0);
+
+    let data = &delta.data;
+    let mut pos = 0;
+    let mut i = 0;
+    while pos < data.len() {
+        i += read_leb128(data, &mut pos);
+        let literals = read_leb128(data, &mut pos);
+
+        for byte in &data[pos..pos + literals] {
+            state[i] ^= byte;
+            i += 1;
+        }
+        pos += literals;
+    }
+
+    state
+}
+
+fn write_leb128(data: &mut Vec<u8>, mut val: usize) {
+    loop {
+        let byte = (val & 0x7F) as u8;
+        val >>= 7;
+
+        if val == 0 {
+            data.push(byte);
+            return;
+        }
+
+        data.push(byte | 0x80);
+    }
+}
+
+fn read_leb128(data: &[u8], pos: &mut usize) -> usize {
+    let mut val = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+
+        val |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            return val;
+        }
+    }
+}
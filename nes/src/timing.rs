@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use bincode::{Decode, Encode};
+
+use crate::cartridge::Region;
+
+/** <https://wiki.nesdev.org/w/index.php?title=Cycle_reference_chart> **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub enum TimingMode {
+    /// RP2A03 / RP2C02
+    Ntsc,
+    /// RP2A07 / RP2C07
+    Pal,
+    /// UMC 6527P, a PAL famiclone with NTSC-like CPU / PPU ratio
+    Dendy,
+}
+
+impl TimingMode {
+    pub fn from_region(region: &Region) -> Self {
+        match region {
+            Region::Ntsc | Region::Multi => TimingMode::Ntsc,
+            Region::Pal => TimingMode::Pal,
+            Region::Dendy => TimingMode::Dendy,
+        }
+    }
+
+    /// CPU clock rate in Hz
+    pub fn cpu_clock_rate(self) -> f64 {
+        match self {
+            // 21.477272 MHz ÷ 12
+            TimingMode::Ntsc => 1789772.7272,
+            // 26.601712 MHz ÷ 16
+            TimingMode::Pal => 1662607.0,
+            // 26.601712 MHz ÷ 15
+            TimingMode::Dendy => 1773447.4667,
+        }
+    }
+
+    /// How often the PPU produces a frame in Hz
+    pub fn frame_rate(self) -> f64 {
+        match self {
+            TimingMode::Ntsc => 60.0988,
+            TimingMode::Pal | TimingMode::Dendy => 50.0070,
+        }
+    }
+
+    /// Number of scanlines per frame, including the pre-render scanline
+    pub fn scanlines(self) -> u16 {
+        match self {
+            TimingMode::Ntsc => 262,
+            TimingMode::Pal | TimingMode::Dendy => 312,
+        }
+    }
+
+    /// The scanline on which the vblank flag is set.
+    /// Dendy has 51 post-render scanlines to keep the NTSC vblank length.
+    pub(crate) fn vblank_scanline(self) -> u16 {
+        match self {
+            TimingMode::Ntsc | TimingMode::Pal => 241,
+            TimingMode::Dendy => 291,
+        }
+    }
+
+    /// NTSC and Dendy have 3 PPU dots per CPU cycle, PAL has 3.2,
+    /// so every 5th CPU cycle has an extra dot
+    pub(crate) fn has_extra_ppu_dot(self, cpu_cycle: u64) -> bool {
+        self == TimingMode::Pal && cpu_cycle.is_multiple_of(5)
+    }
+
+    /// Only the NTSC PPU skips a dot on odd frames
+    pub(crate) fn skips_odd_frame_dot(self) -> bool {
+        self == TimingMode::Ntsc
+    }
+
+    /// The PPU ignores writes to some registers until the end of the first vblank.
+    /// <https://wiki.nesdev.org/w/index.php?title=PPU_power_up_state>
+    pub(crate) fn ppu_warmup_cycles(self) -> u64 {
+        match self {
+            TimingMode::Ntsc => 29658,
+            TimingMode::Pal => 33132,
+            // Not measured, derived from the NTSC value and the 50 extra scanlines
+            TimingMode::Dendy => 35341,
+        }
+    }
+
+    /// Number of CPU cycles needed to run a whole scanline (rounded up)
+    pub(crate) fn cycles_per_scanline(self) -> u32 {
+        match self {
+            TimingMode::Ntsc | TimingMode::Dendy => 114,
+            TimingMode::Pal => 107,
+        }
+    }
+}
+
+impl Display for TimingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimingMode::Ntsc => write!(f, "NTSC"),
+            TimingMode::Pal => write!(f, "PAL"),
+            TimingMode::Dendy => write!(f, "Dendy"),
+        }
+    }
+}
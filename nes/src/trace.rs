@@ -0,0 +1,156 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{disassembler::AddressingMode::*, CpuRegisters, Instruction, Nes};
+
+const JMP: u8 = 0x4C;
+const JSR: u8 = 0x20;
+
+/// Writes a line per executed instruction, in the nestest.log (Nintendulator) layout:
+///
+/// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+pub struct TraceLogger {
+    out: Box<dyn Write + Send>,
+}
+
+impl TraceLogger {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self { out: Box::new(out) }
+    }
+
+    pub fn to_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Logs the instruction that is about to be executed
+    pub fn log(&mut self, nes: &Nes) -> io::Result<()> {
+        writeln!(self.out, "{}", nes.trace_line())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Nes {
+    /// The trace line of the instruction that is about to be executed
+    pub fn trace_line(&self) -> String {
+        let regs = self.cpu_registers();
+        let instruction = self.disassemble(regs.pc);
+
+        let bytes: Vec<String> = instruction
+            .bytes()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+
+        let illegal = match instruction.info().illegal {
+            true => '*',
+            false => ' ',
+        };
+
+        let disassembly = format!(
+            "{}{}",
+            instruction.info().mnemonic,
+            self.trace_operand(&instruction, &regs)
+        );
+
+        format!(
+            "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            regs.pc,
+            bytes.join(" "),
+            illegal,
+            disassembly,
+            regs.a,
+            regs.x,
+            regs.y,
+            regs.p,
+            regs.sp,
+            self.ppu.scanline,
+            self.ppu.xpos,
+            self.cycle_count,
+        )
+    }
+
+    /// The operand, annotated with the effective address and the memory contents like Nintendulator
+    fn trace_operand(&self, instruction: &Instruction, regs: &CpuRegisters) -> String {
+        let operand = instruction.operand;
+        let peek_word =
+            |lo: u16, hi: u16| u16::from_le_bytes([self.peek_cpu(lo), self.peek_cpu(hi)]);
+
+        match instruction.info().mode {
+            Implied => String::new(),
+            Accumulator => " A".to_string(),
+            Immediate => format!(" #${:02X}", operand),
+            Relative => format!(" ${:04X}", instruction.target().unwrap_or(0)),
+            ZeroPage => format!(" ${:02X} = {:02X}", operand, self.peek_cpu(operand)),
+            ZeroPageX | ZeroPageY => {
+                let (index, name) = match instruction.info().mode {
+                    ZeroPageX => (regs.x, 'X'),
+                    _ => (regs.y, 'Y'),
+                };
+                let addr = (operand as u8).wrapping_add(index) as u16;
+
+                format!(
+                    " ${:02X},{} @ {:02X} = {:02X}",
+                    operand,
+                    name,
+                    addr,
+                    self.peek_cpu(addr)
+                )
+            }
+            Absolute => match instruction.opcode {
+                JMP | JSR => format!(" ${:04X}", operand),
+                _ => format!(" ${:04X} = {:02X}", operand, self.peek_cpu(operand)),
+            },
+            AbsoluteX | AbsoluteY => {
+                let (index, name) = match instruction.info().mode {
+                    AbsoluteX => (regs.x, 'X'),
+                    _ => (regs.y, 'Y'),
+                };
+                let addr = operand.wrapping_add(index as u16);
+
+                format!(
+                    " ${:04X},{} @ {:04X} = {:02X}",
+                    operand,
+                    name,
+                    addr,
+                    self.peek_cpu(addr)
+                )
+            }
+            Indirect => {
+                // The high byte isn't read from the next page
+                let hi = (operand & 0xFF00) | (operand as u8).wrapping_add(1) as u16;
+                format!(" (${:04X}) = {:04X}", operand, peek_word(operand, hi))
+            }
+            IndirectX => {
+                let ptr = (operand as u8).wrapping_add(regs.x);
+                let addr = peek_word(ptr as u16, ptr.wrapping_add(1) as u16);
+
+                format!(
+                    " (${:02X},X) @ {:02X} = {:04X} = {:02X}",
+                    operand,
+                    ptr,
+                    addr,
+                    self.peek_cpu(addr)
+                )
+            }
+            IndirectY => {
+                let ptr = operand as u8;
+                let base = peek_word(ptr as u16, ptr.wrapping_add(1) as u16);
+                let addr = base.wrapping_add(regs.y as u16);
+
+                format!(
+                    " (${:02X}),Y = {:04X} @ {:04X} = {:02X}",
+                    operand,
+                    base,
+                    addr,
+                    self.peek_cpu(addr)
+                )
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
+use std::{env, fs};
+
 use common::blargg_test;
+use fearless_nes::{ApuChannel, Nes, Rewind};
 
 mod common;
 
@@ -31,3 +34,101 @@ fn apu_irq_flag() {
 fn apu_blargg_all() {
     blargg_test("apu/apu_test/apu_test.nes", "passed");
 } */
+
+/// The sum of the absolute sample values of frames 35 - 44. The ROM beeps with
+/// pulse 1 (A4) at frame 38, the other channels are silent or constant by then.
+fn beep_loudness(setup: impl Fn(&mut Nes)) -> (Nes, u64) {
+    let base_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let rom = fs::read(base_dir + "/tests/apu/apu_test/rom_singles/7-dmc_basics.nes").unwrap();
+
+    let mut nes = Nes::new(&rom).unwrap();
+    setup(&mut nes);
+
+    let mut loudness = 0;
+    let mut samples = Vec::new();
+    for frame in 0..45 {
+        nes.run_frame();
+        nes.apu_samples(&mut samples);
+        if frame >= 35 {
+            loudness += samples.iter().map(|s| s.unsigned_abs() as u64).sum::<u64>();
+        }
+        samples.clear();
+    }
+
+    (nes, loudness)
+}
+
+#[test]
+fn apu_channel_mute_solo_volume() {
+    let (nes, full) = beep_loudness(|_| ());
+    assert!(full > 0);
+    assert!(nes.channel_enabled(ApuChannel::Pulse1));
+    assert_eq!(nes.channel_solo(), None);
+
+    let (_, muted) = beep_loudness(|nes| nes.set_channel_enabled(ApuChannel::Pulse1, false));
+    assert_eq!(muted, 0);
+
+    let (_, solo) = beep_loudness(|nes| nes.set_channel_solo(Some(ApuChannel::Triangle)));
+    assert_eq!(solo, 0);
+
+    // Solo overrides the enabled state
+    let (_, solo) = beep_loudness(|nes| {
+        nes.set_channel_enabled(ApuChannel::Pulse1, false);
+        nes.set_channel_solo(Some(ApuChannel::Pulse1));
+    });
+    assert_eq!(solo, full);
+
+    let (nes, half) = beep_loudness(|nes| nes.set_channel_volume(ApuChannel::Pulse1, 0.5));
+    assert_eq!(nes.channel_volume(ApuChannel::Pulse1), 0.5);
+    assert!(half > full / 3 && half < full * 2 / 3);
+}
+
+#[test]
+fn apu_channel_settings_arent_saved() {
+    let (mut nes, _) = beep_loudness(|nes| {
+        nes.set_channel_enabled(ApuChannel::Pulse1, false);
+        nes.set_channel_volume(ApuChannel::Triangle, 0.25);
+    });
+
+    let mut loaded = Nes::load_state(&nes.save_state().unwrap()).unwrap();
+    assert!(loaded.channel_enabled(ApuChannel::Pulse1));
+    assert_eq!(loaded.channel_volume(ApuChannel::Triangle), 1.);
+
+    loaded.keep_channel_settings(&nes);
+    assert!(!loaded.channel_enabled(ApuChannel::Pulse1));
+    assert_eq!(loaded.channel_volume(ApuChannel::Triangle), 0.25);
+
+    // Rewinding keeps the settings
+    let mut rewind = Rewind::new(1, 10);
+    rewind.capture(&nes).unwrap();
+    nes.run_frame();
+    nes.set_channel_solo(Some(ApuChannel::Noise));
+
+    assert!(rewind.rewind(&mut nes, 1).unwrap());
+    assert!(!nes.channel_enabled(ApuChannel::Pulse1));
+    assert_eq!(nes.channel_volume(ApuChannel::Triangle), 0.25);
+    assert_eq!(nes.channel_solo(), Some(ApuChannel::Noise));
+}
+
+#[test]
+fn apu_channel_state() {
+    let (nes, _) = beep_loudness(|_| ());
+
+    let pulse = nes.channel_state(ApuChannel::Pulse1);
+    assert_eq!(pulse.period, 256);
+    assert_eq!(pulse.note().as_deref(), Some("A4"));
+    assert_eq!(pulse.duty, Some(0));
+    assert!(pulse.constant_volume);
+    assert!(pulse.length_counter_halted);
+
+    let noise = nes.channel_state(ApuChannel::Noise);
+    assert_eq!(noise.note(), None);
+    assert_eq!(nes.channel_state(ApuChannel::Dmc).length_counter, None);
+
+    #[cfg(feature = "debug_tools")]
+    {
+        let scope = nes.channel_scope(ApuChannel::Dmc);
+        assert_eq!(scope.len(), 1024);
+        assert!(scope.iter().all(|&out| out == 64));
+    }
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::build_rom;
+use fearless_nes::{HeaderSource, Nes, NesError, Region};
+
+// NROM-256 with a battery, 1 bank of CHR ROM
+#[rustfmt::skip]
+const BATTERY_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0000_0010, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// MMC1 with a NES 2.0 header, 8 banks of PRG ROM and 8KB of CHR RAM
+#[rustfmt::skip]
+const INES2_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 8, 0, 0b0001_0010, 0b0000_1000, 0, 0, 0x70, 0x07, 0, 0, 0, 0,
+];
+
+#[test]
+fn battery_ram_roundtrip() {
+    let rom = build_rom(BATTERY_HEADER, 0x8000, 0x2000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    assert_eq!(nes.battery_ram().map(|ram| ram.len()), Some(0x2000));
+
+    let save: Vec<u8> = (0..0x2000).map(|i| i as u8).collect();
+    nes.load_battery_ram(&save).unwrap();
+    assert_eq!(nes.battery_ram(), Some(save.as_slice()));
+
+    // Savestates must carry the battery RAM with them
+    let state = nes.save_state().unwrap();
+    let nes = Nes::load_state(&state).unwrap();
+    assert_eq!(nes.battery_ram(), Some(save.as_slice()));
+}
+
+#[test]
+fn battery_ram_size_mismatch() {
+    let rom = build_rom(BATTERY_HEADER, 0x8000, 0x2000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    assert!(matches!(
+        nes.load_battery_ram(&[0; 0x1000]),
+        Err(NesError::InvalidBatterySave)
+    ));
+}
+
+#[test]
+fn no_battery_ram_without_battery() {
+    let mut header = BATTERY_HEADER;
+    header[6] = 0;
+    let rom = build_rom(header, 0x8000, 0x2000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    assert!(nes.battery_ram().is_none());
+    assert!(nes.load_battery_ram(&[0; 0x2000]).is_err());
+}
+
+#[test]
+fn ines2_header() {
+    let rom = build_rom(INES2_HEADER, 0x20000, 0, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+    let header = &nes.cartridge().header;
+
+    assert_eq!(header.source, HeaderSource::Ines2);
+    assert_eq!(header.mapper, 1);
+    assert_eq!(header.prg_rom_size, 0x20000);
+    assert_eq!(header.chr_rom_size, None);
+    assert_eq!(header.chr_ram_size, Some(0x2000));
+    assert_eq!(header.prg_ram_size, None);
+    assert_eq!(header.prg_nvram_size, Some(0x2000));
+    assert!(header.battery);
+    assert_eq!(header.region, Region::Ntsc);
+
+    assert_eq!(nes.battery_ram().map(|ram| ram.len()), Some(0x2000));
+}
+
+#[test]
+fn ines2_mapper_submapper() {
+    let mut header = INES2_HEADER;
+    // Mapper 0x101, submapper 5
+    header[8] = 0x51;
+    let rom = build_rom(header, 0x20000, 0, 0xEA);
+
+    assert!(matches!(
+        Nes::new(&rom),
+        Err(NesError::UnSupportedMapper(0x101))
+    ));
+
+    // UxROM, submapper 2 (bus conflicts)
+    header[6] = 0x20;
+    header[8] = 0x20;
+    let rom = build_rom(header, 0x20000, 0, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    assert_eq!(nes.cartridge().header.mapper, 2);
+    assert_eq!(nes.cartridge().header.submapper, 2);
+}
+
+#[test]
+fn ines2_exponent_multiplier_size() {
+    let mut header = INES2_HEADER;
+    // 2^15 * 3 = 96KB of PRG ROM
+    header[4] = (15 << 2) | 1;
+    header[9] = 0x0F;
+    let rom = build_rom(header, 0x18000, 0, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    assert_eq!(nes.cartridge().header.prg_rom_size, 0x18000);
+
+    // The size doesn't fit into the ROM file
+    let rom = build_rom(header, 0x10000, 0, 0xEA);
+    assert!(matches!(Nes::new(&rom), Err(NesError::RomCorrupted)));
+
+    // 2^63 overflows
+    header[4] = 63 << 2;
+    let rom = build_rom(header, 0x10000, 0, 0xEA);
+    assert!(matches!(Nes::new(&rom), Err(NesError::InvalidInesFormat)));
+}
+
+#[test]
+fn ines2_chr_rom_and_misc() {
+    let mut header = INES2_HEADER;
+    header[5] = 2;
+    header[10] = 0x07;
+    header[11] = 0;
+    header[12] = 2;
+    header[15] = 0x01;
+    let rom = build_rom(header, 0x20000, 0x4000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+    let header = &nes.cartridge().header;
+
+    assert_eq!(header.chr_rom_size, Some(0x4000));
+    assert_eq!(header.chr_ram_size, None);
+    assert_eq!(header.prg_ram_size, Some(0x2000));
+    assert_eq!(header.prg_nvram_size, None);
+    assert_eq!(header.region, Region::Multi);
+    assert_eq!(header.expansion, 1);
+}
+
+#[test]
+fn trainer_loaded_at_7000() {
+    let mut header = BATTERY_HEADER;
+    header[6] |= 0b0000_0100;
+
+    let trainer: Vec<u8> = (0..512).map(|i| (i * 3) as u8).collect();
+    let rom = build_rom(header, 0x8000, 0x2000, 0xEA);
+    let rom = [&rom[..16], &trainer, &rom[16..]].concat();
+
+    let mut nes = Nes::new(&rom).unwrap();
+    assert!(nes.cartridge().header.trainer);
+
+    let ram = nes.battery_ram().unwrap();
+    assert_eq!(&ram[0x1000..0x1200], trainer.as_slice());
+
+    // Truncated trainer
+    assert!(matches!(
+        Nes::new(&rom[..16 + 256]),
+        Err(NesError::RomCorrupted)
+    ));
+}
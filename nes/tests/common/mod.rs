@@ -4,6 +4,25 @@ use siphasher::sip::SipHasher13;
 
 use fearless_nes::Nes;
 
+/// Reads a ROM from the tests folder
+#[allow(dead_code)]
+pub fn load_rom(rom_path: &str) -> Vec<u8> {
+    let base_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    fs::read(base_dir + "/tests/" + rom_path).unwrap()
+}
+
+/// Loads a ROM from the tests folder and runs `frames` frames
+#[allow(dead_code)]
+pub fn load_nes(rom_path: &str, frames: u64) -> Nes {
+    let mut nes = Nes::new(&load_rom(rom_path)).unwrap();
+    for _ in 0..frames {
+        nes.run_frame();
+    }
+
+    nes
+}
+
+#[allow(dead_code)]
 pub fn blargg_test(rom_path: &str, pass_text: &str) {
     let base_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let test_path = "/tests/";
@@ -13,11 +32,38 @@ pub fn blargg_test(rom_path: &str, pass_text: &str) {
 
     let mut nes = Nes::new(&rom).expect("error when creating test NES instance");
 
-    let s = nes.run_blargg_test();
+    let s = run_blargg_test(&mut nes);
 
     assert_eq!(s, pass_text);
 }
 
+/// Runs until the test status at $6000 is a result code and returns the text output at $6004
+fn run_blargg_test(nes: &mut Nes) -> String {
+    let mut test_running = false;
+
+    loop {
+        nes.run_cpu_cycle();
+
+        let test_state = nes.peek_cpu(0x6000);
+        if test_state == 0x80 {
+            test_running = true;
+        }
+
+        if test_running && test_state <= 81 {
+            break;
+        }
+    }
+
+    let mut s = String::new();
+    let mut addr = 0x6004;
+    while nes.peek_cpu(addr) != 0 {
+        s.push(nes.peek_cpu(addr) as char);
+        addr += 1;
+    }
+
+    s
+}
+
 #[allow(dead_code)]
 pub fn hash_test(rom_path: &str, frames_to_run: u64, expected_hash: u64) {
     let base_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -37,3 +83,17 @@ pub fn hash_test(rom_path: &str, frames_to_run: u64, expected_hash: u64) {
     hasher.write(nes.frame_buffer());
     assert_eq!(hasher.finish(), expected_hash);
 }
+
+/// NROM-128 with a 16KB PRG ROM mapped to $8000 and $C000
+#[allow(dead_code)]
+pub const NROM_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Builds a ROM image out of an iNES header, filling PRG ROM and CHR ROM with `fill`
+#[allow(dead_code)]
+pub fn build_rom(header: [u8; 16], prg_size: usize, chr_size: usize, fill: u8) -> Vec<u8> {
+    let mut rom = Vec::from(header);
+    rom.resize(16 + prg_size + chr_size, fill);
+    rom
+}
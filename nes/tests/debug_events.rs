@@ -0,0 +1,56 @@
+#![cfg(feature = "debug_tools")]
+
+mod common;
+
+use common::load_nes;
+use fearless_nes::{EventKind, Nes};
+
+/// The (address, scanline) of the events of the last frame
+fn events(nes: &Nes, kind: EventKind) -> Vec<(u16, u16)> {
+    nes.debug_events()
+        .iter()
+        .filter(|e| e.kind == kind)
+        .map(|e| (e.addr, e.scanline))
+        .collect()
+}
+
+#[test]
+fn sprite_events() {
+    let nes = load_nes("ppu/ppu_sprite_hit/rom_singles/01-basics.nes", 7);
+    assert_eq!(events(&nes, EventKind::Sprite0Hit), [(0, 121)]);
+
+    let nes = load_nes("ppu/sprite_overflow_tests/1.Basics.nes", 8);
+    assert_eq!(events(&nes, EventKind::SpriteOverflow), [(0, 128)]);
+    assert_eq!(nes.scanline_sprites(128).len(), 9);
+}
+
+#[test]
+fn register_and_dma_events() {
+    let mut nes = load_nes("apu/apu_test/rom_singles/7-dmc_basics.nes", 6);
+    let apu_writes = events(&nes, EventKind::ApuRegisterWrite);
+    assert!(!apu_writes.is_empty());
+    assert!(apu_writes
+        .iter()
+        .all(|(addr, _)| (0x4000..=0x4017).contains(addr)));
+
+    let dmc_reads = events(&nes, EventKind::DmcDma);
+    assert!(!dmc_reads.is_empty());
+    assert!(dmc_reads.iter().all(|(addr, _)| *addr >= 0x8000));
+
+    let misc = load_nes("cpu/instr_misc/instr_misc.nes", 1);
+    assert!(events(&misc, EventKind::MapperRegisterWrite)
+        .iter()
+        .all(|(addr, _)| *addr >= 0x8000));
+    assert!(!events(&misc, EventKind::PpuRegisterWrite).is_empty());
+
+    let nmi = load_nes("ppu/ppu_vbl_nmi/rom_singles/04-nmi_control.nes", 8);
+    assert!(!events(&nmi, EventKind::Nmi).is_empty());
+
+    // Disabled events aren't recorded
+    nes.set_debug_event_enabled(EventKind::DmcDma, false);
+    assert!(!nes.debug_event_enabled(EventKind::DmcDma));
+    nes.run_frame();
+    nes.run_frame();
+    assert!(!events(&nes, EventKind::PpuRegisterRead).is_empty());
+    assert!(events(&nes, EventKind::DmcDma).is_empty());
+}
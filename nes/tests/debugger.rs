@@ -0,0 +1,141 @@
+#![cfg(feature = "debug_tools")]
+
+mod common;
+
+use fearless_nes::{Access, BreakReason, Breakpoint, Condition, Debugger, Nes};
+
+const PROGRAM: &[(u16, &[u8])] = &[
+    // LDX #$FF; TXS; JSR $8010; LDA #$01; STA $0200
+    (
+        0x8000,
+        &[
+            0xA2, 0xFF, 0x9A, 0x20, 0x10, 0x80, 0xA9, 0x01, 0x8D, 0x00, 0x02,
+        ],
+    ),
+    // JMP $800B
+    (0x800B, &[0x4C, 0x0B, 0x80]),
+    // LDA #$42; JSR $8020; RTS
+    (0x8010, &[0xA9, 0x42, 0x20, 0x20, 0x80, 0x60]),
+    // INX; RTS
+    (0x8020, &[0xE8, 0x60]),
+    // Reset vector
+    (0xFFFC, &[0x00, 0x80]),
+];
+
+fn program_nes() -> Nes {
+    let mut rom = common::build_rom(common::NROM_HEADER, 0x4000, 0x2000, 0xEA);
+    for (addr, bytes) in PROGRAM {
+        let offset = 16 + (*addr as usize & 0x3FFF);
+        rom[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    let mut nes = Nes::new(&rom).unwrap();
+    // Wait for the reset sequence
+    let mut debugger = Debugger::new();
+    debugger.step_into(&mut nes);
+    assert_eq!(nes.cpu_registers().pc, 0x8000);
+
+    nes
+}
+
+#[test]
+fn step_into_and_over() {
+    let mut nes = program_nes();
+    let mut debugger = Debugger::new();
+
+    debugger.step_into(&mut nes);
+    assert_eq!(nes.cpu_registers().pc, 0x8002);
+    debugger.step_into(&mut nes);
+    assert_eq!(nes.cpu_registers().pc, 0x8003);
+    assert_eq!(nes.cpu_registers().sp, 0xFF);
+
+    // Stepping over a JSR runs until the subroutine returns
+    debugger.step_over(&mut nes);
+    assert!(debugger.is_stepping());
+    assert_eq!(debugger.run_frame(&mut nes), Some(BreakReason::Step));
+
+    let regs = nes.cpu_registers();
+    assert_eq!(
+        (regs.pc, regs.sp, regs.a, regs.x),
+        (0x8006, 0xFF, 0x42, 0x00)
+    );
+    assert!(!debugger.is_stepping());
+
+    // Other instructions are just stepped into
+    debugger.step_over(&mut nes);
+    assert_eq!(nes.cpu_registers().pc, 0x8008);
+}
+
+#[test]
+fn step_out() {
+    let mut nes = program_nes();
+    let mut debugger = Debugger::new();
+
+    debugger.breakpoints.push(Breakpoint::execute(0x8020));
+    assert_eq!(
+        debugger.run_frame(&mut nes),
+        Some(BreakReason::Breakpoint {
+            index: 0,
+            addr: 0x8020,
+            access: Access::Execute
+        })
+    );
+    assert_eq!(nes.cpu_registers().sp, 0xFB);
+
+    debugger.breakpoints.clear();
+    debugger.step_out(&nes);
+    assert_eq!(debugger.run_frame(&mut nes), Some(BreakReason::Step));
+    assert_eq!(nes.cpu_registers().pc, 0x8015);
+    assert_eq!(nes.cpu_registers().sp, 0xFD);
+}
+
+#[test]
+fn watchpoints_and_conditions() {
+    let mut nes = program_nes();
+    let mut debugger = Debugger::new();
+
+    debugger
+        .breakpoints
+        .push(Breakpoint::watch(0x0200, 0x02FF, false, true));
+    assert_eq!(
+        debugger.run_frame(&mut nes),
+        Some(BreakReason::Breakpoint {
+            index: 0,
+            addr: 0x0200,
+            access: Access::Write
+        })
+    );
+    // Watchpoints break after the instruction
+    assert_eq!(nes.cpu_registers().pc, 0x800B);
+    assert_eq!(nes.peek_cpu(0x0200), 0x01);
+
+    debugger.breakpoints.clear();
+
+    let mut breakpoint = Breakpoint::execute(0x800B);
+    breakpoint.condition = Some("A == $02".parse().unwrap());
+    debugger.breakpoints.push(breakpoint.clone());
+    assert_eq!(debugger.run_frame(&mut nes), None);
+
+    breakpoint.condition = Some("a>=1".parse().unwrap());
+    debugger.breakpoints[0] = breakpoint;
+    assert!(debugger.run_frame(&mut nes).is_some());
+
+    // Continuing doesn't break on the same instruction again
+    let cycles = nes.cycle_count();
+    assert!(debugger.run_frame(&mut nes).is_some());
+    assert_eq!(nes.cycle_count(), cycles + 3);
+}
+
+#[test]
+fn parse_conditions() {
+    let condition: Condition = "SP != 0x1F".parse().unwrap();
+    assert_eq!(condition.to_string(), "SP != $1F");
+
+    let condition: Condition = "c = 1".parse().unwrap();
+    assert_eq!(condition.to_string(), "C == $1");
+
+    assert!("Q == 1".parse::<Condition>().is_err());
+    assert!("A =< 1".parse::<Condition>().is_err());
+    assert!("A == ".parse::<Condition>().is_err());
+    assert!("A 1".parse::<Condition>().is_err());
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "debug_tools")]
+
+mod common;
+
+use common::load_nes;
+use fearless_nes::{AddressingMode, OPCODES};
+
+#[test]
+fn opcode_table() {
+    assert_eq!(OPCODES.iter().filter(|op| !op.illegal).count(), 151);
+
+    let lda = &OPCODES[0xB1];
+    assert_eq!(
+        (lda.mnemonic, lda.mode, lda.cycles, lda.page_cycle),
+        ("LDA", AddressingMode::IndirectY, 5, true)
+    );
+
+    let dcp = &OPCODES[0xDB];
+    assert_eq!((dcp.mnemonic, dcp.cycles, dcp.illegal), ("DCP", 7, true));
+}
+
+#[test]
+fn disassemble_nestest() {
+    let nes = load_nes("cpu/nestest/nestest.nes", 0);
+
+    let jmp = nes.disassemble(0xC000);
+    assert_eq!(jmp.to_string(), "JMP $C5F5");
+    assert_eq!(jmp.bytes(), [0x4C, 0xF5, 0xC5]);
+    assert_eq!(jmp.target(), Some(0xC5F5));
+
+    let lines: Vec<String> = nes
+        .disassemble_range(0xC5F5, 5)
+        .iter()
+        .map(|i| format!("{:04X} {}", i.addr, i))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "C5F5 LDX #$00",
+            "C5F7 STX $00",
+            "C5F9 STX $10",
+            "C5FB STX $11",
+            "C5FD JSR $C72D"
+        ]
+    );
+
+    let before = nes.disassemble_before(0xC5FD, 2);
+    assert_eq!(
+        before.iter().map(|i| i.addr).collect::<Vec<_>>(),
+        [0xC5F9, 0xC5FB]
+    );
+
+    let labeled = nes
+        .disassemble(0xC5F7)
+        .format_with_labels(|addr| (addr == 0x00).then_some("zp_tmp"));
+    assert_eq!(labeled, "STX zp_tmp");
+}
+
+#[test]
+fn prg_rom_banking() {
+    // NROM-128: the 16KB are mirrored
+    let nes = load_nes("cpu/nestest/nestest.nes", 0);
+    assert_eq!(nes.disassemble(0x8010).prg_offset, Some(0x10));
+    assert_eq!(nes.disassemble(0xC010).prg_offset, Some(0x10));
+    assert_eq!(nes.disassemble(0x0010).prg_offset, None);
+
+    // MMC1 with 64KB of PRG ROM, the last bank is fixed at $C000 on power up
+    let nes = load_nes("cpu/instr_misc/instr_misc.nes", 0);
+    assert_eq!(nes.disassemble(0xC000).prg_offset, Some(0xC000));
+    assert_eq!(nes.disassemble(0x8123).prg_offset, Some(0x0123));
+}
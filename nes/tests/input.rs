@@ -0,0 +1,202 @@
+mod common;
+
+use common::build_rom;
+use fearless_nes::{Button, ExpansionDevice, InputDevice, Nes};
+
+// NROM with a battery, 32KB of PRG ROM and 8KB of CHR ROM
+#[rustfmt::skip]
+const NROM_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0000_0010, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// The same, but with a NES 2.0 header specifying the default expansion device
+#[rustfmt::skip]
+const fn nes20_header(expansion: u8) -> [u8; 16] {
+    [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0000_0010, 0b0000_1000, 0, 0, 0x70, 0, 0, 0, 0, expansion]
+}
+
+// Strobes the controllers and stores 32 reads of $4016 to $6000 and of $4017 to $6020
+#[rustfmt::skip]
+const READ_PORTS_PROGRAM: [u8; 32] = [
+    0xA9, 0x01, 0x8D, 0x16, 0x40, // LDA #1, STA $4016
+    0xA9, 0x00, 0x8D, 0x16, 0x40, // LDA #0, STA $4016
+    0xA2, 0x00, // LDX #0
+    0xAD, 0x16, 0x40, 0x9D, 0x00, 0x60, // LDA $4016, STA $6000,X
+    0xAD, 0x17, 0x40, 0x9D, 0x20, 0x60, // LDA $4017, STA $6020,X
+    0xE8, 0xE0, 0x20, 0xD0, 0xEF, // INX, CPX #32, BNE $E00C
+    0x4C, 0x1D, 0xE0, // JMP $E01D
+];
+
+// Makes the whole picture white and then keeps storing the light sense
+// ($6000 = 1 if the light was ever detected) and the trigger ($6001) of the Zapper on $4017
+#[rustfmt::skip]
+const ZAPPER_PROGRAM: [u8; 56] = [
+    0x2C, 0x02, 0x20, 0x10, 0xFB, // BIT $2002, BPL $E000
+    0x2C, 0x02, 0x20, 0x10, 0xFB, // BIT $2002, BPL $E005
+    0xA9, 0x3F, 0x8D, 0x06, 0x20, // LDA #$3F, STA $2006
+    0xA9, 0x00, 0x8D, 0x06, 0x20, // LDA #0, STA $2006
+    0xA9, 0x30, 0x8D, 0x07, 0x20, // LDA #$30, STA $2007
+    0xA9, 0x00, 0x8D, 0x06, 0x20, 0x8D, 0x06, 0x20, // LDA #0, STA $2006, STA $2006
+    0xAD, 0x17, 0x40, 0x29, 0x08, 0xD0, 0x05, // LDA $4017, AND #$08, BNE $E02D
+    0xA9, 0x01, 0x8D, 0x00, 0x60, // LDA #1, STA $6000
+    0xAD, 0x17, 0x40, 0x29, 0x10, 0x8D, 0x01, 0x60, // LDA $4017, AND #$10, STA $6001
+    0x4C, 0x21, 0xE0, // JMP $E021
+];
+
+fn build_input_rom(header: [u8; 16], program: &[u8]) -> Vec<u8> {
+    let mut rom = build_rom(header, 0x8000, 0x2000, 0xEA);
+
+    let last_bank = 16 + 0x6000;
+    rom[last_bank..last_bank + program.len()].copy_from_slice(program);
+    rom[last_bank + 0x1FFC] = 0x00;
+    rom[last_bank + 0x1FFD] = 0xE0;
+    rom
+}
+
+/// Returns the 32 values of the data line `bit` read from $4016 and $4017
+fn read_ports(nes: &mut Nes, bit: u8) -> (Vec<u8>, Vec<u8>) {
+    nes.run_frame();
+
+    let ram = nes.battery_ram().unwrap();
+    let port_1 = ram[..0x20].iter().map(|b| (b >> bit) & 1).collect();
+    let port_2 = ram[0x20..0x40].iter().map(|b| (b >> bit) & 1).collect();
+    (port_1, port_2)
+}
+
+/// The standard controller report with the `pressed` buttons set
+fn report(pressed: &[Button]) -> Vec<u8> {
+    Button::ALL
+        .iter()
+        .map(|b| pressed.contains(b) as u8)
+        .collect()
+}
+
+#[test]
+fn second_controller_port() {
+    let rom = build_input_rom(NROM_HEADER, &READ_PORTS_PROGRAM);
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.port_device(1), InputDevice::Controller);
+
+    nes.set_button_state(Button::A, true);
+    nes.set_player_button_state(1, Button::Start, true);
+    nes.set_player_button_state(1, Button::Right, true);
+
+    let (port_1, port_2) = read_ports(&mut nes, 0);
+
+    assert_eq!(port_1[..8], report(&[Button::A]));
+    assert_eq!(port_2[..8], report(&[Button::Start, Button::Right]));
+    // Official controllers return 1 after the report
+    assert!(port_1[8..].iter().chain(&port_2[8..]).all(|&b| b == 1));
+}
+
+#[test]
+fn out_of_range_players_and_ports_are_ignored() {
+    let rom = build_input_rom(NROM_HEADER, &READ_PORTS_PROGRAM);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    nes.set_player_button_state(4, Button::A, true);
+    nes.set_port_device(2, InputDevice::Zapper);
+
+    let (port_1, port_2) = read_ports(&mut nes, 0);
+    assert_eq!(port_1[..8], report(&[]));
+    assert_eq!(port_2[..8], report(&[]));
+    assert_eq!(nes.port_device(1), InputDevice::Controller);
+    assert_eq!(nes.port_device(2), InputDevice::Unplugged);
+}
+
+#[test]
+fn four_score() {
+    let rom = build_input_rom(nes20_header(0x02), &READ_PORTS_PROGRAM);
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.port_device(0), InputDevice::FourScore);
+    assert_eq!(nes.port_device(1), InputDevice::FourScore);
+
+    nes.set_player_button_state(0, Button::A, true);
+    nes.set_player_button_state(1, Button::B, true);
+    nes.set_player_button_state(2, Button::Up, true);
+    nes.set_player_button_state(3, Button::Left, true);
+
+    let (port_1, port_2) = read_ports(&mut nes, 0);
+
+    assert_eq!(port_1[..8], report(&[Button::A]));
+    assert_eq!(port_1[8..16], report(&[Button::Up]));
+    assert_eq!(port_1[16..24], [0, 0, 0, 1, 0, 0, 0, 0]);
+
+    assert_eq!(port_2[..8], report(&[Button::B]));
+    assert_eq!(port_2[8..16], report(&[Button::Left]));
+    assert_eq!(port_2[16..24], [0, 0, 1, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn famicom_4_player_adapter() {
+    let rom = build_input_rom(nes20_header(0x03), &READ_PORTS_PROGRAM);
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.expansion_device(), ExpansionDevice::Famicom4p);
+
+    nes.set_player_button_state(2, Button::Select, true);
+    nes.set_player_button_state(3, Button::Down, true);
+
+    // Players 3 and 4 are read from D1
+    let (port_1, port_2) = read_ports(&mut nes, 1);
+
+    assert_eq!(port_1[..8], report(&[Button::Select]));
+    assert_eq!(port_2[..8], report(&[Button::Down]));
+}
+
+#[test]
+fn zapper_light_sense_and_trigger() {
+    let rom = build_input_rom(nes20_header(0x08), &ZAPPER_PROGRAM);
+
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.port_device(1), InputDevice::Zapper);
+
+    nes.set_zapper_state(Some((128, 120)), true);
+    for _ in 0..4 {
+        nes.run_frame();
+    }
+    assert_eq!(&nes.battery_ram().unwrap()[..2], &[1, 0x10]);
+
+    // Aiming off-screen never detects any light
+    let mut nes = Nes::new(&rom).unwrap();
+    nes.set_zapper_state(None, false);
+    for _ in 0..4 {
+        nes.run_frame();
+    }
+    assert_eq!(&nes.battery_ram().unwrap()[..2], &[0, 0]);
+}
+
+#[test]
+fn arkanoid_vaus() {
+    let rom = build_input_rom(nes20_header(0x0F), &READ_PORTS_PROGRAM);
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.port_device(1), InputDevice::ArkanoidVaus);
+
+    // The rightmost position is reported as $F2, inverted and MSB-first
+    nes.set_vaus_state(255, true);
+
+    let (_, knob) = read_ports(&mut nes, 4);
+    assert_eq!(knob[..8], [0, 0, 0, 0, 1, 1, 0, 1]);
+
+    let (_, fire) = read_ports(&mut nes, 3);
+    assert!(fire.iter().all(|&b| b == 1));
+}
+
+#[test]
+fn power_pad() {
+    let rom = build_input_rom(nes20_header(0x0B), &READ_PORTS_PROGRAM);
+    let mut nes = Nes::new(&rom).unwrap();
+    assert_eq!(nes.port_device(1), InputDevice::PowerPad);
+
+    // Buttons 1 and 12
+    nes.set_power_pad_button(0, true);
+    nes.set_power_pad_button(11, true);
+    // The mat doesn't have these
+    nes.set_power_pad_button(12, true);
+    nes.set_power_pad_button(20, true);
+
+    let (_, low) = read_ports(&mut nes, 3);
+    let (_, high) = read_ports(&mut nes, 4);
+
+    assert_eq!(low[..8], [0, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(high[..8], [0, 0, 1, 0, 1, 1, 1, 1]);
+}
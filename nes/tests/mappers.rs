@@ -1,6 +1,63 @@
 mod common;
 
-use common::blargg_test;
+use common::{blargg_test, build_rom};
+use fearless_nes::{MemoryRegion, Nes};
+
+// MMC5 with a battery, 32KB of PRG ROM and 8KB of CHR ROM
+#[rustfmt::skip]
+const MMC5_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0101_0010, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// VRC4b (mapper 25, submapper 1) with a NES 2.0 header, battery-backed PRG RAM
+#[rustfmt::skip]
+const VRC4B_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b1001_0010, 0b0001_1000, 0x10, 0, 0x70, 0, 0, 0, 0, 0,
+];
+
+// VRC4b swaps the A0 and A1 register select lines, so $9001 is the PRG swap mode register
+#[rustfmt::skip]
+const VRC4B_PROGRAM: [u8; 31] = [
+    0xA9, 0x01, 0x8D, 0x00, 0x80, // LDA #1, STA $8000
+    0xAD, 0x00, 0x80, 0x8D, 0x00, 0x60, // LDA $8000, STA $6000
+    0xA9, 0x02, 0x8D, 0x01, 0x90, // LDA #$02, STA $9001
+    0xAD, 0x00, 0xC0, 0x8D, 0x01, 0x60, // LDA $C000, STA $6001
+    0xAD, 0x00, 0x80, 0x8D, 0x02, 0x60, // LDA $8000, STA $6002
+    0x4C, 0x1C, 0xE0, // JMP $E01C
+];
+
+// VRC2a (mapper 22) with an iNES 1.0 header, 32KB of PRG ROM and 8KB of CHR ROM
+#[rustfmt::skip]
+const VRC2A_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0110_0000, 0b0001_0000, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// VRC6a (mapper 24), 32KB of PRG ROM and 8KB of CHR ROM
+#[rustfmt::skip]
+const VRC6_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b1000_0000, 0b0001_0000, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// Sunsoft FME-7 / 5B (mapper 69), 32KB of PRG ROM and 8KB of CHR ROM
+#[rustfmt::skip]
+const SUNSOFT_5B_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0101_0000, 0b0100_0000, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// Namco 163 (mapper 19) with a battery, 32KB of PRG ROM and 8KB of CHR ROM
+#[rustfmt::skip]
+const NAMCO_163_HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0b0011_0010, 0b0001_0000, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// Writes $AB and $CD to the start of the internal sound RAM
+#[rustfmt::skip]
+const NAMCO_163_PROGRAM: [u8; 18] = [
+    0xA9, 0x80, 0x8D, 0x00, 0xF8, // LDA #$80, STA $F800
+    0xA9, 0xAB, 0x8D, 0x00, 0x48, // LDA #$AB, STA $4800
+    0xA9, 0xCD, 0x8D, 0x00, 0x48, // LDA #$CD, STA $4800
+    0x4C, 0x0F, 0xE0, // JMP $E00F
+];
 
 #[test]
 fn mmc3_test_2_1_clocking() {
@@ -36,3 +93,527 @@ fn mmc3_test_2_4_scanline_timing() {
 fn mmc3_test_2_5_mmc3() {
     blargg_test("mappers/mmc3_test_2/5-MMC3.nes", "\n5-MMC3\n\nPassed\n");
 }
+
+#[test]
+fn mmc5_multiplier_and_prg_banking() {
+    // Stores the product of 13 * 21 to $6000 and the first byte of 8KB PRG bank 1 to $6002
+    let program = Program::new()
+        .write(0x5102, 0x02)
+        .write(0x5103, 0x01)
+        .write(0x5205, 13)
+        .write(0x5206, 21)
+        .copy(0x5205, 0x6000)
+        .copy(0x5206, 0x6001)
+        .write(0x5114, 0x81)
+        .copy(0x8000, 0x6002);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    nes.run_frame();
+
+    let ram = nes.battery_ram().unwrap();
+    assert_eq!(&ram[..3], &[0x11, 0x01, 1]);
+}
+
+#[test]
+fn mmc5_scanline_irq() {
+    // Background rendering on, IRQ on scanline 100. The I flag stays set, so the IRQ is only
+    // visible in $5204.
+    let program = Program::new()
+        .wait_vblank()
+        .wait_vblank()
+        .write(0x5203, 100)
+        .write(0x5204, 0x80)
+        .write(0x2001, 0x08);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    for _ in 0..3 {
+        nes.run_frame();
+    }
+
+    run_to_scanline(&mut nes, 50);
+    assert_eq!(nes.peek_cpu(0x5204), 0x40);
+
+    run_to_scanline(&mut nes, 99);
+    assert_eq!(nes.peek_cpu(0x5204), 0x40);
+
+    run_to_scanline(&mut nes, 101);
+    assert_eq!(nes.peek_cpu(0x5204), 0xC0);
+
+    // The PPU doesn't fetch anything in vblank, the "in frame" flag is cleared
+    run_to_scanline(&mut nes, 245);
+    assert_eq!(nes.peek_cpu(0x5204), 0x80);
+}
+
+#[test]
+fn mmc5_rendering_disabled_is_not_in_frame() {
+    let program = Program::new().write(0x5203, 100).write(0x5204, 0x80);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    nes.run_frame();
+
+    run_to_scanline(&mut nes, 120);
+    assert_eq!(nes.peek_cpu(0x5204), 0);
+}
+
+#[test]
+fn mmc5_exram_and_fill_nametables() {
+    /*
+    ExRAM is written in mode 2 (outside of rendering, mode 0 would write zeros),
+    then used as a nametable in mode 0.
+    $2000: ExRAM, $2400: CIRAM page 1, $2800: CIRAM page 0, $2C00: fill mode
+    */
+    let program = Program::new()
+        .write(0x5104, 2)
+        .write(0x5C05, 0x77)
+        .write(0x5FC0, 0x1B)
+        .write(0x5104, 0)
+        .write(0x5C06, 0x55)
+        .write(0x5105, 0b1100_0110)
+        .write(0x5106, 0x99)
+        .write(0x5107, 2);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    nes.run_frame();
+
+    assert_eq!(nes.peek_ppu(0x2005), 0x77);
+    assert_eq!(nes.peek_ppu(0x23C0), 0x1B);
+    // Written in mode 0 while not rendering
+    assert_eq!(nes.peek_ppu(0x2006), 0);
+    // Not readable by the CPU in mode 0
+    assert_ne!(nes.peek_cpu(0x5C05), 0x77);
+
+    nes.poke_ppu(0x2400, 0x12);
+    assert_eq!(nes.peek_memory(MemoryRegion::NametableRam, 0x400), 0x12);
+    nes.poke_ppu(0x2800, 0x34);
+    assert_eq!(nes.peek_memory(MemoryRegion::NametableRam, 0), 0x34);
+
+    // The fill tile and the fill attribute replicated to all 4 quadrants
+    assert_eq!(nes.peek_ppu(0x2C00), 0x99);
+    assert_eq!(nes.peek_ppu(0x2EBF), 0x99);
+    assert_eq!(nes.peek_ppu(0x2FC0), 0xAA);
+    assert_eq!(nes.peek_ppu(0x2FFF), 0xAA);
+}
+
+#[test]
+fn mmc5_exram_modes() {
+    // Mode 3 is read-only and the ExRAM nametable reads as zeros in modes 2 and 3
+    let program = Program::new()
+        .write(0x5104, 2)
+        .write(0x5C05, 0x77)
+        .write(0x5105, 0b10)
+        .write(0x5104, 3)
+        .write(0x5C05, 0x11);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    nes.run_frame();
+
+    assert_eq!(nes.peek_cpu(0x5C05), 0x77);
+    assert_eq!(nes.peek_ppu(0x2005), 0);
+}
+
+#[test]
+fn mmc5_chr_sets_with_8x16_sprites() {
+    // 1KB CHR banks, set A maps bank 3 at $0000, set B maps bank 5 at $0000 and $1000
+    let program = Program::new()
+        .write(0x5101, 3)
+        .write(0x5120, 3)
+        .write(0x5128, 5);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    for addr in 0..0x2000 {
+        nes.poke_memory(MemoryRegion::Chr, addr, (addr / 0x400) as u8);
+    }
+    nes.run_frame();
+
+    // With 8x8 sprites, only set A is used
+    assert_eq!(nes.peek_ppu(0x0000), 3);
+
+    // With 8x16 sprites, the last written set is used outside of rendering
+    let program = Program::new()
+        .write(0x2000, 0x20)
+        .write(0x5101, 3)
+        .write(0x5120, 3)
+        .write(0x5128, 5);
+
+    let mut nes = load_program(MMC5_HEADER, program);
+    for addr in 0..0x2000 {
+        nes.poke_memory(MemoryRegion::Chr, addr, (addr / 0x400) as u8);
+    }
+    nes.run_frame();
+
+    assert_eq!(nes.peek_ppu(0x0000), 5);
+    assert_eq!(nes.peek_ppu(0x1000), 5);
+}
+
+#[test]
+fn mmc5_pulse_isnt_silenced_by_low_periods() {
+    // 50% duty, constant volume 15, period 7 (~14 kHz)
+    let program = Program::new()
+        .write(0x5015, 0x01)
+        .write(0x5000, 0xBF)
+        .write(0x5002, 7)
+        .write(0x5003, 0x08);
+
+    let frequency = expansion_audio_frequency(load_program(MMC5_HEADER, program));
+    assert_frequency(frequency, CPU_CLOCK / (16. * 8.));
+}
+
+#[test]
+fn vrc4_submapper_decoding() {
+    let mut rom = build_rom(VRC4B_HEADER, 0x8000, 0x2000, 0xEA);
+    load_8kb_banked_program(&mut rom, &VRC4B_PROGRAM);
+
+    let mut nes = Nes::new(&rom).unwrap();
+    nes.run_frame();
+
+    let ram = nes.battery_ram().unwrap();
+    assert_eq!(&ram[..3], &[1, 1, 2]);
+}
+
+#[test]
+fn vrc2_latch_with_ines_1_header() {
+    // Without a battery, there's only the 1-bit latch
+    let mut nes = load_program(VRC2A_HEADER, Program::new().write(0x6000, 0xFF));
+    nes.run_frame();
+    assert_eq!(nes.peek_cpu(0x6000), 1);
+    assert_eq!(nes.peek_cpu(0x6FFF), 1);
+
+    // The games with a battery have PRG RAM instead
+    let mut header = VRC2A_HEADER;
+    header[6] |= 0b10;
+    let mut nes = load_program(header, Program::new().write(0x6000, 0xFF));
+    nes.run_frame();
+    assert_eq!(nes.peek_cpu(0x6000), 0xFF);
+    assert_eq!(nes.peek_cpu(0x6FFF), 0);
+}
+
+#[test]
+fn vrc6_pulse_frequency() {
+    // 50% duty, volume 15, period $100
+    let program = Program::new()
+        .write(0x9000, 0x7F)
+        .write(0x9001, 0x00)
+        .write(0x9002, 0x81);
+
+    let frequency = expansion_audio_frequency(load_program(VRC6_HEADER, program));
+    assert_frequency(frequency, CPU_CLOCK / (16. * 257.));
+}
+
+#[test]
+fn vrc6_sawtooth_frequency() {
+    // Accumulator rate $20, period $100
+    let program = Program::new()
+        .write(0xB000, 0x20)
+        .write(0xB001, 0x00)
+        .write(0xB002, 0x81);
+
+    let frequency = expansion_audio_frequency(load_program(VRC6_HEADER, program));
+    assert_frequency(frequency, CPU_CLOCK / (14. * 257.));
+}
+
+#[test]
+fn sunsoft_5b_tone_frequency() {
+    // Channel A: tone enabled, period $100, volume 15
+    let program = Program::new()
+        .write(0xC000, 0x07)
+        .write(0xE000, 0x3E)
+        .write(0xC000, 0x00)
+        .write(0xE000, 0x00)
+        .write(0xC000, 0x01)
+        .write(0xE000, 0x01)
+        .write(0xC000, 0x08)
+        .write(0xE000, 0x0F);
+
+    // The output flips every period of the internal clock (CPU / 16)
+    let frequency = expansion_audio_frequency(load_program(SUNSOFT_5B_HEADER, program));
+    assert_frequency(frequency, CPU_CLOCK / (16. * 2. * 256.));
+}
+
+#[test]
+fn namco_163_internal_ram_is_battery_backed() {
+    let mut rom = build_rom(NAMCO_163_HEADER, 0x8000, 0x2000, 0xEA);
+    load_8kb_banked_program(&mut rom, &NAMCO_163_PROGRAM);
+
+    let mut nes = Nes::new(&rom).unwrap();
+    nes.run_frame();
+
+    // The 128 bytes of internal RAM come after the 8KB of PRG RAM
+    let save = nes.battery_ram().unwrap().to_vec();
+    assert_eq!(save.len(), 0x2000 + 0x80);
+    assert_eq!(&save[0x2000..0x2002], &[0xAB, 0xCD]);
+
+    let mut nes = Nes::new(&rom).unwrap();
+    nes.load_battery_ram(&save).unwrap();
+    assert_eq!(nes.battery_ram(), Some(save.as_slice()));
+}
+
+#[test]
+fn namco_163_sound_ram_reads() {
+    // The address auto-increments on reads too, so $AB ends up in $00 and $CD in $01
+    let program = Program::new()
+        .write(0xF800, 0x80)
+        .write(0x4800, 0xAB)
+        .write(0x4800, 0xCD)
+        .write(0xF800, 0x80)
+        .copy(0x4800, 0x0000)
+        .copy(0x4800, 0x0001);
+
+    let mut nes = load_program(NAMCO_163_HEADER, program);
+    nes.run_frame();
+
+    assert_eq!(nes.peek_cpu(0x0000), 0xAB);
+    assert_eq!(nes.peek_cpu(0x0001), 0xCD);
+}
+
+/// A square wave of 16 4-bit samples at the start of the sound RAM, played by channel 7
+/// with a frequency of $2000 and a volume of 15. Channel 6 is silent.
+fn namco_163_wave_program(enabled_channels: u8) -> Program {
+    let mut program = Program::new().write(0xF800, 0x80);
+    for sample in [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0] {
+        program = program.write(0x4800, sample);
+    }
+
+    // Frequency, phase, length 16, wave address, enabled channels and volume
+    let channel_7 = [0x00, 0x00, 0x20, 0x00, 0xF0, 0x00, 0x00];
+    program = program.write(0xF800, 0x80 | 0x78);
+    for val in channel_7
+        .into_iter()
+        .chain([((enabled_channels - 1) << 4) | 0x0F])
+    {
+        program = program.write(0x4800, val);
+    }
+
+    // The same frequency, with a volume of 0
+    program = program.write(0xF800, 0x80 | 0x70);
+    for val in [0x00, 0x00, 0x20] {
+        program = program.write(0x4800, val);
+    }
+    program
+}
+
+#[test]
+fn namco_163_wavetable_frequency() {
+    // The phase advances by 1/8 of a sample each update, and a channel is updated
+    // every 15 CPU cycles times the number of enabled channels
+    let frequency =
+        expansion_audio_frequency(load_program(NAMCO_163_HEADER, namco_163_wave_program(1)));
+    assert_frequency(frequency, CPU_CLOCK * 0x2000 as f64 / (15. * 65536. * 16.));
+
+    let frequency =
+        expansion_audio_frequency(load_program(NAMCO_163_HEADER, namco_163_wave_program(2)));
+    assert_frequency(
+        frequency,
+        CPU_CLOCK * 0x2000 as f64 / (2. * 15. * 65536. * 16.),
+    );
+}
+
+#[test]
+fn namco_163_enabled_channels() {
+    // The phases are written back to the sound RAM, channel 6 is only updated when
+    // 2 channels are enabled
+    let channel_6_phase = |enabled_channels| {
+        let mut nes = load_program(NAMCO_163_HEADER, namco_163_wave_program(enabled_channels));
+        nes.run_frame();
+        nes.run_frame();
+
+        let ram = &nes.battery_ram().unwrap()[0x2000..];
+        (ram[0x71], ram[0x73], ram[0x75])
+    };
+
+    assert_eq!(channel_6_phase(1), (0, 0, 0));
+    assert_ne!(channel_6_phase(2), (0, 0, 0));
+}
+
+#[test]
+fn namco_163_irq_counter() {
+    // The IRQ handler counts the IRQs in $10 and disables the counter to acknowledge them
+    let program = Program::new()
+        .store(
+            IRQ_HANDLER,
+            &[
+                0xE6, 0x10, // INC $10
+                0xA9, 0x7F, 0x8D, 0x00, 0x58, // LDA #$7F, STA $5800
+                0x40, // RTI
+            ],
+        )
+        .write(0x5000, 0x00)
+        .write(0x5800, 0x80 | 0x70)
+        .cli();
+
+    let mut nes = load_program(NAMCO_163_HEADER, program);
+    nes.run_frame();
+
+    // The 15-bit counter counts up every CPU cycle, and stays at $7FFF
+    assert_eq!(nes.peek_cpu(0x0010), 1);
+    assert_eq!(nes.peek_cpu(0x5000), 0xFF);
+    assert_eq!(nes.peek_cpu(0x5800), 0x7F);
+}
+
+#[test]
+fn namco_163_chr_rom_nametables() {
+    // $2000 uses the 1KB CHR bank 5, $2400 uses the second page of the internal VRAM.
+    // Bank $E0 at $0000 is the internal VRAM, unless it's disabled in $E800.
+    let program = Program::new()
+        .write(0xC000, 5)
+        .write(0xC800, 0xE1)
+        .write(0x8000, 0xE0)
+        .write(0x8800, 0xE0);
+
+    let mut nes = load_program(NAMCO_163_HEADER, program);
+    for addr in 0..0x2000 {
+        nes.poke_memory(MemoryRegion::Chr, addr, (addr / 0x400) as u8);
+    }
+    nes.poke_memory(MemoryRegion::NametableRam, 0x400, 0x42);
+    nes.run_frame();
+
+    assert_eq!(nes.peek_ppu(0x2000), 5);
+    assert_eq!(nes.peek_ppu(0x2400), 0x42);
+    assert_eq!(nes.peek_ppu(0x0000), 0);
+
+    let program = Program::new().write(0x8000, 0xE0).write(0xE800, 0x40);
+
+    let mut nes = load_program(NAMCO_163_HEADER, program);
+    for addr in 0..0x2000 {
+        nes.poke_memory(MemoryRegion::Chr, addr, (addr / 0x400) as u8 + 1);
+    }
+    nes.run_frame();
+
+    // $E0 * 1KB wraps around to the first CHR bank
+    assert_eq!(nes.peek_ppu(0x0000), 1);
+}
+
+/// The NTSC CPU clock rate
+const CPU_CLOCK: f64 = 1789773.;
+
+/// The frequency of the expansion audio in the second frame, the APU channels are silent.
+/// It measures the time between the falls from the top quarter of the range to the bottom
+/// quarter, the band-limited steps ring a little.
+fn expansion_audio_frequency(mut nes: Nes) -> f64 {
+    // High enough for the ultrasonic MMC5 pulses
+    const SAMPLE_RATE: f64 = 192000.;
+    nes.set_sample_rate(SAMPLE_RATE);
+
+    let mut samples = Vec::new();
+
+    nes.run_frame();
+    nes.apu_samples(&mut samples);
+    samples.clear();
+
+    nes.run_frame();
+    nes.apu_samples(&mut samples);
+
+    let min = *samples.iter().min().unwrap() as i32;
+    let max = *samples.iter().max().unwrap() as i32;
+    let low = min + (max - min) / 4;
+    let high = max - (max - min) / 4;
+
+    // The sample indices of the falling edges
+    let mut is_high = false;
+    let mut edges = Vec::new();
+    for (i, &sample) in samples.iter().enumerate() {
+        let sample = sample as i32;
+        if is_high && sample < low {
+            is_high = false;
+            edges.push(i);
+        } else if !is_high && sample > high {
+            is_high = true;
+        }
+    }
+
+    let periods = edges.len() - 1;
+    let seconds = (edges[periods] - edges[0]) as f64 / SAMPLE_RATE;
+    periods as f64 / seconds
+}
+
+fn assert_frequency(frequency: f64, expected: f64) {
+    assert!(
+        (frequency - expected).abs() < expected * 0.01,
+        "{} Hz, expected {} Hz",
+        frequency,
+        expected
+    );
+}
+
+fn run_to_scanline(nes: &mut Nes, scanline: u16) {
+    while nes.ppu_registers().scanline != scanline {
+        nes.run_cpu_cycle();
+    }
+}
+
+/// A program made of register writes and copies, it ends with an infinite loop.
+/// It's loaded at $E000 by load_program.
+struct Program(Vec<u8>);
+
+impl Program {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// LDA #val, STA addr
+    fn write(mut self, addr: u16, val: u8) -> Self {
+        let [lo, hi] = addr.to_le_bytes();
+        self.0.extend([0xA9, val, 0x8D, lo, hi]);
+        self
+    }
+
+    /// LDA from, STA to
+    fn copy(mut self, from: u16, to: u16) -> Self {
+        let [from_lo, from_hi] = from.to_le_bytes();
+        let [to_lo, to_hi] = to.to_le_bytes();
+        self.0.extend([0xAD, from_lo, from_hi, 0x8D, to_lo, to_hi]);
+        self
+    }
+
+    /// Stores the bytes to RAM, one write at a time
+    fn store(mut self, addr: u16, bytes: &[u8]) -> Self {
+        for (i, &byte) in bytes.iter().enumerate() {
+            self = self.write(addr + i as u16, byte);
+        }
+        self
+    }
+
+    /// Enables the IRQs
+    fn cli(mut self) -> Self {
+        self.0.push(0x58);
+        self
+    }
+
+    /// BIT $2002, BPL to the BIT. The PPU ignores the register writes until it has warmed up,
+    /// which takes about 2 frames.
+    fn wait_vblank(mut self) -> Self {
+        self.0.extend([0x2C, 0x02, 0x20, 0x10, 0xFB]);
+        self
+    }
+
+    /// JMP to itself
+    fn build(mut self) -> Vec<u8> {
+        let [lo, hi] = (0xE000 + self.0.len() as u16).to_le_bytes();
+        self.0.extend([0x4C, lo, hi]);
+        self.0
+    }
+}
+
+/// A 32KB PRG ROM, 8KB CHR ROM cartridge running the program
+fn load_program(header: [u8; 16], program: Program) -> Nes {
+    let mut rom = build_rom(header, 0x8000, 0x2000, 0xEA);
+    load_8kb_banked_program(&mut rom, &program.build());
+
+    Nes::new(&rom).unwrap()
+}
+
+/// The IRQ vector points to RAM, so the tests can store their own handler there
+const IRQ_HANDLER: u16 = 0x0300;
+
+/// Fills each 8KB bank of the 32KB PRG ROM with its number and puts the program
+/// and the vectors in the last bank, which is fixed at $E000
+fn load_8kb_banked_program(rom: &mut [u8], program: &[u8]) {
+    for (bank, prg) in rom[16..16 + 0x8000].chunks_mut(0x2000).enumerate() {
+        prg.fill(bank as u8);
+    }
+
+    let last_bank = 16 + 0x6000;
+    rom[last_bank..last_bank + program.len()].copy_from_slice(program);
+    rom[last_bank + 0x1FFC] = 0x00;
+    rom[last_bank + 0x1FFD] = 0xE0;
+    rom[last_bank + 0x1FFE..last_bank + 0x2000].copy_from_slice(&IRQ_HANDLER.to_le_bytes());
+}
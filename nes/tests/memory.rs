@@ -0,0 +1,194 @@
+mod common;
+
+use common::load_nes;
+use fearless_nes::{MemoryRegion, Mirroring, PpuRegisters};
+
+#[test]
+fn peek_has_no_side_effects() {
+    let nes = load_nes("cpu/instr_misc/instr_misc.nes", 60);
+    let state = nes.save_state().unwrap();
+
+    for addr in 0..=0xFFFF {
+        nes.peek_cpu(addr);
+    }
+    for addr in 0..=0x3FFF {
+        nes.peek_ppu(addr);
+    }
+
+    assert_eq!(nes.save_state().unwrap(), state);
+
+    // The internal RAM is mirrored up to $1FFF
+    for addr in 0..0x800 {
+        assert_eq!(nes.peek_cpu(addr), nes.cpu_ram()[addr as usize]);
+        assert_eq!(nes.peek_cpu(addr + 0x1800), nes.cpu_ram()[addr as usize]);
+    }
+}
+
+#[test]
+fn poke_cpu() {
+    let mut nes = load_nes("cpu/instr_misc/instr_misc.nes", 60);
+
+    nes.poke_cpu(0x0812, 0xAB);
+    assert_eq!(nes.cpu_ram()[0x12], 0xAB);
+    assert_eq!(nes.peek_cpu(0x0012), 0xAB);
+
+    nes.poke_cpu(0x6123, 0xCD);
+    assert_eq!(nes.peek_cpu(0x6123), 0xCD);
+
+    // ROM can't be written
+    let rom_byte = nes.peek_cpu(0x8000);
+    nes.poke_cpu(0x8000, !rom_byte);
+    assert_eq!(nes.peek_cpu(0x8000), rom_byte);
+}
+
+#[test]
+fn poke_ppu() {
+    let mut nes = load_nes("cpu/instr_misc/instr_misc.nes", 0);
+
+    nes.poke_ppu(0x2042, 0x12);
+    assert_eq!(nes.peek_ppu(0x2042), 0x12);
+    // The PPU address space is mirrored above $3FFF
+    assert_eq!(nes.peek_ppu(0x6042), 0x12);
+
+    // Palette entries are 6 bits
+    nes.poke_palette(0x10, 0xE1);
+    assert_eq!(nes.peek_palette(0x00), 0x21);
+    assert_eq!(nes.peek_ppu(0x3F10), 0x21);
+    assert_eq!(nes.peek_ppu(0x3F30), 0x21);
+
+    nes.poke_ppu(0x3F05, 0x16);
+    assert_eq!(nes.peek_palette(0x05), 0x16);
+
+    nes.poke_oam(0xFF, 0x34);
+    assert_eq!(nes.peek_oam(0xFF), 0x34);
+}
+
+#[test]
+fn memory_regions() {
+    let mut nes = load_nes("cpu/instr_misc/instr_misc.nes", 0);
+
+    let lens: Vec<usize> = MemoryRegion::ALL
+        .iter()
+        .map(|region| nes.memory_len(*region))
+        .collect();
+    assert_eq!(
+        lens,
+        [0x10000, 0x800, 0x10000, 0x2000, 0x2000, 0x800, 0x100, 0x20]
+    );
+
+    nes.poke_memory(MemoryRegion::InternalRam, 0x12, 0xAB);
+    assert_eq!(nes.peek_cpu(0x1012), 0xAB);
+    nes.poke_memory(MemoryRegion::PrgRam, 0x123, 0xCD);
+    assert_eq!(nes.peek_memory(MemoryRegion::PrgRam, 0x123), 0xCD);
+
+    // The last bank is fixed at $C000, and the ROM can be patched
+    let rom_byte = nes.peek_cpu(0xC010);
+    assert_eq!(nes.peek_memory(MemoryRegion::PrgRom, 0xC010), rom_byte);
+    nes.poke_memory(MemoryRegion::PrgRom, 0xC010, !rom_byte);
+    assert_eq!(nes.peek_cpu(0xC010), !rom_byte);
+
+    nes.poke_memory(MemoryRegion::Chr, 0x1FFF, 0x5A);
+    assert_eq!(nes.peek_ppu(0x1FFF), 0x5A);
+
+    // Horizontal mirroring, $2400 is the same nametable as $2000
+    nes.poke_ppu(0x2400, 0x77);
+    assert_eq!(nes.peek_memory(MemoryRegion::NametableRam, 0), 0x77);
+    nes.poke_memory(MemoryRegion::NametableRam, 0x400, 0x99);
+    assert_eq!(nes.peek_ppu(0x2800), 0x99);
+
+    nes.poke_memory(MemoryRegion::Oam, 0x42, 0x24);
+    assert_eq!(nes.peek_oam(0x42), 0x24);
+    nes.poke_memory(MemoryRegion::Palette, 0x11, 0x16);
+    assert_eq!(nes.peek_palette(0x11), 0x16);
+
+    // Out of range
+    nes.poke_memory(MemoryRegion::Oam, 0x100, 0x24);
+    assert_eq!(nes.peek_memory(MemoryRegion::Palette, 0x20), 0);
+}
+
+#[test]
+fn ppu_viewers() {
+    let mut nes = load_nes("cpu/instr_misc/instr_misc.nes", 0);
+
+    // Tile 0 is empty, tile 1 has a diagonal line of color 3 over color 1
+    for row in 0..8 {
+        nes.poke_ppu(row, 0);
+        nes.poke_ppu(row + 8, 0);
+        nes.poke_ppu(0x10 + row, 0xFF);
+        nes.poke_ppu(0x18 + row, 0x80 >> row);
+    }
+
+    let pixels = nes.tile_pixels(0x10);
+    assert_eq!(&pixels[..10], [3, 1, 1, 1, 1, 1, 1, 1, 1, 3]);
+
+    nes.poke_palette(0x00, 0x0F);
+    for color in 1..4 {
+        nes.poke_palette(0x08 + color, 0x20 + color);
+        nes.poke_palette(0x14 + color, 0x30 + color);
+    }
+
+    let table = nes.pattern_table(0, 5);
+    assert_eq!(table[0], 0x0F);
+    assert_eq!(table[8], 0x33);
+    assert_eq!(table[9], 0x31);
+
+    // Tile 1 with palette 2 in the top-left corner of the first nametable
+    assert_eq!(nes.mirroring(), Mirroring::Horizontal);
+    for addr in 0x2000..0x3000 {
+        nes.poke_ppu(addr, 0);
+    }
+    nes.poke_ppu(0x2000, 1);
+    nes.poke_ppu(0x23C0, 0b10);
+    assert_eq!(nes.attribute_palette(0x2000, 1, 1), 2);
+
+    let nametables = nes.nametables();
+    assert_eq!(nametables[0], 0x23);
+    assert_eq!(nametables[1], 0x21);
+    // The second nametable is a mirror of the first one
+    assert_eq!(nametables[256], 0x23);
+    assert_eq!(nametables[240 * 512], 0x0F);
+
+    // Fine Y 5, second row of nametables, coarse Y 3, coarse X 2
+    let registers = PpuRegisters {
+        temp_vram_addr: (0b101 << 12) | (0b10 << 10) | (3 << 5) | 2,
+        fine_x: 5,
+        ..nes.ppu_registers()
+    };
+    assert_eq!(registers.scroll(), (21, 269));
+}
+
+#[test]
+fn oam_viewer() {
+    let mut nes = load_nes("cpu/instr_misc/instr_misc.nes", 0);
+
+    // Tile 1 has a diagonal line of color 3 over color 1
+    for row in 0..8 {
+        nes.poke_ppu(0x10 + row, 0xFF);
+        nes.poke_ppu(0x18 + row, 0x80 >> row);
+    }
+
+    for addr in 0..=255 {
+        nes.poke_oam(addr, 0xFF);
+    }
+    for sprite in 0..10 {
+        nes.poke_oam(sprite * 4, 20);
+    }
+    nes.poke_oam(13, 1);
+    nes.poke_oam(14, 0b0110_0010);
+    nes.poke_oam(15, 40);
+
+    let entry = nes.oam_entry(3);
+    assert_eq!((entry.y, entry.tile, entry.x), (20, 1, 40));
+    assert_eq!(entry.palette(), 6);
+    assert!(entry.behind_background());
+    assert!(entry.horizontal_flip());
+    assert!(!entry.vertical_flip());
+
+    let pixels = nes.sprite_pixels(&entry);
+    assert_eq!(pixels.len(), 64);
+    assert_eq!(&pixels[..8], [1, 1, 1, 1, 1, 1, 1, 3]);
+
+    assert_eq!(nes.scanline_sprites(20), (0..10).collect::<Vec<u8>>());
+    assert_eq!(nes.scanline_sprites(27).len(), 10);
+    assert!(nes.scanline_sprites(28).is_empty());
+}
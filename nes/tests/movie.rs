@@ -0,0 +1,201 @@
+mod common;
+
+use common::load_rom;
+use fearless_nes::{Button, Fm2, Movie, MovieMode, MoviePlayer, Nes, NesError, ReplayInputs};
+
+/// Runs the movie until `end_frame`, pressing or releasing the buttons on some frames
+fn record(player: &mut MoviePlayer, nes: &mut Nes, end_frame: u64, buttons: &[Button]) {
+    while nes.frame_count() < end_frame {
+        let frame = nes.frame_count();
+        if frame.is_multiple_of(7) {
+            let button = buttons[(frame / 7) as usize % buttons.len()];
+            player.set_button_state(nes, (frame % 2) as usize, button, !frame.is_multiple_of(3));
+        }
+
+        player.run_frame(nes);
+    }
+}
+
+fn play_back(movie: Movie, rom: &[u8]) -> Nes {
+    let (mut player, mut nes) = MoviePlayer::start(movie, rom, MovieMode::ReadOnly).unwrap();
+    while !player.is_finished(&nes) {
+        player.run_frame(&mut nes);
+    }
+
+    nes
+}
+
+#[test]
+fn movie_playback_is_deterministic() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+
+    let movie = Movie::from_power_on(&Nes::new(&rom).unwrap());
+    let (mut player, mut nes) = MoviePlayer::start(movie, &rom, MovieMode::ReadWrite).unwrap();
+    record(
+        &mut player,
+        &mut nes,
+        200,
+        &[Button::A, Button::Left, Button::Start],
+    );
+
+    let recorded_state = nes.save_state().unwrap();
+    let movie = Movie::load(&player.into_movie().save().unwrap()).unwrap();
+    assert_eq!(movie.end_frame(), 200);
+
+    let nes = play_back(movie, &rom);
+    assert_eq!(nes.frame_count(), 200);
+    assert_eq!(nes.save_state().unwrap(), recorded_state);
+}
+
+#[test]
+fn movie_rerecording() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+
+    let mut nes = Nes::new(&rom).unwrap();
+    for _ in 0..30 {
+        nes.run_frame();
+    }
+
+    let movie = Movie::from_savestate(&nes).unwrap();
+    let (mut player, mut nes) = MoviePlayer::start(movie, &rom, MovieMode::ReadWrite).unwrap();
+    record(&mut player, &mut nes, 100, &[Button::B, Button::Up]);
+    let branch = nes.save_state().unwrap();
+    record(&mut player, &mut nes, 150, &[Button::Select]);
+
+    // Going back cuts off the rest of the movie
+    player.load_state(&mut nes, &branch).unwrap();
+    assert_eq!(player.movie().rerecord_count, 1);
+    assert_eq!(player.movie().end_frame(), 100);
+    assert!(player.movie().inputs.inputs.iter().all(|ic| ic.frame < 100));
+
+    record(&mut player, &mut nes, 180, &[Button::Right, Button::Down]);
+    let recorded_state = nes.save_state().unwrap();
+
+    // The read-only mode ignores the player's inputs
+    player.set_mode(&nes, MovieMode::ReadOnly);
+    player.set_button_state(&mut nes, 0, Button::A, true);
+    assert!(player.movie().inputs.inputs.iter().all(|ic| ic.frame < 180));
+
+    let nes = play_back(player.into_movie(), &rom);
+    assert_eq!(nes.frame_count(), 180);
+    assert_eq!(nes.save_state().unwrap(), recorded_state);
+}
+
+#[test]
+fn movie_rom_mismatch() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+    let other_rom = load_rom("cpu/nestest/nestest.nes");
+
+    let movie = Movie::from_power_on(&Nes::new(&rom).unwrap());
+    assert!(matches!(
+        movie.start(&other_rom),
+        Err(NesError::MovieRomMismatch)
+    ));
+}
+
+#[test]
+fn replay_inputs_formats() {
+    let mut inputs = ReplayInputs::new();
+    inputs.add_input_change(10, 1, Button::B, true);
+    let save = inputs.save_with_end_frame(20).unwrap();
+
+    let inputs = ReplayInputs::load_state(&save).unwrap();
+    assert_eq!(inputs.end_frame, 20);
+    assert_eq!(inputs.inputs[0].player, 1);
+    assert!(inputs.inputs[0].button == Button::B);
+
+    // The layout without the header and the players: 2 input changes (frame, button, state)
+    // and the end frame
+    let old_save = [2, 10, 0, 1, 20, 3, 0, 30];
+    let inputs = ReplayInputs::load_state(&old_save).unwrap();
+
+    assert_eq!(inputs.end_frame, 30);
+    assert_eq!(inputs.inputs.len(), 2);
+    assert!(inputs.inputs.iter().all(|ic| ic.player == 0));
+    assert_eq!(inputs.inputs[1].frame, 20);
+    assert!(inputs.inputs[1].button == Button::Start);
+    assert!(!inputs.inputs[1].state);
+}
+
+#[test]
+fn movie_invalid_player() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+
+    let mut movie = Movie::from_power_on(&Nes::new(&rom).unwrap());
+    movie.inputs.add_input_change(5, 4, Button::A, true);
+    let save = movie.save().unwrap();
+
+    assert!(matches!(Movie::load(&save), Err(NesError::InvalidMovie)));
+    assert!(matches!(
+        MoviePlayer::start(movie, &rom, MovieMode::ReadOnly),
+        Err(NesError::InvalidMovie)
+    ));
+}
+
+const FM2_MOVIE: &str = "version 3
+emuVersion 22020
+rerecordCount 42
+palFlag 0
+romFilename instr_misc
+romChecksum base64:AAAAAAAAAAAAAAAAAAAAAA==
+guid 452DE2C3-EF43-2FA9-77AC-0677FC51543B
+fourscore 0
+port0 1
+port1 1
+port2 0
+|1|........|........||
+|0|.......A|........||
+|0|R......A|...U....||
+|0|R.......|........||
+|0|........|........||
+";
+
+#[test]
+fn fm2_import_export() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+    let nes = Nes::new(&rom).unwrap();
+
+    let mut fm2 = Fm2::parse(FM2_MOVIE).unwrap();
+    assert_eq!(fm2.rerecord_count, 42);
+    assert_eq!(fm2.inputs.end_frame, 5);
+
+    let changes: Vec<_> = fm2
+        .inputs
+        .inputs
+        .iter()
+        .map(|ic| (ic.frame, ic.player, ic.button, ic.state))
+        .collect();
+    assert!(
+        changes
+            == [
+                (1, 0, Button::A, true),
+                (2, 0, Button::Right, true),
+                (2, 1, Button::Up, true),
+                (3, 0, Button::A, false),
+                (3, 1, Button::Up, false),
+                (4, 0, Button::Right, false),
+            ]
+    );
+
+    // The checksum has to match the ROM
+    assert!(matches!(
+        Fm2::parse(FM2_MOVIE).unwrap().into_movie(&nes),
+        Err(NesError::MovieRomMismatch)
+    ));
+    fm2.rom_md5 = nes.rom_md5();
+
+    let movie = fm2.into_movie(&nes).unwrap();
+    let mut exported = Fm2::from_movie(&movie, &nes).unwrap();
+    exported.rom_filename = "instr_misc".to_string();
+
+    let text = exported.export();
+    assert!(text.contains("rerecordCount 42\n"));
+    assert!(
+        text.ends_with("|0|R......A|...U....||\n|0|R.......|........||\n|0|........|........||\n")
+    );
+
+    let reimported = Fm2::parse(&text).unwrap();
+    assert_eq!(reimported.rom_md5, nes.rom_md5());
+    assert_eq!(reimported.inputs.end_frame, 5);
+    assert_eq!(reimported.inputs.inputs.len(), changes.len());
+}
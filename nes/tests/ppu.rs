@@ -3,7 +3,7 @@ mod common;
 use common::{blargg_test, hash_test};
 
 //TODO: get read_buffer test working
-//TODO: implement oamtest3 - iNES 2.0 needed
+//TODO: implement oamtest3 - AxROM with 16KB of PRG ROM and 1KB of CHR RAM needed
 
 //TODO: group vbl_nmi tests after passing them
 #[test]
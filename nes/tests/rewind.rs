@@ -0,0 +1,56 @@
+mod common;
+
+use common::load_rom;
+use fearless_nes::{Nes, Rewind};
+
+fn run_frames(nes: &mut Nes, rewind: &mut Rewind, end_frame: u64) {
+    while nes.frame_count() < end_frame {
+        nes.run_frame();
+        rewind.capture(nes).unwrap();
+    }
+}
+
+#[test]
+fn rewind_restores_exact_state() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+
+    let mut nes = Nes::new(&rom).unwrap();
+    let mut states = vec![nes.save_state().unwrap()];
+    for _ in 0..300 {
+        nes.run_frame();
+        states.push(nes.save_state().unwrap());
+    }
+
+    let mut nes = Nes::new(&rom).unwrap();
+    let mut rewind = Rewind::new(2, 100);
+    run_frames(&mut nes, &mut rewind, 120);
+
+    // Goes back to the newest snapshot that is at least 15 frames old
+    assert!(rewind.rewind(&mut nes, 15).unwrap());
+    assert_eq!(nes.frame_count(), 104);
+    assert_eq!(nes.save_state().unwrap(), states[104]);
+
+    // Running forward again gives the same results
+    run_frames(&mut nes, &mut rewind, 300);
+    assert_eq!(nes.save_state().unwrap(), states[300]);
+
+    // There are 150 snapshots, dropping the oldest chunk of 60 would leave less than 100
+    assert!(rewind.rewind(&mut nes, 1000).unwrap());
+    assert_eq!(nes.frame_count(), 2);
+    assert_eq!(nes.save_state().unwrap(), states[2]);
+}
+
+#[test]
+fn rewind_capacity_below_chunk_size() {
+    let rom = load_rom("cpu/instr_misc/instr_misc.nes");
+
+    let mut nes = Nes::new(&rom).unwrap();
+    let mut rewind = Rewind::new(1, 30);
+    run_frames(&mut nes, &mut rewind, 31);
+    assert!(!rewind.is_empty());
+
+    // The chunks of 60 snapshots are dropped as long as 30 snapshots remain
+    run_frames(&mut nes, &mut rewind, 200);
+    assert!(rewind.rewind(&mut nes, 1000).unwrap());
+    assert_eq!(nes.frame_count(), 121);
+}
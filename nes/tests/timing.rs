@@ -0,0 +1,71 @@
+mod common;
+
+use common::build_rom;
+use fearless_nes::{Nes, TimingMode};
+
+// NROM-256 with a NES 2.0 header
+#[rustfmt::skip]
+const HEADER: [u8; 16] = [
+    0x4E, 0x45, 0x53, 0x1A, 2, 1, 0, 0b0000_1000, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// Checks the average number of CPU cycles per frame (with rendering disabled)
+fn cycles_per_frame(nes: &mut Nes, expected: f64) {
+    nes.run_frame();
+    let start = nes.cycle_count();
+
+    for _ in 0..10 {
+        nes.run_frame();
+    }
+
+    let cycles = (nes.cycle_count() - start) as f64;
+    // The frame can only end on instruction boundaries
+    assert!((cycles - expected * 10.).abs() <= 4., "{} cycles", cycles);
+}
+
+#[test]
+fn timing_from_header() {
+    let regions = [
+        (0, TimingMode::Ntsc),
+        (1, TimingMode::Pal),
+        (2, TimingMode::Ntsc),
+        (3, TimingMode::Dendy),
+    ];
+
+    for (region, timing) in regions {
+        let mut header = HEADER;
+        header[12] = region;
+        let rom = build_rom(header, 0x8000, 0x2000, 0xEA);
+        let nes = Nes::new(&rom).unwrap();
+
+        assert_eq!(nes.timing_mode(), timing);
+    }
+}
+
+#[test]
+fn ntsc_frame_length() {
+    let rom = build_rom(HEADER, 0x8000, 0x2000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    cycles_per_frame(&mut nes, 341. * 262. / 3.);
+}
+
+#[test]
+fn pal_frame_length() {
+    let mut header = HEADER;
+    header[12] = 1;
+    let rom = build_rom(header, 0x8000, 0x2000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+
+    cycles_per_frame(&mut nes, 341. * 312. / 3.2);
+}
+
+#[test]
+fn dendy_override() {
+    let rom = build_rom(HEADER, 0x8000, 0x2000, 0xEA);
+    let mut nes = Nes::new(&rom).unwrap();
+    nes.set_timing_mode(TimingMode::Dendy);
+
+    assert_eq!(nes.timing_mode(), TimingMode::Dendy);
+    cycles_per_frame(&mut nes, 341. * 312. / 3.);
+}
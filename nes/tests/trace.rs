@@ -0,0 +1,99 @@
+#![cfg(feature = "debug_tools")]
+
+mod common;
+
+use std::{env, fs, path::PathBuf};
+
+use fearless_nes::{Debugger, Nes, TraceLogger};
+
+const PROGRAM: &[u8] = &[
+    0xA2, 0x02, // LDX #$02
+    0xA0, 0x01, // LDY #$01
+    0xA9, 0x34, // LDA #$34
+    0x85, 0x12, // STA $12
+    0xA9, 0x02, // LDA #$02
+    0x85, 0x13, // STA $13
+    0xA1, 0x10, // LDA ($10,X)
+    0xB1, 0x12, // LDA ($12),Y
+    0xBD, 0x00, 0x03, // LDA $0300,X
+    0xB5, 0x10, // LDA $10,X
+    0xA7, 0x12, // LAX $12
+    0x4A, // LSR A
+    0x6C, 0x12, 0x00, // JMP ($0012)
+];
+
+const EXPECTED: &[&str] = &[
+    "8000  A2 02     LDX #$02                        A:00 X:00 Y:00 P:24 SP:FA PPU:  0, 21 CYC:7",
+    "8002  A0 01     LDY #$01                        A:00 X:02 Y:00 P:24 SP:FA PPU:  0, 27 CYC:9",
+    "8004  A9 34     LDA #$34                        A:00 X:02 Y:01 P:24 SP:FA PPU:  0, 33 CYC:11",
+    "8006  85 12     STA $12 = 00                    A:34 X:02 Y:01 P:24 SP:FA PPU:  0, 39 CYC:13",
+    "8008  A9 02     LDA #$02                        A:34 X:02 Y:01 P:24 SP:FA PPU:  0, 48 CYC:16",
+    "800A  85 13     STA $13 = 00                    A:02 X:02 Y:01 P:24 SP:FA PPU:  0, 54 CYC:18",
+    "800C  A1 10     LDA ($10,X) @ 12 = 0234 = 00    A:02 X:02 Y:01 P:24 SP:FA PPU:  0, 63 CYC:21",
+    "800E  B1 12     LDA ($12),Y = 0234 @ 0235 = 00  A:00 X:02 Y:01 P:26 SP:FA PPU:  0, 81 CYC:27",
+    "8010  BD 00 03  LDA $0300,X @ 0302 = 00         A:00 X:02 Y:01 P:26 SP:FA PPU:  0, 96 CYC:32",
+    "8013  B5 10     LDA $10,X @ 12 = 34             A:00 X:02 Y:01 P:26 SP:FA PPU:  0,108 CYC:36",
+    "8015  A7 12    *LAX $12 = 34                    A:34 X:02 Y:01 P:24 SP:FA PPU:  0,120 CYC:40",
+    "8017  4A        LSR A                           A:34 X:34 Y:01 P:24 SP:FA PPU:  0,129 CYC:43",
+    "8018  6C 12 00  JMP ($0012) = 0234              A:1A X:34 Y:01 P:24 SP:FA PPU:  0,135 CYC:45",
+];
+
+fn trace_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("fearless_nes_{}_{}.log", name, std::process::id()))
+}
+
+#[test]
+fn trace_program() {
+    let mut rom = common::build_rom(common::NROM_HEADER, 0x4000, 0x2000, 0xEA);
+    rom[16..16 + PROGRAM.len()].copy_from_slice(PROGRAM);
+    // Reset vector
+    rom[16 + 0x3FFC..16 + 0x3FFE].copy_from_slice(&[0x00, 0x80]);
+
+    let mut nes = Nes::new(&rom).unwrap();
+    let mut debugger = Debugger::new();
+    // Reset sequence
+    debugger.step_into(&mut nes);
+
+    let path = trace_path("program");
+    debugger.start_trace(TraceLogger::to_file(&path).unwrap());
+    for _ in 0..EXPECTED.len() {
+        debugger.step_into(&mut nes);
+    }
+    debugger.stop_trace().unwrap();
+
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(log.lines().collect::<Vec<_>>(), EXPECTED);
+}
+
+#[test]
+fn trace_nestest_frame() {
+    let mut nes = common::load_nes("cpu/nestest/nestest.nes", 0);
+
+    let path = trace_path("nestest");
+    let mut debugger = Debugger::new();
+    debugger.start_trace(TraceLogger::to_file(&path).unwrap());
+    assert!(debugger.is_active());
+
+    assert_eq!(debugger.run_frame(&mut nes), None);
+    debugger.stop_trace().unwrap();
+    assert!(!debugger.is_tracing());
+
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(
+        lines[0],
+        "C004  78        SEI                             A:00 X:00 Y:00 P:24 SP:FA PPU:  0, 21 CYC:7"
+    );
+    assert_eq!(
+        lines[1],
+        "C005  D8        CLD                             A:00 X:00 Y:00 P:24 SP:FA PPU:  0, 27 CYC:9"
+    );
+    // LDX #$FF; TXS; then the vblank wait loop until the end of the frame
+    assert!(lines[4..]
+        .iter()
+        .all(|line| line.starts_with("C009") || line.starts_with("C00C")));
+}